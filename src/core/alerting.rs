@@ -0,0 +1,353 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+use json::{object, JsonValue};
+use rustls::RootCertStore;
+use url::Url;
+use crate::core::api::{MonitoringMessageListener, OrderListener};
+use crate::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order, OrderStatus};
+use crate::core::risk::DAILY_LOSS_LIMIT_REASON;
+use crate::core::utils::time;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+// the OMS not connected for longer after the first error is alerted
+pub const CONNECT_GRACE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub timestamp: u128,
+    pub severity: Severity,
+    // alerts with the same key are deduplicated
+    pub key: String,
+    pub message: String,
+}
+
+impl Alert {
+    pub fn new(timestamp: u128, severity: Severity, key: String, message: String) -> Self {
+        Self { timestamp, severity, key, message }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        object! {
+            timestamp: self.timestamp.to_string(),
+            severity: format!("{:?}", self.severity),
+            key: self.key.as_str(),
+            message: self.message.as_str(),
+            // slack compatible webhooks
+            text: format!("[{:?}] {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Destination of the alerts, called from the dispatcher thread
+pub trait AlertSink: Send {
+    fn name(&self) -> &str;
+
+    fn send(&mut self, alert: &Alert) -> Result<(), String>;
+}
+
+/// POST of the alert json to the http(s) url
+pub struct WebhookSink {
+    url: Url,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|err| format!("Invalid webhook url {url}: {err}"))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err(format!("Unsupported webhook url {url}"));
+        }
+        Ok(Self { url })
+    }
+
+    // the status line of the response
+    fn exchange(stream: &mut (impl Read + Write), request: &str) -> std::io::Result<String> {
+        stream.write_all(request.as_bytes())?;
+        stream.flush()?;
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status)?;
+        Ok(status)
+    }
+}
+
+impl AlertSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn send(&mut self, alert: &Alert) -> Result<(), String> {
+        let host = self.url.host_str().ok_or("No webhook host")?;
+        let port = self.url.port_or_known_default().ok_or("No webhook port")?;
+        let path = match self.url.query() {
+            Some(query) => format!("{}?{query}", self.url.path()),
+            None => self.url.path().to_string(),
+        };
+        let body = alert.to_json().dump();
+        let request = format!(
+            "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len(),
+        );
+
+        let socket = TcpStream::connect((host, port)).map_err(|err| err.to_string())?;
+        socket.set_read_timeout(Some(WEBHOOK_TIMEOUT)).map_err(|err| err.to_string())?;
+        socket.set_write_timeout(Some(WEBHOOK_TIMEOUT)).map_err(|err| err.to_string())?;
+        let status = match self.url.scheme() {
+            "http" => Self::exchange(&mut { socket }, &request),
+            "https" => {
+                let root_store = RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.into() };
+                let config = rustls::ClientConfig::builder()
+                    .with_root_certificates(root_store)
+                    .with_no_client_auth();
+                let server_name = host.to_string().try_into().map_err(|err| format!("Invalid host {host}: {err}"))?;
+                let connection = rustls::ClientConnection::new(Arc::new(config), server_name).map_err(|err| err.to_string())?;
+                Self::exchange(&mut rustls::StreamOwned::new(connection, socket), &request)
+            }
+            scheme => return Err(format!("Unsupported scheme {scheme}")),
+        }.map_err(|err| err.to_string())?;
+
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(format!("Webhook response: {}", status.trim())),
+        }
+    }
+}
+
+/// Runs the command with the alert in the ALERT_* environment variables
+pub struct CommandSink {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandSink {
+    pub fn new(program: &str, args: Vec<String>) -> Self {
+        Self { program: program.to_string(), args }
+    }
+}
+
+impl AlertSink for CommandSink {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn send(&mut self, alert: &Alert) -> Result<(), String> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .env("ALERT_SEVERITY", format!("{:?}", alert.severity))
+            .env("ALERT_KEY", &alert.key)
+            .env("ALERT_MESSAGE", &alert.message)
+            .env("ALERT_JSON", alert.to_json().dump())
+            .status()
+            .map_err(|err| format!("Can't run {}: {err}", self.program))?;
+        if !status.success() {
+            return Err(format!("{} exited with {status}", self.program));
+        }
+        Ok(())
+    }
+}
+
+/// Appends the alert json lines to the file
+pub struct FileSink {
+    path: String,
+}
+
+impl FileSink {
+    pub fn new(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl AlertSink for FileSink {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn send(&mut self, alert: &Alert) -> Result<(), String> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path).map_err(|err| err.to_string())?;
+        writeln!(file, "{}", alert.to_json().dump()).map_err(|err| err.to_string())
+    }
+}
+
+/// Deduplication by the alert key and the rate limit over all alerts
+pub struct AlertPolicy {
+    dedup_window: Duration,
+    rate_window: Duration,
+    max_per_rate_window: usize,
+    last_sent: HashMap<String, u128>,
+    sent: VecDeque<u128>,
+    // dropped since the last delivered alert
+    suppressed: u64,
+}
+
+impl AlertPolicy {
+    pub fn new(dedup_window: Duration, rate_window: Duration, max_per_rate_window: usize) -> Self {
+        Self {
+            dedup_window,
+            rate_window,
+            max_per_rate_window,
+            last_sent: HashMap::new(),
+            sent: VecDeque::new(),
+            suppressed: 0,
+        }
+    }
+
+    /// The alert to deliver, None for the duplicate or over the rate limit
+    pub fn admit(&mut self, mut alert: Alert) -> Option<Alert> {
+        let ts = alert.timestamp;
+        if let Some(last_ts) = self.last_sent.get(&alert.key) {
+            if ts < last_ts + self.dedup_window.as_nanos() {
+                self.suppressed += 1;
+                return None;
+            }
+        }
+        while self.sent.front().is_some_and(|sent_ts| sent_ts + self.rate_window.as_nanos() <= ts) {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= self.max_per_rate_window {
+            self.suppressed += 1;
+            return None;
+        }
+        self.sent.push_back(ts);
+        self.last_sent.insert(alert.key.clone(), ts);
+        if self.suppressed > 0 {
+            alert.message = format!("{} ({} alerts suppressed)", alert.message, self.suppressed);
+            self.suppressed = 0;
+        }
+        Some(alert)
+    }
+}
+
+/// Turns the monitoring messages and the order errors into alerts, delivered to the sinks by the own thread
+pub struct Alerter {
+    policy: AlertPolicy,
+    // None without the sinks
+    sender: Option<SyncSender<Alert>>,
+    // the OMS error before the first connection isn't the disconnect
+    connected: HashSet<(MonitoringEntity, Exchange)>,
+    // the first error of the OMS not connected yet
+    connecting: HashMap<(MonitoringEntity, Exchange), u128>,
+}
+
+impl Alerter {
+    pub fn new(policy: AlertPolicy, mut sinks: Vec<Box<dyn AlertSink>>) -> Self {
+        let sender = (!sinks.is_empty()).then(|| {
+            let (sender, receiver) = sync_channel::<Alert>(1000);
+            thread::Builder::new().name("alerts".to_string()).spawn(move || {
+                for alert in receiver {
+                    for sink in sinks.iter_mut() {
+                        if let Err(err) = sink.send(&alert) {
+                            log::error!("Alert {} isn't delivered to {}: {err}", alert.key, sink.name());
+                        }
+                    }
+                }
+            }).expect("Failed to spawn alerts thread");
+            sender
+        });
+        Self { policy, sender, connected: HashSet::new(), connecting: HashMap::new() }
+    }
+
+    pub fn alert(&mut self, alert: Alert) {
+        match alert.severity {
+            Severity::Warning => log::warn!("Alert {}: {}", alert.key, alert.message),
+            Severity::Critical => log::error!("Alert {}: {}", alert.key, alert.message),
+        }
+        let (Some(alert), Some(sender)) = (self.policy.admit(alert), &self.sender) else {
+            return;
+        };
+        match sender.try_send(alert) {
+            Ok(_) => {}
+            Err(TrySendError::Full(alert)) => log::error!("Alerts queue is full, {} is dropped", alert.key),
+            Err(TrySendError::Disconnected(alert)) => log::error!("Alerts thread is down, {} is dropped", alert.key),
+        }
+    }
+
+    /// Alerts of the OMS not connected within the grace period since its first error, reported once
+    pub fn connect_alerts(&mut self, ts: u128) -> Vec<Alert> {
+        let overdue = self.connecting.iter()
+            .filter(|(_, since)| **since + CONNECT_GRACE.as_nanos() <= ts)
+            .map(|(group, _)| group.clone())
+            .collect::<Vec<_>>();
+        overdue.into_iter().map(|group| {
+            self.connecting.remove(&group);
+            let exchange = group.1;
+            Alert::new(ts, Severity::Critical, format!("oms:{}", exchange.as_str()), format!("{exchange:?} OMS isn't connected"))
+        }).collect()
+    }
+
+    /// Raise the overdue connection alerts
+    pub fn check(&mut self, ts: u128) {
+        for alert in self.connect_alerts(ts) {
+            self.alert(alert);
+        }
+    }
+
+    pub fn message_alert(&mut self, message: &MonitoringMessage) -> Option<Alert> {
+        let group = (message.entity, message.exchange.clone());
+        if message.status == MonitoringStatus::Ok {
+            self.connecting.remove(&group);
+            self.connected.insert(group);
+            return None;
+        }
+        let exchange = &message.exchange;
+        let (severity, key, text) = match message.entity {
+            MonitoringEntity::OrderManagementSystem => {
+                if !self.connected.remove(&group) {
+                    self.connecting.entry(group).or_insert(message.timestamp);
+                    return None;
+                }
+                (Severity::Critical, "oms", format!("{exchange:?} OMS disconnected"))
+            }
+            MonitoringEntity::AccountUpdate => (Severity::Critical, "account", format!("{exchange:?} account stream failed")),
+            MonitoringEntity::OrderRateLimit => (Severity::Warning, "rate_limit", format!("{exchange:?} orders are throttled")),
+            MonitoringEntity::PriceTicker => (
+                Severity::Warning,
+                "price_ticker",
+                format!("{exchange:?} price ticker stream {}/{} failed", message.connection, message.entity_id),
+            ),
+        };
+        Some(Alert::new(message.timestamp, severity, format!("{key}:{}", exchange.as_str()), text))
+    }
+
+    pub fn order_alert(order: &Order) -> Option<Alert> {
+        if order.status != OrderStatus::Error {
+            return None;
+        }
+        let ts = if order.timestamp > 0 { order.timestamp } else { time() };
+        if order.error.starts_with(DAILY_LOSS_LIMIT_REASON) {
+            return Some(Alert::new(ts, Severity::Critical, format!("loss_limit:{}", order.strategy_id), order.error.clone()));
+        }
+        Some(Alert::new(
+            ts,
+            Severity::Warning,
+            format!("order_error:{}", order.strategy_id),
+            format!("Order {} {:?} {} of {} failed: {}", order.client_order_id, order.side, order.instrument.symbol, order.strategy_id, order.error),
+        ))
+    }
+}
+
+impl MonitoringMessageListener for Alerter {
+    fn on_monitoring_message(&mut self, message: &MonitoringMessage) {
+        if let Some(alert) = self.message_alert(message) {
+            self.alert(alert);
+        }
+    }
+}
+
+impl OrderListener for Alerter {
+    fn on_order(&mut self, order: &Order) {
+        if let Some(alert) = Self::order_alert(order) {
+            self.alert(alert);
+        }
+    }
+}
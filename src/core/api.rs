@@ -1,9 +1,11 @@
 use std::collections::HashMap;
-use std::sync::Arc;
-use crate::core::dto::{Balance, Exchange, Instrument, MonitoringMessage, Order, PriceTicker};
+use json::JsonValue;
+use crate::core::control::StrategyCommand;
+use crate::core::dto::{Balance, Exchange, InstrumentId, InstrumentsUpdate, MonitoringMessage, Order, PriceTicker};
+use crate::core::health::HealthEvent;
 
 pub trait PriceTickerListener {
-    fn on_price_ticker(&mut self, price_ticker: &PriceTicker, tickers_map: &HashMap<Exchange, HashMap<Arc<Instrument>, PriceTicker>>);
+    fn on_price_ticker(&mut self, price_ticker: &PriceTicker, tickers_map: &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>);
 }
 
 
@@ -21,4 +23,19 @@ pub trait MonitoringMessageListener {
     fn on_monitoring_message(&mut self, message: &MonitoringMessage);
 }
 
-pub trait BaseStrategy: PriceTickerListener + OrderListener + BalanceListener + MonitoringMessageListener {}
+pub trait HealthListener {
+    fn on_health_event(&mut self, event: &HealthEvent);
+}
+
+pub trait InstrumentsListener {
+    fn on_instruments_update(&mut self, update: &InstrumentsUpdate);
+}
+
+pub trait ControlListener {
+    fn strategy_id(&self) -> &str;
+
+    /// Apply the command, returns the strategy state
+    fn on_control(&mut self, command: &StrategyCommand) -> Result<JsonValue, String>;
+}
+
+pub trait BaseStrategy: PriceTickerListener + OrderListener + BalanceListener + HealthListener + InstrumentsListener + ControlListener {}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::core::dto::{Exchange, InstrumentId, PriceTicker};
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConnectionStats {
+    // updates delivered first
+    pub wins: u64,
+    // updates already delivered by another connection, the copies dropped by the bus conflation aren't seen
+    pub duplicates: u64,
+}
+
+/// Picks the first copy of the update from the redundant connections
+#[derive(Default)]
+pub struct Arbitrator {
+    latest_update_ids: HashMap<InstrumentId, u64>,
+    latest_exchange_ts: HashMap<InstrumentId, u128>,
+    // connection of the latest taken update
+    sources: HashMap<InstrumentId, (Exchange, usize)>,
+    pub stats: HashMap<(Exchange, usize), ConnectionStats>,
+    next_report_ts: u128,
+}
+
+impl Arbitrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// False if the update with the same or a newer id is already taken.
+    /// Tickers without the update id are ordered by the exchange time if any, duplicated prices are dropped by the filter
+    pub fn accept(&mut self, price_ticker: &PriceTicker) -> bool {
+        if price_ticker.update_id == 0 {
+            if price_ticker.exchange_ts == 0 {
+                return true;
+            }
+            // the same time may be the next update
+            let latest = self.latest_exchange_ts.entry(price_ticker.instrument.id).or_insert(0);
+            if *latest > price_ticker.exchange_ts {
+                return false;
+            }
+            *latest = price_ticker.exchange_ts;
+            self.sources.insert(price_ticker.instrument.id, (price_ticker.instrument.exchange.clone(), price_ticker.connection));
+            return true;
+        }
+        let stats = self.stats
+            .entry((price_ticker.instrument.exchange.clone(), price_ticker.connection))
+            .or_default();
+        let latest = self.latest_update_ids.entry(price_ticker.instrument.id).or_insert(0);
+        if *latest >= price_ticker.update_id {
+            stats.duplicates += 1;
+            return false;
+        }
+        *latest = price_ticker.update_id;
+        stats.wins += 1;
+        self.sources.insert(price_ticker.instrument.id, (price_ticker.instrument.exchange.clone(), price_ticker.connection));
+        true
+    }
+
+    pub fn connection_stats(&self, exchange: &Exchange, connection: usize) -> ConnectionStats {
+        self.stats.get(&(exchange.clone(), connection)).cloned().unwrap_or_default()
+    }
+
+    /// Update ids restart with the new subscription
+    pub fn reset(&mut self) {
+        self.latest_update_ids.clear();
+        self.latest_exchange_ts.clear();
+        self.sources.clear();
+    }
+
+    /// Forget the instruments of the exchange, none of its connections is left
+    pub fn reset_exchange(&mut self, exchange: &Exchange) {
+        self.reset_sources(|(e, _)| e == exchange);
+    }
+
+    /// Forget the instruments last updated by the connection, the other connections keep theirs
+    pub fn reset_connection(&mut self, exchange: &Exchange, connection: usize) {
+        self.reset_sources(|(e, c)| e == exchange && *c == connection);
+    }
+
+    fn reset_sources(&mut self, matches: impl Fn(&(Exchange, usize)) -> bool) {
+        let instruments = self.sources.iter().filter(|(_, source)| matches(source)).map(|(id, _)| *id).collect::<Vec<_>>();
+        for instrument in instruments {
+            self.remove(instrument);
+        }
+    }
+
+    pub fn remove(&mut self, instrument: InstrumentId) {
+        self.latest_update_ids.remove(&instrument);
+        self.latest_exchange_ts.remove(&instrument);
+        self.sources.remove(&instrument);
+    }
+
+    /// Log the connections wins share once in the interval
+    pub fn report(&mut self, ts: u128) {
+        if self.next_report_ts > ts {
+            return;
+        }
+        self.next_report_ts = ts + REPORT_INTERVAL.as_nanos();
+        for ((exchange, connection), stats) in self.stats.iter() {
+            let total = stats.wins + stats.duplicates;
+            if total == 0 {
+                continue;
+            }
+            log::info!(
+                "{exchange:?} connection {connection}: wins {} ({:.1}%), duplicates {}",
+                stats.wins, stats.wins as f64 * 100.0 / total as f64, stats.duplicates,
+            );
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use crossbeam_queue::ArrayQueue;
+use crate::core::dto::{Balance, Exchange, InstrumentId, InstrumentsUpdate, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order, PriceTicker, DTO};
+use crate::core::metrics::{Counter, METRICS};
+
+// the producer retry interval on the full channel
+const BLOCK_BACKOFF: Duration = Duration::from_micros(50);
+
+/// What the push does on the full channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    // the producer waits for the free slot
+    Block,
+    // the pushed message is dropped
+    DropNewest,
+    // the oldest pending message is dropped
+    DropOldest,
+}
+
+/// Wakes the consumer waiting for the messages of any channel sharing it
+#[derive(Default)]
+pub struct Signal {
+    // pushed since the last wait
+    pending: AtomicBool,
+    waiting: AtomicBool,
+    lock: Mutex<()>,
+    condvar: Condvar,
+}
+
+impl Signal {
+    pub fn notify(&self) {
+        self.pending.store(true, Ordering::SeqCst);
+        // the lock is taken only when the consumer sleeps
+        if self.waiting.load(Ordering::SeqCst) {
+            let _guard = self.lock.lock().expect("Can't get the lock");
+            self.condvar.notify_all();
+        }
+    }
+
+    /// Sleep till the notification or the timeout, returns at once if notified since the last wait
+    pub fn wait(&self, timeout: Duration) {
+        let guard = self.lock.lock().expect("Can't get the lock");
+        self.waiting.store(true, Ordering::SeqCst);
+        if !self.pending.swap(false, Ordering::SeqCst) {
+            let _ = self.condvar.wait_timeout(guard, timeout).expect("Can't get the lock");
+        }
+        self.waiting.store(false, Ordering::SeqCst);
+        self.pending.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Bounded multi-producer channel of the topic
+pub struct Channel<T> {
+    name: String,
+    queue: Arc<ArrayQueue<T>>,
+    policy: OverflowPolicy,
+    signal: Arc<Signal>,
+    // pushes which found the channel full
+    overflows: Counter,
+}
+
+impl<T: Send + 'static> Channel<T> {
+    pub fn new(name: &str, capacity: usize, policy: OverflowPolicy, signal: Arc<Signal>) -> Self {
+        let queue = Arc::new(ArrayQueue::new(capacity));
+        let queue_ref = Arc::clone(&queue);
+        METRICS.gauge_fn("arbot_queue_depth", "Messages waiting in the queue", &[("queue", name)], move || queue_ref.len() as f64);
+        Self {
+            name: name.to_string(),
+            queue,
+            policy,
+            signal,
+            overflows: METRICS.counter("arbot_bus_overflows_total", "Pushes to the full bus channel", &[("topic", name)]),
+        }
+    }
+
+    /// False if the message or the oldest pending one is dropped
+    pub fn push(&self, message: T) -> bool {
+        let mut delivered = true;
+        match self.queue.push(message) {
+            Ok(_) => {}
+            Err(message) => {
+                self.overflows.inc();
+                match self.policy {
+                    OverflowPolicy::Block => {
+                        let mut message = message;
+                        while let Err(back) = self.queue.push(message) {
+                            message = back;
+                            thread::sleep(BLOCK_BACKOFF);
+                        }
+                    }
+                    OverflowPolicy::DropNewest => {
+                        log::warn!("Channel {} is full, the message is dropped", self.name);
+                        return false;
+                    }
+                    OverflowPolicy::DropOldest => {
+                        delivered = self.queue.force_push(message).is_none();
+                    }
+                }
+            }
+        }
+        self.signal.notify();
+        delivered
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        self.queue.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn overflows(&self) -> u64 {
+        self.overflows.get()
+    }
+}
+
+#[derive(Default)]
+struct PendingTickers {
+    tickers: HashMap<InstrumentId, PriceTicker>,
+    // instruments in the order of the first pending update
+    order: VecDeque<InstrumentId>,
+}
+
+/// Tickers pending consumption, the update of the pending instrument is merged into it.
+/// The consumer falling behind gets the latest prices instead of the backlog
+pub struct TickerChannel {
+    pending: Arc<Mutex<PendingTickers>>,
+    signal: Arc<Signal>,
+    conflated: HashMap<Exchange, Counter>,
+}
+
+impl TickerChannel {
+    pub fn new(signal: Arc<Signal>) -> Self {
+        let pending: Arc<Mutex<PendingTickers>> = Arc::default();
+        let pending_ref = Arc::clone(&pending);
+        METRICS.gauge_fn("arbot_queue_depth", "Messages waiting in the queue", &[("queue", "tickers")], move || {
+            pending_ref.lock().expect("Can't get the lock").order.len() as f64
+        });
+        let conflated = Exchange::iterator()
+            .map(|exchange| (exchange.clone(), METRICS.counter(
+                "arbot_tickers_conflated_total",
+                "Price tickers merged into the pending update of the instrument",
+                &[("exchange", exchange.as_str())],
+            )))
+            .collect();
+        Self { pending, signal, conflated }
+    }
+
+    /// False if the update is older than the pending one and dropped
+    pub fn push(&self, ticker: PriceTicker) -> bool {
+        let mut pending = self.pending.lock().expect("Can't get the lock");
+        match pending.tickers.get_mut(&ticker.instrument.id) {
+            Some(pending_ticker) => {
+                // the redundant connection copy or the late update
+                let stale = if ticker.update_id != 0 {
+                    pending_ticker.update_id >= ticker.update_id
+                } else {
+                    ticker.exchange_ts != 0 && pending_ticker.exchange_ts > ticker.exchange_ts
+                };
+                if stale {
+                    return false;
+                }
+                if let Some(counter) = self.conflated.get(&ticker.instrument.exchange) {
+                    counter.inc();
+                }
+                // keeps the side not changed by the partial update
+                pending_ticker.update(&ticker);
+            }
+            None => {
+                pending.order.push_back(ticker.instrument.id);
+                pending.tickers.insert(ticker.instrument.id, ticker);
+            }
+        }
+        drop(pending);
+        self.signal.notify();
+        true
+    }
+
+    pub fn pop(&self) -> Option<PriceTicker> {
+        let mut pending = self.pending.lock().expect("Can't get the lock");
+        let id = pending.order.pop_front()?;
+        pending.tickers.remove(&id)
+    }
+
+    /// Instruments with the pending update
+    pub fn len(&self) -> usize {
+        self.pending.lock().expect("Can't get the lock").order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove the pending tickers of the exchange, in the pending order
+    pub fn take_exchange(&self, exchange: &Exchange) -> Vec<PriceTicker> {
+        let mut pending = self.pending.lock().expect("Can't get the lock");
+        let PendingTickers { tickers, order } = &mut *pending;
+        let mut taken = vec![];
+        order.retain(|id| match tickers.get(id) {
+            Some(ticker) if &ticker.instrument.exchange == exchange => {
+                taken.extend(tickers.remove(id));
+                false
+            }
+            _ => true,
+        });
+        taken
+    }
+
+    pub fn conflated(&self, exchange: &Exchange) -> u64 {
+        self.conflated.get(exchange).map_or(0, Counter::get)
+    }
+}
+
+/// Channels of the messages consumed by the main loop, one per DTO topic
+pub struct EventBus {
+    pub tickers: TickerChannel,
+    pub orders: Channel<Order>,
+    pub balances: Channel<Balance>,
+    pub monitoring: Channel<MonitoringMessage>,
+    pub instruments: Channel<InstrumentsUpdate>,
+    // tickers pending before the stream error of their exchange followed by the error
+    held: Mutex<VecDeque<DTO>>,
+    signal: Arc<Signal>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let signal = Arc::new(Signal::default());
+        Self {
+            tickers: TickerChannel::new(Arc::clone(&signal)),
+            orders: Channel::new("order_updates", capacity, OverflowPolicy::Block, Arc::clone(&signal)),
+            balances: Channel::new("balances", capacity, OverflowPolicy::Block, Arc::clone(&signal)),
+            monitoring: Channel::new("monitoring", capacity, OverflowPolicy::Block, Arc::clone(&signal)),
+            instruments: Channel::new("instruments", capacity, OverflowPolicy::Block, Arc::clone(&signal)),
+            held: Mutex::new(VecDeque::new()),
+            signal,
+        }
+    }
+
+    /// Shared by the other channels waking the same consumer
+    pub fn signal(&self) -> Arc<Signal> {
+        Arc::clone(&self.signal)
+    }
+
+    /// Push to the channel of the message topic
+    pub fn publish(&self, dto: DTO) -> bool {
+        match dto {
+            DTO::PriceTicker(ticker) => self.tickers.push(ticker),
+            DTO::Order(order) => self.orders.push(order),
+            DTO::Balance(balance) => self.balances.push(balance),
+            DTO::MonitoringMessage(message) => self.monitoring.push(message),
+            DTO::InstrumentsUpdate(update) => self.instruments.push(update),
+        }
+    }
+
+    /// The next message, the tickers go after the other topics.
+    /// The tickers pending before the stream error go ahead of it, the error handler clears the prices
+    pub fn next(&self) -> Option<DTO> {
+        let mut held = self.held.lock().expect("Can't get the lock");
+        if let Some(dto) = held.pop_front() {
+            return Some(dto);
+        }
+        if let Some(order) = self.orders.pop() {
+            return Some(DTO::Order(order));
+        }
+        if let Some(message) = self.monitoring.pop() {
+            if message.entity == MonitoringEntity::PriceTicker && message.status == MonitoringStatus::Error {
+                held.extend(self.tickers.take_exchange(&message.exchange).into_iter().map(DTO::PriceTicker));
+                held.push_back(DTO::MonitoringMessage(message));
+                return held.pop_front();
+            }
+            return Some(DTO::MonitoringMessage(message));
+        }
+        if let Some(update) = self.instruments.pop() {
+            return Some(DTO::InstrumentsUpdate(update));
+        }
+        if let Some(balance) = self.balances.pop() {
+            return Some(DTO::Balance(balance));
+        }
+        self.tickers.pop().map(DTO::PriceTicker)
+    }
+
+    pub fn len(&self) -> usize {
+        let held = self.held.lock().expect("Can't get the lock").len();
+        held + self.tickers.len() + self.orders.len() + self.balances.len() + self.monitoring.len() + self.instruments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sleep till the next publish or the timeout
+    pub fn wait(&self, timeout: Duration) {
+        self.signal.wait(timeout);
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use json::{object, JsonValue};
+use crate::core::bus::Channel;
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Exchange, InstrumentId, PriceTicker};
+use crate::core::http::{self, HttpResponse};
+use crate::core::utils::{decimal_to_json, json_to_decimal};
+
+// the main loop may be busy with the tickers burst
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrategyCommand {
+    State,
+    // no new paths are entered, the path in progress is finished
+    Pause,
+    Resume,
+    MonitoringOnly(bool),
+    Sizing { min_order_size: Decimal, max_order_size: Decimal },
+    Graph,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlCommand {
+    Strategies,
+    Strategy(String, StrategyCommand),
+    // blocks the new orders and cancels the open ones
+    KillSwitch(bool),
+    Tickers,
+    Health,
+}
+
+pub type ControlReply = Result<JsonValue, String>;
+
+/// Command executed by the main loop, the result is sent back to the API thread
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    reply: mpsc::Sender<ControlReply>,
+}
+
+impl ControlRequest {
+    pub fn new(command: ControlCommand) -> (Self, mpsc::Receiver<ControlReply>) {
+        let (reply, receiver) = mpsc::channel();
+        (Self { command, reply }, receiver)
+    }
+
+    pub fn reply(&self, result: ControlReply) {
+        // the API thread may be gone after the timeout
+        let _ = self.reply.send(result);
+    }
+}
+
+fn parse_body(body: &str) -> Result<JsonValue, String> {
+    json::parse(body).map_err(|err| format!("Invalid json body: {err}"))
+}
+
+fn parse_bool(body: &str, field: &str) -> Result<bool, String> {
+    parse_body(body)?[field].as_bool().ok_or(format!("Missing '{field}' bool field"))
+}
+
+fn parse_positive_decimal(data: &JsonValue, field: &str) -> Result<Decimal, String> {
+    json_to_decimal(&data[field])
+        .filter(|value| *value > Decimal::ZERO)
+        .ok_or(format!("Missing '{field}' positive decimal field"))
+}
+
+/// The command of the route, None for the unknown one
+///
+/// - `GET /strategies`, `GET /strategies/{id}`, `GET /strategies/{id}/graph`
+/// - `POST /strategies/{id}/pause`, `POST /strategies/{id}/resume`
+/// - `POST /strategies/{id}/monitoring_only` `{"enabled": true}`
+/// - `POST /strategies/{id}/sizing` `{"min_order_size": "20", "max_order_size": "30"}`
+/// - `POST /kill_switch` `{"active": true}`
+/// - `GET /tickers`, `GET /health`
+pub fn parse_command(method: &str, path: &str, body: &str) -> Result<Option<ControlCommand>, String> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let command = match (method, segments.as_slice()) {
+        ("GET", ["strategies"]) => ControlCommand::Strategies,
+        ("GET", ["tickers"]) => ControlCommand::Tickers,
+        ("GET", ["health"]) => ControlCommand::Health,
+        ("POST", ["kill_switch"]) => ControlCommand::KillSwitch(parse_bool(body, "active")?),
+        (_, ["strategies", id, rest @ ..]) => {
+            let command = match (method, rest) {
+                ("GET", []) => StrategyCommand::State,
+                ("GET", ["graph"]) => StrategyCommand::Graph,
+                ("POST", ["pause"]) => StrategyCommand::Pause,
+                ("POST", ["resume"]) => StrategyCommand::Resume,
+                ("POST", ["monitoring_only"]) => StrategyCommand::MonitoringOnly(parse_bool(body, "enabled")?),
+                ("POST", ["sizing"]) => {
+                    let data = parse_body(body)?;
+                    let min_order_size = parse_positive_decimal(&data, "min_order_size")?;
+                    let max_order_size = parse_positive_decimal(&data, "max_order_size")?;
+                    if min_order_size > max_order_size {
+                        return Err(format!("Min order size {min_order_size} is above the max {max_order_size}"));
+                    }
+                    StrategyCommand::Sizing { min_order_size, max_order_size }
+                }
+                _ => return Ok(None),
+            };
+            ControlCommand::Strategy(id.to_string(), command)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(command))
+}
+
+fn error_response(status: &'static str, error: String) -> HttpResponse {
+    HttpResponse::json(status, object! { error: error }.dump())
+}
+
+/// Serve the control API on the address, the commands are pushed to the channel
+pub fn serve(address: &str, channel: Arc<Channel<ControlRequest>>) -> SocketAddr {
+    http::serve("control", address, move |request| {
+        let command = match parse_command(&request.method, &request.path, &request.body) {
+            Ok(Some(command)) => command,
+            Ok(None) => return HttpResponse::not_found(),
+            Err(err) => return error_response("400 Bad Request", err),
+        };
+        log::info!("Control command: {command:?}");
+        let (request, receiver) = ControlRequest::new(command);
+        if !channel.push(request) {
+            return error_response("503 Service Unavailable", "Control queue is full".to_string());
+        }
+        match receiver.recv_timeout(REPLY_TIMEOUT) {
+            Ok(Ok(value)) => HttpResponse::json("200 OK", value.dump()),
+            Ok(Err(err)) => error_response("400 Bad Request", err),
+            Err(_) => error_response("504 Gateway Timeout", "No reply from the engine".to_string()),
+        }
+    })
+}
+
+pub fn tickers_to_json(tickers_map: &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>) -> JsonValue {
+    let mut data = JsonValue::new_object();
+    for (exchange, tickers) in tickers_map {
+        let mut exchange_data = JsonValue::new_object();
+        for ticker in tickers.values() {
+            exchange_data[ticker.instrument.symbol.as_str()] = object! {
+                bid: decimal_to_json(ticker.bid),
+                bid_amount: decimal_to_json(ticker.bid_amount),
+                ask: decimal_to_json(ticker.ask),
+                ask_amount: decimal_to_json(ticker.ask_amount),
+                timestamp: ticker.timestamp.to_string(),
+                exchange_ts: ticker.exchange_ts.to_string(),
+            };
+        }
+        data[exchange.as_str()] = exchange_data;
+    }
+    data
+}
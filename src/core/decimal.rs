@@ -0,0 +1,313 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+use crate::core::utils::RoundingMode;
+
+// max digits after the point, more precise results are rounded
+const MAX_SCALE: u32 = 18;
+// significant digits of the division result, within the max scale
+const DIV_PRECISION: i64 = 18;
+
+/// Fixed-point decimal number: `mantissa * 10^-scale`
+#[derive(Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseDecimalError(pub String);
+
+fn pow10(exp: u32) -> Option<i128> {
+    10_i128.checked_pow(exp)
+}
+
+// decimal digits of the mantissa
+fn digits(mantissa: i128) -> i64 {
+    mantissa.unsigned_abs().checked_ilog10().map_or(0, |d| d as i64 + 1)
+}
+
+// integer division rounded half away from zero
+fn div_half_up(n: i128, d: i128) -> i128 {
+    let q = n / d;
+    let r = n % d;
+    if r.abs() * 2 >= d.abs() {
+        if (n < 0) == (d < 0) { q + 1 } else { q - 1 }
+    } else {
+        q
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal { mantissa: 0, scale: 0 };
+    pub const ONE: Decimal = Decimal { mantissa: 1, scale: 0 };
+
+    pub const fn new(mantissa: i128, scale: u32) -> Self {
+        Self { mantissa, scale }
+    }
+
+    pub fn from_f64(value: f64, scale: u32) -> Self {
+        let scale = scale.min(MAX_SCALE);
+        Self { mantissa: (value * 10_f64.powi(scale as i32)).round() as i128, scale }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10_f64.powi(self.scale as i32)
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn abs(&self) -> Self {
+        Self { mantissa: self.mantissa.abs(), scale: self.scale }
+    }
+
+    /// Strip trailing zeros
+    pub fn normalize(&self) -> Self {
+        let mut result = *self;
+        while result.scale > 0 && result.mantissa % 10 == 0 {
+            result.mantissa /= 10;
+            result.scale -= 1;
+        }
+        result
+    }
+
+    fn upscale(&self, scale: u32) -> Option<i128> {
+        if self.mantissa == 0 {
+            return Some(0);
+        }
+        pow10(scale - self.scale).and_then(|p| self.mantissa.checked_mul(p))
+    }
+
+    fn rescale_half_up(&self, scale: u32) -> Self {
+        if scale >= self.scale {
+            return Self { mantissa: self.upscale(scale).expect("Decimal overflow"), scale };
+        }
+        // the divisor beyond i128 rounds to zero
+        let mantissa = pow10(self.scale - scale).map_or(0, |d| div_half_up(self.mantissa, d));
+        Self { mantissa, scale }
+    }
+
+    /// Round to the `scale` digits after the point: `Up` towards +inf, `Down` towards -inf
+    pub fn round(&self, scale: u32, rounding_mode: RoundingMode) -> Self {
+        if scale >= self.scale {
+            return Self { mantissa: self.upscale(scale).expect("Decimal overflow"), scale };
+        }
+        let mantissa = match (pow10(self.scale - scale), rounding_mode) {
+            (Some(d), RoundingMode::Up) => self.mantissa.div_euclid(d) + if self.mantissa.rem_euclid(d) > 0 { 1 } else { 0 },
+            (Some(d), RoundingMode::Down) => self.mantissa.div_euclid(d),
+            // less than a unit of the scale
+            (None, RoundingMode::Up) => (self.mantissa > 0) as i128,
+            (None, RoundingMode::Down) => -((self.mantissa < 0) as i128),
+        };
+        Self { mantissa, scale }
+    }
+
+    fn align(&self, other: &Self) -> (i128, i128, u32) {
+        let scale = self.scale.max(other.scale);
+        (
+            self.upscale(scale).expect("Decimal overflow"),
+            other.upscale(scale).expect("Decimal overflow"),
+            scale,
+        )
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseDecimalError(s.to_string());
+        let (number, exp) = match s.find(['e', 'E']) {
+            Some(pos) => (&s[..pos], s[pos + 1..].parse::<i32>().map_err(|_| err())?),
+            None => (s, 0),
+        };
+        let (negative, number) = match number.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, number.strip_prefix('+').unwrap_or(number)),
+        };
+        let (int_part, frac_part) = number.split_once('.').unwrap_or((number, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(err());
+        }
+
+        let mut mantissa: i128 = 0;
+        for c in int_part.chars().chain(frac_part.chars()) {
+            let digit = c.to_digit(10).ok_or_else(err)?;
+            mantissa = mantissa.checked_mul(10)
+                .and_then(|m| m.checked_add(digit as i128))
+                .ok_or_else(err)?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+
+        let scale = frac_part.len() as i64 - exp as i64;
+        if scale < 0 {
+            let factor = u32::try_from(-scale).ok().and_then(pow10).ok_or_else(err)?;
+            return Ok(Self { mantissa: mantissa.checked_mul(factor).ok_or_else(err)?, scale: 0 });
+        }
+        if scale > MAX_SCALE as i64 {
+            // the digits beyond the max scale are rounded
+            let mantissa = u32::try_from(scale - MAX_SCALE as i64).ok()
+                .and_then(pow10)
+                .map_or(0, |d| div_half_up(mantissa, d));
+            return Ok(Self { mantissa, scale: MAX_SCALE });
+        }
+        Ok(Self { mantissa, scale: scale as u32 })
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let abs = self.mantissa.unsigned_abs();
+        if self.scale == 0 {
+            return write!(f, "{sign}{abs}");
+        }
+        let p = 10_u128.pow(self.scale);
+        write!(f, "{sign}{}.{:0width$}", abs / p, abs % p, width = self.scale as usize)
+    }
+}
+
+impl fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.normalize(), other.normalize());
+        let scale = a.scale.max(b.scale);
+        match (a.upscale(scale), b.upscale(scale)) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            // the upscaled operand is beyond i128, so it's larger in magnitude
+            (None, _) => if a.mantissa > 0 { Ordering::Greater } else { Ordering::Less },
+            (_, None) => if b.mantissa > 0 { Ordering::Less } else { Ordering::Greater },
+        }
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Self::Output {
+        Self { mantissa: -self.mantissa, scale: self.scale }
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let (a, b, scale) = self.align(&rhs);
+        Self { mantissa: a.checked_add(b).expect("Decimal overflow"), scale }
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let (a, b, scale) = self.align(&rhs);
+        Self { mantissa: a.checked_sub(b).expect("Decimal overflow"), scale }
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (mut a, mut b) = (self.normalize(), rhs.normalize());
+        loop {
+            if let Some(mantissa) = a.mantissa.checked_mul(b.mantissa) {
+                let result = Self { mantissa, scale: a.scale + b.scale };
+                if result.scale > MAX_SCALE {
+                    return result.rescale_half_up(MAX_SCALE).normalize();
+                }
+                return result;
+            }
+            // drop the least significant digit of the more precise operand
+            if a.scale == 0 && b.scale == 0 {
+                panic!("Decimal overflow: {self} * {rhs}");
+            }
+            if a.scale >= b.scale {
+                a = a.rescale_half_up(a.scale - 1);
+            } else {
+                b = b.rescale_half_up(b.scale - 1);
+            }
+        }
+    }
+}
+
+impl Div for Decimal {
+    type Output = Decimal;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        assert!(!rhs.is_zero(), "Decimal division by zero: {self} / {rhs}");
+        let (a, mut b) = (self.normalize(), rhs.normalize());
+        // integer digits of the quotient, one more at most
+        let magnitude = (digits(a.mantissa) - a.scale as i64) - (digits(b.mantissa) - b.scale as i64);
+        let mut scale = (DIV_PRECISION - magnitude).clamp(0, MAX_SCALE as i64) as u32;
+        loop {
+            // a * 10^(scale + b.scale - a.scale) / b
+            let exp = scale as i64 + b.scale as i64 - a.scale as i64;
+            let mantissa = if exp >= 0 {
+                pow10(exp as u32).and_then(|p| a.mantissa.checked_mul(p)).map(|n| div_half_up(n, b.mantissa))
+            } else {
+                // the divisor beyond i128 rounds the quotient to zero
+                Some(pow10(-exp as u32).and_then(|p| b.mantissa.checked_mul(p)).map_or(0, |d| div_half_up(a.mantissa, d)))
+            };
+            match mantissa {
+                Some(mantissa) => return Self { mantissa, scale }.normalize(),
+                None if b.scale > 0 => b = b.rescale_half_up(b.scale - 1),
+                None if scale > 0 => scale -= 1,
+                None => panic!("Decimal overflow: {self} / {rhs}"),
+            }
+        }
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Decimal {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Decimal::ZERO, |acc, x| acc + x)
+    }
+}
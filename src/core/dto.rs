@@ -1,24 +1,29 @@
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::slice::Iter;
+use std::str::FromStr;
 use std::sync::{Arc, LazyLock};
-use crate::core::dto::Exchange::{Binance, Bit2me, Mexc};
+use crate::core::decimal::Decimal;
+use crate::core::dto::Exchange::{Binance, Bit2me, Bybit, Kraken, Mexc, Okx};
 
 static BLANK_STR: LazyLock<String> = LazyLock::new(|| "".to_string());
-pub const TICKER_PRICE_NOT_CHANGED: f64 = -100.;
+pub const TICKER_PRICE_NOT_CHANGED: Decimal = Decimal::new(-100, 0);
+// precision of the fee multipliers
+const FEE_SCALE: u32 = 8;
 
 
 static BLANK_INSTRUMENT: LazyLock<Arc<Instrument>> = LazyLock::new(|| Arc::new(Instrument {
+    id: InstrumentId::default(),
     exchange: Exchange::Any,
     symbol: BLANK_STR.clone(),
     base: BLANK_STR.clone(),
     quote: BLANK_STR.clone(),
     amount_precision: 0,
     price_precision: 0,
-    order_amount_max: 0.0,
-    order_amount_min: 0.0,
-    order_notional_min: 0.0,
-    order_notional_max: 0.0,
+    order_amount_max: Decimal::ZERO,
+    order_amount_min: Decimal::ZERO,
+    order_notional_min: Decimal::ZERO,
+    order_notional_max: Decimal::ZERO,
     maker_fee: 0.0,
     taker_fee: 0.0,
 }));
@@ -28,19 +33,25 @@ static BLANK_INSTRUMENT: LazyLock<Arc<Instrument>> = LazyLock::new(|| Arc::new(I
 pub struct PriceTicker {
     pub timestamp: u128,
     pub instrument: Arc<Instrument>,
-    pub bid: f64,
-    pub bid_amount: f64,
-    pub ask: f64,
-    pub ask_amount: f64,
+    pub bid: Decimal,
+    pub bid_amount: Decimal,
+    pub ask: Decimal,
+    pub ask_amount: Decimal,
+    // exchange update id, 0 if the venue has none
+    pub update_id: u64,
+    // exchange event time in ns, 0 if the venue has none
+    pub exchange_ts: u128,
+    // redundant connection the ticker came from
+    pub connection: usize,
 }
 
 impl PriceTicker {
-    pub fn effective_bid(&self, fee: f64) -> f64 {
-        self.bid * (1.0 - fee)
+    pub fn effective_bid(&self, fee: f64) -> Decimal {
+        self.bid * Decimal::from_f64(1.0 - fee, FEE_SCALE)
     }
 
-    pub fn effective_ask(&self, fee: f64) -> f64 {
-        self.ask * (1.0 + fee)
+    pub fn effective_ask(&self, fee: f64) -> Decimal {
+        self.ask * Decimal::from_f64(1.0 + fee, FEE_SCALE)
     }
 
     pub fn is_prices_equals(&self, other: &Self) -> bool {
@@ -51,6 +62,9 @@ impl PriceTicker {
 
     pub fn update(&mut self, another: &PriceTicker) {
         self.timestamp = another.timestamp;
+        self.update_id = another.update_id;
+        self.exchange_ts = another.exchange_ts;
+        self.connection = another.connection;
         if another.bid != TICKER_PRICE_NOT_CHANGED {
             self.bid = another.bid;
             self.bid_amount = another.bid_amount;
@@ -70,6 +84,9 @@ impl PriceTicker {
             bid_amount: self.bid_amount,
             ask: self.ask,
             ask_amount: self.ask_amount,
+            update_id: self.update_id,
+            exchange_ts: self.exchange_ts,
+            connection: self.connection,
         }
     }
 }
@@ -79,61 +96,96 @@ pub enum Exchange {
     Any,
     Binance,
     Bit2me,
-    Mexc
+    Mexc,
+    Okx,
+    Bybit,
+    Kraken,
 }
 
 impl Exchange {
     pub fn iterator() -> Iter<'static, Exchange> {
-        static EXCHANGES: [Exchange; 3] = [Exchange::Binance, Exchange::Bit2me, Exchange::Mexc];
+        static EXCHANGES: [Exchange; 6] = [Exchange::Binance, Exchange::Bit2me, Exchange::Mexc, Exchange::Okx, Exchange::Bybit, Exchange::Kraken];
         EXCHANGES.iter()
     }
 
-    pub fn from_str(exchange: &str) -> Exchange {
-        match exchange {
-            "binance" => Binance,
-            "bit2me" => Bit2me,
-            "mexc" => Mexc,
-            "any" => Exchange::Any,
-            _ => panic!("Unknown exchange: {exchange}")
-        }
-    }
-
     pub fn as_str(&self) -> &str {
         match self {
             Binance => "binance",
             Bit2me => "bit2me",
             Mexc => "mexc",
+            Okx => "okx",
+            Bybit => "bybit",
+            Kraken => "kraken",
             Exchange::Any => "any",
-            _ => panic!("Unknown exchange: {self:?}")
         }
     }
 }
 
-#[derive(Debug)]
+impl FromStr for Exchange {
+    type Err = String;
+
+    fn from_str(exchange: &str) -> Result<Self, Self::Err> {
+        match exchange {
+            "binance" => Ok(Binance),
+            "bit2me" => Ok(Bit2me),
+            "mexc" => Ok(Mexc),
+            "okx" => Ok(Okx),
+            "bybit" => Ok(Bybit),
+            "kraken" => Ok(Kraken),
+            "any" => Ok(Exchange::Any),
+            _ => Err(format!("Unknown exchange: {exchange}")),
+        }
+    }
+}
+
+/// Compact instrument key assigned by the InstrumentsMap, unique across exchanges
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstrumentId(pub u32);
+
+#[derive(Debug, Clone)]
 pub struct Instrument {
+    // 0 until the instrument is added to the InstrumentsMap
+    pub id: InstrumentId,
     pub exchange: Exchange,
     pub symbol: String,
     pub base: String,
     pub quote: String,
     pub amount_precision: usize,
     pub price_precision: usize,
-    pub order_amount_max: f64,
-    pub order_amount_min: f64,
-    pub order_notional_min: f64,
-    pub order_notional_max: f64,
+    pub order_amount_max: Decimal,
+    pub order_amount_min: Decimal,
+    pub order_notional_min: Decimal,
+    pub order_notional_max: Decimal,
     pub maker_fee: f64,
     pub taker_fee: f64
 }
 
+impl Instrument {
+    /// Same precisions, limits and fees
+    pub fn has_same_params(&self, other: &Instrument) -> bool {
+        self.base == other.base
+            && self.quote == other.quote
+            && self.amount_precision == other.amount_precision
+            && self.price_precision == other.price_precision
+            && self.order_amount_max == other.order_amount_max
+            && self.order_amount_min == other.order_amount_min
+            && self.order_notional_min == other.order_notional_min
+            && self.order_notional_max == other.order_notional_max
+            && self.maker_fee == other.maker_fee
+            && self.taker_fee == other.taker_fee
+    }
+}
+
 impl Hash for Instrument {
     fn hash<H: Hasher>(&self, state: &mut H) {
+        self.exchange.hash(state);
         self.symbol.hash(state);
     }
 }
 
 impl PartialEq for Instrument {
     fn eq(&self, other: &Self) -> bool {
-        self.symbol == other.symbol
+        self.exchange == other.exchange && self.symbol == other.symbol
     }
 }
 
@@ -145,7 +197,7 @@ pub enum OrderSide {
     Sell
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum OrderStatus {
     // created but not yet sent to exchange
     Scheduled,
@@ -165,13 +217,20 @@ pub enum OrderStatus {
     Error
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl OrderStatus {
+    pub fn is_final(&self) -> bool {
+        matches!(self, OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Error)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum OrderType {
     Market,
     Limit,
     LimitMaker
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum TimeInForce {
     GTC,  //  An order will be on the book unless the order is canceled.
     IOC,  //  An order will try to fill the order as much as it can before the order expires.
@@ -179,21 +238,31 @@ pub enum TimeInForce {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Order {
     pub timestamp: u128,
     pub instrument: Arc<Instrument>,
     pub exchange_order_id: String,
     pub client_order_id: String,
+    pub strategy_id: String,
     pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
     pub side: OrderSide,
     pub status: OrderStatus,
-    pub price: f64,
-    pub amount: f64,
-    pub amount_quote: f64,
-    pub amount_filled: f64,
-    pub fees: Vec<(String, f64)>,
-    pub error: String
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub amount_quote: Decimal,
+    pub amount_filled: Decimal,
+    pub fees: Vec<(String, Decimal)>,
+    pub error: String,
+    // socket read time of the ticker the order is decided on, 0 if none
+    pub signal_ts: u128,
+}
+
+impl Default for Order {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Order {
@@ -203,15 +272,18 @@ impl Order {
             instrument: Arc::clone(&BLANK_INSTRUMENT),
             exchange_order_id: "".to_string(),
             client_order_id: "".to_string(),
+            strategy_id: "".to_string(),
             order_type: OrderType::Market,
+            time_in_force: TimeInForce::GTC,
             side: OrderSide::Buy,
             status: OrderStatus::Scheduled,
-            price: 0.0,
-            amount: 0.0,
-            amount_quote: 0.0,
-            amount_filled: 0.0,
+            price: Decimal::ZERO,
+            amount: Decimal::ZERO,
+            amount_quote: Decimal::ZERO,
+            amount_filled: Decimal::ZERO,
             fees: vec![],
             error: "".to_string(),
+            signal_ts: 0,
         }
     }
 
@@ -223,24 +295,18 @@ impl Order {
     //     }
     // }
 
-    pub fn balance(&self) -> Option<(String, f64)> {
-        // if self.status == OrderStatus::Filled {
-        //     match self.side {
-        //         OrderSide::Buy => Some((self.instrument.base.clone(), self.amount_filled - self.amount_filled*self.instrument.taker_fee)),
-        //         OrderSide::Sell => Some((self.instrument.quote.clone(), self.amount_quote - self.amount_quote*self.instrument.taker_fee)),
-        //     }
-        // } else {
-        //     None
-        // }
-        // bnb
-        if self.status == OrderStatus::Filled {
-            match self.side {
-                OrderSide::Buy => Some((self.instrument.base.clone(), self.amount_filled)),
-                OrderSide::Sell => Some((self.instrument.quote.clone(), self.amount_quote)),
-            }
-        } else {
-            None
+    /// Received currency and amount net of the fees charged in it.
+    /// Fees paid with the exchange token (BNB, MX) don't reduce the amount
+    pub fn balance(&self) -> Option<(String, Decimal)> {
+        if self.status != OrderStatus::Filled {
+            return None;
         }
+        let (currency, amount) = match self.side {
+            OrderSide::Buy => (&self.instrument.base, self.amount_filled),
+            OrderSide::Sell => (&self.instrument.quote, self.amount_quote),
+        };
+        let fee: Decimal = self.fees.iter().filter(|(c, _)| c == currency).map(|(_, fee)| *fee).sum();
+        Some((currency.clone(), amount - fee))
     }
 }
 
@@ -259,17 +325,18 @@ impl Balance {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MonitoringStatus {
     Ok,
     Error
 }
 
 
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
 pub enum MonitoringEntity {
     PriceTicker,
     OrderManagementSystem,
+    OrderRateLimit,
     AccountUpdate
 }
 
@@ -279,7 +346,10 @@ pub struct MonitoringMessage {
     pub timestamp: u128,
     pub status: MonitoringStatus,
     pub entity: MonitoringEntity,
-    pub entity_id: usize
+    pub exchange: Exchange,
+    pub entity_id: usize,
+    // redundant stream connection the socket belongs to
+    pub connection: usize,
 }
 
 impl MonitoringMessage {
@@ -287,9 +357,25 @@ impl MonitoringMessage {
         timestamp: u128,
         status: MonitoringStatus,
         entity: MonitoringEntity,
+        exchange: Exchange,
         entity_id: usize
     ) -> Self {
-        Self {timestamp, status, entity, entity_id }
+        Self {timestamp, status, entity, exchange, entity_id, connection: 0 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstrumentsUpdate {
+    pub timestamp: u128,
+    pub added: Vec<Arc<Instrument>>,
+    pub removed: Vec<Arc<Instrument>>,
+    // the same symbol with new params
+    pub changed: Vec<Arc<Instrument>>,
+}
+
+impl InstrumentsUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
     }
 }
 
@@ -298,5 +384,6 @@ pub enum DTO {
     PriceTicker(PriceTicker),
     Order(Order),
     Balance(Balance),
-    MonitoringMessage(MonitoringMessage)
+    MonitoringMessage(MonitoringMessage),
+    InstrumentsUpdate(InstrumentsUpdate),
 }
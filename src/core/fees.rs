@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Exchange, Instrument, OrderSide};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+/// How the commission is charged
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeeCurrency {
+    // deducted from the received asset
+    Proceeds,
+    // paid with the exchange token (BNB, MX), discount 0.25 = 25% off
+    Token { currency: String, discount: f64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct FeeTier {
+    pub name: String,
+    // 30 days trading volume required for the tier
+    pub min_volume: Decimal,
+    pub maker_fee: f64,
+    pub taker_fee: f64,
+}
+
+impl FeeTier {
+    pub fn new(name: &str, min_volume: Decimal, maker_fee: f64, taker_fee: f64) -> Self {
+        Self { name: name.to_string(), min_volume, maker_fee, taker_fee }
+    }
+}
+
+/// Account fees on the exchange. Without tiers the instrument fees are used
+#[derive(Debug, Clone)]
+pub struct FeeSchedule {
+    pub tiers: Vec<FeeTier>,
+    pub volume_30d: Decimal,
+    pub fee_currency: FeeCurrency,
+}
+
+impl FeeSchedule {
+    pub fn new(mut tiers: Vec<FeeTier>, volume_30d: Decimal, fee_currency: FeeCurrency) -> Self {
+        tiers.sort_by_key(|tier| tier.min_volume);
+        Self { tiers, volume_30d, fee_currency }
+    }
+
+    /// The highest tier reached by the account volume
+    pub fn tier(&self) -> Option<&FeeTier> {
+        self.tiers.iter().rev().find(|tier| tier.min_volume <= self.volume_30d)
+    }
+
+    /// The tier rate before the token discount
+    pub fn full_rate(&self, instrument: &Instrument, liquidity: Liquidity) -> f64 {
+        match (self.tier(), liquidity) {
+            (Some(tier), Liquidity::Maker) => tier.maker_fee,
+            (Some(tier), Liquidity::Taker) => tier.taker_fee,
+            (None, Liquidity::Maker) => instrument.maker_fee,
+            (None, Liquidity::Taker) => instrument.taker_fee,
+        }
+    }
+
+    pub fn rate(&self, instrument: &Instrument, liquidity: Liquidity) -> f64 {
+        let rate = self.full_rate(instrument, liquidity);
+        match &self.fee_currency {
+            FeeCurrency::Proceeds => rate,
+            FeeCurrency::Token { discount, .. } => rate * (1. - discount),
+        }
+    }
+}
+
+/// Fee schedules by exchange, exchanges without a schedule pay the instrument fees from the proceeds
+#[derive(Debug, Clone, Default)]
+pub struct FeeModel {
+    schedules: HashMap<Exchange, FeeSchedule>,
+}
+
+impl FeeModel {
+    pub fn new() -> Self {
+        Self { schedules: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, exchange: Exchange, schedule: FeeSchedule) {
+        self.schedules.insert(exchange, schedule);
+    }
+
+    pub fn schedule(&self, exchange: &Exchange) -> Option<&FeeSchedule> {
+        self.schedules.get(exchange)
+    }
+
+    /// Cost of the trade as a share of the notional, whatever currency it's paid in
+    pub fn rate(&self, instrument: &Instrument, liquidity: Liquidity) -> f64 {
+        match self.schedules.get(&instrument.exchange) {
+            Some(schedule) => schedule.rate(instrument, liquidity),
+            None if liquidity == Liquidity::Maker => instrument.maker_fee,
+            None => instrument.taker_fee,
+        }
+    }
+
+    /// Rate without the token discount, charged when the token can't pay the fee
+    pub fn full_rate(&self, instrument: &Instrument, liquidity: Liquidity) -> f64 {
+        match self.schedules.get(&instrument.exchange) {
+            Some(schedule) => schedule.full_rate(instrument, liquidity),
+            None => self.rate(instrument, liquidity),
+        }
+    }
+
+    /// Share of the received amount kept by the exchange, 0 when the fee is paid with the token
+    pub fn proceeds_rate(&self, instrument: &Instrument, liquidity: Liquidity) -> f64 {
+        match self.token(&instrument.exchange) {
+            Some(_) => 0.,
+            None => self.rate(instrument, liquidity),
+        }
+    }
+
+    /// Token the fees are paid with on the exchange
+    pub fn token(&self, exchange: &Exchange) -> Option<&str> {
+        match &self.schedules.get(exchange)?.fee_currency {
+            FeeCurrency::Token { currency, .. } => Some(currency.as_str()),
+            FeeCurrency::Proceeds => None,
+        }
+    }
+
+    /// Currency the fee of the order is charged in
+    pub fn fee_currency(&self, instrument: &Instrument, side: OrderSide) -> String {
+        match (self.token(&instrument.exchange), side) {
+            (Some(token), _) => token.to_string(),
+            (None, OrderSide::Buy) => instrument.base.clone(),
+            (None, OrderSide::Sell) => instrument.quote.clone(),
+        }
+    }
+}
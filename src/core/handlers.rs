@@ -1,37 +1,50 @@
 use std::collections::HashMap;
-use std::sync::Arc;
-use crate::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus};
+use std::sync::{Arc, RwLock};
+use crate::core::dto::{Exchange, InstrumentsUpdate, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order};
 use crate::{
     core::api::PriceTickerListener,
     core::dto::PriceTicker,
 };
-use crate::core::api::{BaseStrategy, MonitoringMessageListener};
-use crate::core::dto::{Instrument};
+use crate::core::arbitration::Arbitrator;
+use crate::core::api::{BaseStrategy, InstrumentsListener, MonitoringMessageListener, OrderListener};
+use crate::core::dto::{InstrumentId};
+use crate::core::health::{HealthRegistry, HealthStatus};
+use crate::core::metrics::{Gauge, METRICS};
+use crate::core::order_tracker::OrderTracker;
 
 pub struct PriceTickerFilter {
     pub listeners: Vec<Box<dyn BaseStrategy>>,
-    tickers_map: HashMap<Exchange, HashMap<Arc<Instrument>, PriceTicker>>
+    tickers_map: HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>,
+    order_tracker: Arc<RwLock<OrderTracker>>,
+    pub arbitrator: Arbitrator,
+    pub health: HealthRegistry,
+    oms_connected: Gauge,
 }
 
 impl PriceTickerFilter {
-    pub fn new(listeners: Vec<Box<dyn BaseStrategy>>) -> Self {
+    pub fn new(listeners: Vec<Box<dyn BaseStrategy>>, order_tracker: Arc<RwLock<OrderTracker>>) -> Self {
         let mut tickers_map = HashMap::new();
         for exchange in Exchange::iterator() {
             tickers_map.insert((*exchange).clone(), HashMap::default());
         }
-        Self { tickers_map, listeners }
+        let oms_connected = METRICS.gauge("arbot_oms_connected", "1 if the order management system is up", &[]);
+        Self { tickers_map, listeners, order_tracker, arbitrator: Arbitrator::new(), health: HealthRegistry::new(), oms_connected }
+    }
+
+    pub fn tickers_map(&self) -> &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>> {
+        &self.tickers_map
     }
 
     fn update(&mut self, price_ticker: &PriceTicker) -> bool {
         let mut result = true;
         let exchange_tickers_map = self.tickers_map.get_mut(&price_ticker.instrument.exchange).unwrap();
-        match exchange_tickers_map.get(&price_ticker.instrument) {
+        match exchange_tickers_map.get_mut(&price_ticker.instrument.id) {
             Some(p) => {
                 result = !p.is_prices_equals(price_ticker);
-                exchange_tickers_map.get_mut(&price_ticker.instrument).unwrap().update(price_ticker);
+                p.update(price_ticker);
             }
             None => {
-                exchange_tickers_map.insert(Arc::clone(&price_ticker.instrument), price_ticker.copy());
+                exchange_tickers_map.insert(price_ticker.instrument.id, price_ticker.copy());
             }
         };
         result
@@ -39,7 +52,11 @@ impl PriceTickerFilter {
 }
 
 impl PriceTickerListener for PriceTickerFilter {
-    fn on_price_ticker(&mut self, price_ticker: &PriceTicker, _: &HashMap<Exchange, HashMap<Arc<Instrument>, PriceTicker>>) {
+    fn on_price_ticker(&mut self, price_ticker: &PriceTicker, _: &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>) {
+        self.arbitrator.report(price_ticker.timestamp);
+        if !self.arbitrator.accept(price_ticker) {
+            return; // duplicate or out-of-order update
+        }
         if self.update(price_ticker) {
             for listener in self.listeners.iter_mut() {
                 listener.on_price_ticker(price_ticker, &self.tickers_map);
@@ -48,19 +65,69 @@ impl PriceTickerListener for PriceTickerFilter {
     }
 }
 
+impl OrderListener for PriceTickerFilter {
+    fn on_order(&mut self, order: &Order) {
+        let tracked = match self.order_tracker.write().expect("Can't get the lock").update(order) {
+            Ok(tracked) => tracked.clone(),
+            Err(err) => {
+                log::warn!("Execution report dropped {err:?}: {order:?}");
+                return;
+            }
+        };
+        let status = format!("{:?}", tracked.status);
+        METRICS.counter(
+            "arbot_order_updates_total",
+            "Order updates by the status",
+            &[("exchange", tracked.instrument.exchange.as_str()), ("status", &status)],
+        ).inc();
+        for listener in self.listeners.iter_mut() {
+            listener.on_order(&tracked);
+        }
+    }
+}
+
 impl MonitoringMessageListener for PriceTickerFilter {
     fn on_monitoring_message(&mut self, message: &MonitoringMessage) {
+        let event = self.health.on_monitoring_message(message);
         match message.entity {
-            MonitoringEntity::PriceTicker => match message.status {
-                MonitoringStatus::Error => {
-                    self.tickers_map.clear();
-                    for exchange in Exchange::iterator() {
-                        self.tickers_map.insert((*exchange).clone(), HashMap::default());
+            MonitoringEntity::PriceTicker if message.status == MonitoringStatus::Error => {
+                if self.health.group_status(&message.entity, &message.exchange) == HealthStatus::Down {
+                    // no stream of the exchange is left, the prices are stale
+                    self.arbitrator.reset_exchange(&message.exchange);
+                    if let Some(tickers) = self.tickers_map.get_mut(&message.exchange) {
+                        tickers.clear();
                     }
-                },
-                _ => {}
+                } else {
+                    // update ids of the failed connection restart with the new subscription
+                    self.arbitrator.reset_connection(&message.exchange, message.connection);
+                }
+            }
+            MonitoringEntity::OrderManagementSystem => match message.status {
+                MonitoringStatus::Ok => self.oms_connected.set(1.0),
+                MonitoringStatus::Error => self.oms_connected.set(0.0),
             }
             _ => {}
         }
+        // the strategies follow the derived group state
+        if let Some(event) = event {
+            for listener in self.listeners.iter_mut() {
+                listener.on_health_event(&event);
+            }
+        }
+    }
+}
+
+impl InstrumentsListener for PriceTickerFilter {
+    fn on_instruments_update(&mut self, update: &InstrumentsUpdate) {
+        // changed instruments come back with the next ticker
+        for instrument in update.removed.iter().chain(update.changed.iter()) {
+            if let Some(tickers) = self.tickers_map.get_mut(&instrument.exchange) {
+                tickers.remove(&instrument.id);
+            }
+            self.arbitrator.remove(instrument.id);
+        }
+        for listener in self.listeners.iter_mut() {
+            listener.on_instruments_update(update);
+        }
     }
 }
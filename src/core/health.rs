@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use json::{object, JsonValue};
+use crate::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthStatus {
+    Ok,
+    // some entities of the group are errored
+    Degraded,
+    // all entities of the group are errored
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HealthKey {
+    pub entity: MonitoringEntity,
+    pub exchange: Exchange,
+    pub connection: usize,
+    pub entity_id: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityHealth {
+    pub status: MonitoringStatus,
+    pub last_seen_ts: u128,
+    // the latest status change
+    pub changed_ts: u128,
+    pub errors: u64,
+}
+
+/// Change of the errored entities count of the entity and exchange group
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthEvent {
+    pub timestamp: u128,
+    pub entity: MonitoringEntity,
+    pub exchange: Exchange,
+    pub status: HealthStatus,
+    pub errored: usize,
+    pub total: usize,
+}
+
+/// State of the stream sockets, OMS and account streams built from the monitoring messages
+#[derive(Default)]
+pub struct HealthRegistry {
+    entities: HashMap<HealthKey, EntityHealth>,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the message, the event if the group errored count is changed
+    pub fn on_monitoring_message(&mut self, message: &MonitoringMessage) -> Option<HealthEvent> {
+        let key = HealthKey {
+            entity: message.entity,
+            exchange: message.exchange.clone(),
+            connection: message.connection,
+            entity_id: message.entity_id,
+        };
+        let (errored_before, _) = self.group_counts(&message.entity, &message.exchange);
+        let health = self.entities.entry(key).or_insert(EntityHealth {
+            status: MonitoringStatus::Ok,
+            last_seen_ts: message.timestamp,
+            changed_ts: message.timestamp,
+            errors: 0,
+        });
+        health.last_seen_ts = message.timestamp;
+        if message.status == MonitoringStatus::Error {
+            health.errors += 1;
+        }
+        if health.status != message.status {
+            health.status = message.status;
+            health.changed_ts = message.timestamp;
+        }
+
+        let (errored, total) = self.group_counts(&message.entity, &message.exchange);
+        if errored == errored_before {
+            return None;
+        }
+        let status = self.group_status(&message.entity, &message.exchange);
+        match status {
+            HealthStatus::Down => log::error!("{:?} {:?} is fully down", message.exchange, message.entity),
+            HealthStatus::Degraded => log::warn!("{:?} {:?} is degraded: {errored}/{total} errored", message.exchange, message.entity),
+            HealthStatus::Ok => log::info!("{:?} {:?} is up", message.exchange, message.entity),
+        }
+        Some(HealthEvent {
+            timestamp: message.timestamp,
+            entity: message.entity,
+            exchange: message.exchange.clone(),
+            status,
+            errored,
+            total,
+        })
+    }
+
+    // (errored, total) of the group
+    fn group_counts(&self, entity: &MonitoringEntity, exchange: &Exchange) -> (usize, usize) {
+        self.entities.iter()
+            .filter(|(key, _)| &key.entity == entity && &key.exchange == exchange)
+            .fold((0, 0), |(errored, total), (_, health)| {
+                (errored + (health.status == MonitoringStatus::Error) as usize, total + 1)
+            })
+    }
+
+    /// Ok for the unknown group
+    pub fn group_status(&self, entity: &MonitoringEntity, exchange: &Exchange) -> HealthStatus {
+        match self.group_counts(entity, exchange) {
+            (0, _) => HealthStatus::Ok,
+            (errored, total) if errored == total => HealthStatus::Down,
+            _ => HealthStatus::Degraded,
+        }
+    }
+
+    /// The worst status of the groups
+    pub fn status(&self) -> HealthStatus {
+        self.entities.keys()
+            .map(|key| self.group_status(&key.entity, &key.exchange))
+            .max()
+            .unwrap_or(HealthStatus::Ok)
+    }
+
+    pub fn get(&self, key: &HealthKey) -> Option<&EntityHealth> {
+        self.entities.get(key)
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = (&HealthKey, &EntityHealth)> {
+        self.entities.iter()
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut entities: Vec<(&HealthKey, &EntityHealth)> = self.entities.iter().collect();
+        entities.sort_by_key(|(key, _)| (key.exchange.as_str(), format!("{:?}", key.entity), key.connection, key.entity_id));
+        let entities: Vec<JsonValue> = entities.iter().map(|(key, health)| object! {
+            entity: format!("{:?}", key.entity),
+            exchange: key.exchange.as_str(),
+            connection: key.connection,
+            entity_id: key.entity_id,
+            status: format!("{:?}", health.status),
+            last_seen_ts: health.last_seen_ts.to_string(),
+            changed_ts: health.changed_ts.to_string(),
+            errors: health.errors,
+        }).collect();
+        object! { status: format!("{:?}", self.status()), entities: entities }
+    }
+}
@@ -0,0 +1,92 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+pub struct HttpResponse {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn new(status: &'static str, content_type: &'static str, body: String) -> Self {
+        Self { status, content_type, body }
+    }
+
+    pub fn json(status: &'static str, body: String) -> Self {
+        Self::new(status, "application/json", body)
+    }
+
+    pub fn not_found() -> Self {
+        Self::new("404 Not Found", "text/plain", "Not found\n".to_string())
+    }
+}
+
+/// Minimal HTTP/1.1 server for the local endpoints, a connection per request
+pub fn serve(name: &str, address: &str, handler: impl Fn(&HttpRequest) -> HttpResponse + Send + 'static) -> SocketAddr {
+    let listener = TcpListener::bind(address).unwrap_or_else(|e| panic!("Can't bind the {name} endpoint to {address}: {e}"));
+    let address = listener.local_addr().expect("Can't get the endpoint address");
+    log::info!("{name} endpoint: http://{address}");
+    let name = name.to_string();
+    thread::Builder::new().name(name.clone()).spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|mut stream| {
+                let request = read_request(&mut stream)?;
+                write_response(&mut stream, &handler(&request))
+            });
+            if let Err(err) = result {
+                log::warn!("{name} request failed: {err}");
+            }
+        }
+    }).expect("Failed to spawn http thread");
+    address
+}
+
+fn read_request(stream: &mut TcpStream) -> std::io::Result<HttpRequest> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut content_length = 0;
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && !line.trim().is_empty() {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+        line.clear();
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Body is too large: {content_length}")));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    Ok(HttpRequest {
+        method: parts.next().unwrap_or_default().to_string(),
+        path: parts.next().unwrap_or_default().to_string(),
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn write_response(stream: &mut TcpStream, response: &HttpResponse) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status, response.content_type, response.body.len(), response.body,
+    )?;
+    stream.flush()
+}
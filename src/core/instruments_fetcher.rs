@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use json::{object, JsonValue};
+use url::Url;
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Exchange, Instrument, InstrumentId};
+use crate::core::map::InstrumentsMap;
+use crate::core::utils::{decimal_to_json, json_to_decimal};
+
+// exchangeInfo doesn't contain the account commissions
+const BINANCE_FEE: f64 = 0.001;
+const BIT2ME_FEE: f64 = 0.001;
+const OKX_MAKER_FEE: f64 = 0.0008;
+const OKX_TAKER_FEE: f64 = 0.001;
+const BYBIT_FEE: f64 = 0.001;
+// used when the exchange has no explicit limit
+const NO_LIMIT: Decimal = Decimal::new(1_000_000_000_000, 0);
+
+#[derive(Debug, Default, PartialEq)]
+pub struct InstrumentsDiff {
+    pub added: Vec<String>,
+    pub delisted: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// Read the payload from the file or the plain http url
+pub fn load_payload(source: &str) -> JsonValue {
+    let raw = if source.starts_with("http://") || source.starts_with("https://") {
+        http_get(source)
+    } else {
+        fs::read_to_string(source).unwrap_or_else(|e| panic!("Failed to read {source}: {e}"))
+    };
+    json::parse(&raw).unwrap_or_else(|e| panic!("Failed to parse payload from {source}: {e}"))
+}
+
+fn http_get(source: &str) -> String {
+    let url = Url::parse(source).unwrap_or_else(|e| panic!("Invalid url {source}: {e}"));
+    assert_eq!("http", url.scheme(), "Only plain http is supported, download the payload to a file instead");
+    let host = url.host_str().expect("No host");
+    let port = url.port_or_known_default().unwrap();
+    let path = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    };
+
+    let mut stream = TcpStream::connect((host, port)).unwrap_or_else(|e| panic!("Can't connect to {host}:{port}: {e}"));
+    write!(stream, "GET {path} HTTP/1.0\r\nHost: {host}\r\nAccept: application/json\r\n\r\n").expect("Can't send request");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("Can't read response");
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or_else(|| panic!("Malformed response from {source}"));
+    let status = head.lines().next().unwrap_or_default();
+    assert!(status.split_whitespace().nth(1) == Some("200"), "Unexpected response from {source}: {status}");
+    body.to_string()
+}
+
+// digits after the point of the step, e.g. 0.00100000 -> 3
+fn step_precision(step: &Decimal) -> usize {
+    step.normalize().scale() as usize
+}
+
+fn decimal_field(data: &JsonValue, field: &str) -> Option<Decimal> {
+    json_to_decimal(&data[field])
+}
+
+pub fn parse_binance(data: &JsonValue) -> Vec<Instrument> {
+    let mut instruments = Vec::new();
+    for raw in data["symbols"].members() {
+        if raw["status"] != "TRADING" {
+            continue;
+        }
+        let symbol = raw["symbol"].as_str().expect("No symbol");
+        let filter = |filter_type: &str| raw["filters"].members().find(|f| f["filterType"] == filter_type);
+        let (Some(price_filter), Some(lot_size)) = (filter("PRICE_FILTER"), filter("LOT_SIZE")) else {
+            log::warn!("Binance {symbol} has no PRICE_FILTER/LOT_SIZE, skip");
+            continue;
+        };
+        // NOTIONAL replaced MIN_NOTIONAL, some payloads still have the old one
+        let notional = filter("NOTIONAL").or(filter("MIN_NOTIONAL"));
+
+        instruments.push(Instrument {
+            id: InstrumentId::default(),
+            exchange: Exchange::Binance,
+            symbol: symbol.to_string(),
+            base: raw["baseAsset"].as_str().expect("No baseAsset").to_string(),
+            quote: raw["quoteAsset"].as_str().expect("No quoteAsset").to_string(),
+            amount_precision: step_precision(&decimal_field(lot_size, "stepSize").expect("No stepSize")),
+            price_precision: step_precision(&decimal_field(price_filter, "tickSize").expect("No tickSize")),
+            order_amount_max: decimal_field(lot_size, "maxQty").unwrap_or(NO_LIMIT),
+            order_amount_min: decimal_field(lot_size, "minQty").unwrap_or(Decimal::ZERO),
+            order_notional_min: notional.and_then(|f| decimal_field(f, "minNotional")).unwrap_or(Decimal::ZERO),
+            order_notional_max: notional.and_then(|f| decimal_field(f, "maxNotional")).unwrap_or(NO_LIMIT),
+            maker_fee: BINANCE_FEE,
+            taker_fee: BINANCE_FEE,
+        });
+    }
+    instruments
+}
+
+pub fn parse_mexc(data: &JsonValue) -> Vec<Instrument> {
+    let mut instruments = Vec::new();
+    for raw in data["symbols"].members() {
+        // "1" - online, older payloads use "ENABLED"
+        let is_online = raw["status"] == "1" || raw["status"] == "ENABLED";
+        if !is_online || raw["isSpotTradingAllowed"] == false {
+            continue;
+        }
+        let amount_precision = raw["baseAssetPrecision"].as_usize().expect("No baseAssetPrecision");
+        let min_amount = decimal_field(raw, "baseSizePrecision")
+            .filter(|step| !step.is_zero())
+            .unwrap_or(Decimal::new(1, amount_precision as u32));
+
+        instruments.push(Instrument {
+            id: InstrumentId::default(),
+            exchange: Exchange::Mexc,
+            symbol: raw["symbol"].as_str().expect("No symbol").to_string(),
+            base: raw["baseAsset"].as_str().expect("No baseAsset").to_string(),
+            quote: raw["quoteAsset"].as_str().expect("No quoteAsset").to_string(),
+            amount_precision,
+            price_precision: raw["quotePrecision"].as_usize().expect("No quotePrecision"),
+            order_amount_max: NO_LIMIT,
+            order_amount_min: min_amount,
+            order_notional_min: decimal_field(raw, "quoteAmountPrecision").unwrap_or(Decimal::ZERO),
+            order_notional_max: decimal_field(raw, "maxQuoteAmount").unwrap_or(NO_LIMIT),
+            maker_fee: decimal_field(raw, "makerCommission").expect("No makerCommission").to_f64(),
+            taker_fee: decimal_field(raw, "takerCommission").expect("No takerCommission").to_f64(),
+        });
+    }
+    instruments
+}
+
+pub fn parse_bit2me(data: &JsonValue) -> Vec<Instrument> {
+    let mut instruments = Vec::new();
+    for raw in data.members() {
+        if raw["status"] != "active" {
+            continue;
+        }
+        let symbol = raw["symbol"].as_str().expect("No symbol");
+        let Some((base, quote)) = symbol.split_once('/') else {
+            log::warn!("Bit2me unexpected symbol {symbol}, skip");
+            continue;
+        };
+
+        instruments.push(Instrument {
+            id: InstrumentId::default(),
+            exchange: Exchange::Bit2me,
+            symbol: symbol.to_string(),
+            base: base.to_string(),
+            quote: quote.to_string(),
+            amount_precision: raw["amountPrecision"].as_usize().expect("No amountPrecision"),
+            price_precision: raw["pricePrecision"].as_usize().expect("No pricePrecision"),
+            order_amount_max: decimal_field(raw, "maxAmount").unwrap_or(NO_LIMIT),
+            order_amount_min: decimal_field(raw, "minAmount").unwrap_or(Decimal::ZERO),
+            order_notional_min: decimal_field(raw, "minOrderSize").unwrap_or(Decimal::ZERO),
+            order_notional_max: decimal_field(raw, "maxOrderSize").unwrap_or(NO_LIMIT),
+            maker_fee: BIT2ME_FEE,
+            taker_fee: BIT2ME_FEE,
+        });
+    }
+    instruments
+}
+
+pub fn parse_okx(data: &JsonValue) -> Vec<Instrument> {
+    let mut instruments = Vec::new();
+    for raw in data["data"].members() {
+        if raw["state"] != "live" || raw["instType"] != "SPOT" {
+            continue;
+        }
+        let symbol = raw["instId"].as_str().expect("No instId");
+        let lot_size = decimal_field(raw, "lotSz").expect("No lotSz");
+
+        instruments.push(Instrument {
+            id: InstrumentId::default(),
+            exchange: Exchange::Okx,
+            symbol: symbol.to_string(),
+            base: raw["baseCcy"].as_str().expect("No baseCcy").to_string(),
+            quote: raw["quoteCcy"].as_str().expect("No quoteCcy").to_string(),
+            amount_precision: step_precision(&lot_size),
+            price_precision: step_precision(&decimal_field(raw, "tickSz").expect("No tickSz")),
+            order_amount_max: decimal_field(raw, "maxLmtSz").unwrap_or(NO_LIMIT),
+            order_amount_min: decimal_field(raw, "minSz").unwrap_or(lot_size),
+            order_notional_min: Decimal::ZERO,
+            order_notional_max: decimal_field(raw, "maxLmtAmt").unwrap_or(NO_LIMIT),
+            maker_fee: OKX_MAKER_FEE,
+            taker_fee: OKX_TAKER_FEE,
+        });
+    }
+    instruments
+}
+
+pub fn parse_bybit(data: &JsonValue) -> Vec<Instrument> {
+    let mut instruments = Vec::new();
+    for raw in data["result"]["list"].members() {
+        if raw["status"] != "Trading" {
+            continue;
+        }
+        let lot_size = &raw["lotSizeFilter"];
+
+        instruments.push(Instrument {
+            id: InstrumentId::default(),
+            exchange: Exchange::Bybit,
+            symbol: raw["symbol"].as_str().expect("No symbol").to_string(),
+            base: raw["baseCoin"].as_str().expect("No baseCoin").to_string(),
+            quote: raw["quoteCoin"].as_str().expect("No quoteCoin").to_string(),
+            amount_precision: step_precision(&decimal_field(lot_size, "basePrecision").expect("No basePrecision")),
+            price_precision: step_precision(&decimal_field(&raw["priceFilter"], "tickSize").expect("No tickSize")),
+            order_amount_max: decimal_field(lot_size, "maxOrderQty").unwrap_or(NO_LIMIT),
+            order_amount_min: decimal_field(lot_size, "minOrderQty").unwrap_or(Decimal::ZERO),
+            order_notional_min: decimal_field(lot_size, "minOrderAmt").unwrap_or(Decimal::ZERO),
+            order_notional_max: decimal_field(lot_size, "maxOrderAmt").unwrap_or(NO_LIMIT),
+            maker_fee: BYBIT_FEE,
+            taker_fee: BYBIT_FEE,
+        });
+    }
+    instruments
+}
+
+pub fn to_json(instruments: &[Instrument]) -> JsonValue {
+    let mut data = JsonValue::new_array();
+    for instrument in instruments {
+        data.push(object! {
+            exchange: instrument.exchange.as_str(),
+            symbol: instrument.symbol.as_str(),
+            base: instrument.base.as_str(),
+            quote: instrument.quote.as_str(),
+            amount_precision: instrument.amount_precision,
+            price_precision: instrument.price_precision,
+            order_amount_max: decimal_to_json(instrument.order_amount_max),
+            order_amount_min: decimal_to_json(instrument.order_amount_min),
+            order_notional_min: decimal_to_json(instrument.order_notional_min),
+            order_notional_max: decimal_to_json(instrument.order_notional_max),
+            maker_fee: instrument.maker_fee,
+            taker_fee: instrument.taker_fee,
+        }).unwrap();
+    }
+    data
+}
+
+fn key(instrument: &Instrument) -> String {
+    format!("{}:{}", instrument.exchange.as_str(), instrument.symbol)
+}
+
+pub fn diff(old: &[Instrument], new: &[Instrument]) -> InstrumentsDiff {
+    let old: BTreeMap<String, &Instrument> = old.iter().map(|i| (key(i), i)).collect();
+    let new: BTreeMap<String, &Instrument> = new.iter().map(|i| (key(i), i)).collect();
+
+    let mut result = InstrumentsDiff::default();
+    for (key, instrument) in &new {
+        match old.get(key) {
+            None => result.added.push(key.clone()),
+            Some(old_instrument) if !old_instrument.has_same_params(instrument) => result.changed.push(key.clone()),
+            _ => {}
+        }
+    }
+    result.delisted = old.keys().filter(|key| !new.contains_key(*key)).cloned().collect();
+    result
+}
+
+/// `fetch-instruments [--binance <src>] [--mexc <src>] [--bit2me <src>] [--okx <src>] [--bybit <src>] [--output <path>] [--dry-run]`
+///
+/// Source is a file or http url with the exchange payload. Instruments of the exchanges
+/// without a source are kept from the existing output file.
+pub fn run(args: &[String]) {
+    let mut sources = Vec::new();
+    let mut output = "./data/spot_insts.json".to_string();
+    let mut dry_run = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--binance" | "--mexc" | "--bit2me" | "--okx" | "--bybit" => {
+                let exchange = arg[2..].parse::<Exchange>().unwrap_or_else(|err| panic!("{err}"));
+                sources.push((exchange, args.next().unwrap_or_else(|| panic!("No source for {arg}")).clone()));
+            }
+            "--output" => output = args.next().expect("No output path").clone(),
+            "--dry-run" => dry_run = true,
+            _ => panic!("Unknown argument: {arg}"),
+        }
+    }
+    assert!(!sources.is_empty(), "No sources, use --binance/--mexc/--bit2me/--okx/--bybit <file or url>");
+
+    let old = if Path::new(&output).exists() {
+        let raw = fs::read_to_string(&output).unwrap_or_else(|e| panic!("Failed to read {output}: {e}"));
+        InstrumentsMap::parse_instruments(&json::parse(&raw).unwrap_or_else(|e| panic!("Failed to parse {output}: {e}")))
+            .unwrap_or_else(|e| panic!("Invalid instruments in {output}: {e}"))
+    } else {
+        log::warn!("{output} doesn't exist, all instruments are new");
+        vec![]
+    };
+
+    let mut new: Vec<Instrument> = Vec::new();
+    for exchange in Exchange::iterator() {
+        match sources.iter().find(|(e, _)| e == exchange) {
+            Some((_, source)) => {
+                let payload = load_payload(source);
+                let instruments = match exchange {
+                    Exchange::Binance => parse_binance(&payload),
+                    Exchange::Mexc => parse_mexc(&payload),
+                    Exchange::Bit2me => parse_bit2me(&payload),
+                    Exchange::Okx => parse_okx(&payload),
+                    Exchange::Bybit => parse_bybit(&payload),
+                    Exchange::Kraken | Exchange::Any => unreachable!(),
+                };
+                log::info!("{exchange:?}: {} instruments from {source}", instruments.len());
+                new.extend(instruments);
+            }
+            None => new.extend(old.iter().filter(|i| i.exchange == *exchange).cloned()),
+        }
+    }
+    new.sort_by_key(key);
+
+    let report = diff(&old, &new);
+    log::info!("New ({}): {:?}", report.added.len(), report.added);
+    log::info!("Delisted ({}): {:?}", report.delisted.len(), report.delisted);
+    log::info!("Changed ({}): {:?}", report.changed.len(), report.changed);
+
+    if dry_run {
+        return;
+    }
+    fs::write(&output, to_json(&new).pretty(2)).unwrap_or_else(|e| panic!("Failed to write {output}: {e}"));
+    log::info!("{} instruments written to {output}", new.len());
+}
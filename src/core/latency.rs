@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::thread;
+use std::time::Duration;
+use crate::core::dto::Exchange;
+
+// buckets per power of two, ~6% value error
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+// exact values below
+const LINEAR_LIMIT: u64 = 2 * SUB_BUCKETS as u64;
+const BUCKETS: usize = LINEAR_LIMIT as usize + (64 - SUB_BUCKET_BITS as usize - 1) * SUB_BUCKETS;
+
+/// Checkpoints of the ticker to order path, each measured from the socket read.
+/// `SocketRead` is measured from the exchange event time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    SocketRead,
+    QueuePush,
+    QueuePop,
+    Decision,
+    OrderEncode,
+    FixSend,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [Stage::SocketRead, Stage::QueuePush, Stage::QueuePop, Stage::Decision, Stage::OrderEncode, Stage::FixSend];
+}
+
+/// Log-linear histogram of the ns values
+#[derive(Clone)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    min: u64,
+    max: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self { counts: vec![0; BUCKETS], count: 0, min: u64::MAX, max: 0 }
+    }
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn index(value: u64) -> usize {
+        if value < LINEAR_LIMIT {
+            return value as usize;
+        }
+        let exp = 63 - value.leading_zeros();
+        let sub = (value >> (exp - SUB_BUCKET_BITS)) as usize & (SUB_BUCKETS - 1);
+        LINEAR_LIMIT as usize + (exp - SUB_BUCKET_BITS - 1) as usize * SUB_BUCKETS + sub
+    }
+
+    // the highest value of the bucket
+    fn bucket_high(index: usize) -> u64 {
+        if index < LINEAR_LIMIT as usize {
+            return index as u64;
+        }
+        let exp = ((index - LINEAR_LIMIT as usize) / SUB_BUCKETS) as u32 + SUB_BUCKET_BITS + 1;
+        let sub = ((index - LINEAR_LIMIT as usize) % SUB_BUCKETS) as u64;
+        let width = 1u64 << (exp - SUB_BUCKET_BITS);
+        ((SUB_BUCKETS as u64 + sub) << (exp - SUB_BUCKET_BITS)) + (width - 1)
+    }
+
+    pub fn record(&mut self, value: u64) {
+        self.counts[Self::index(value)] += 1;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> u64 {
+        if self.count == 0 { 0 } else { self.min }
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// The value the `percentile` (0..100) of the records are less or equal to
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((percentile / 100.0 * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Self::bucket_high(index).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Per exchange and stage histograms, written by the stream, strategy and OMS threads
+#[derive(Default)]
+pub struct LatencyMonitor {
+    histograms: Mutex<HashMap<(Exchange, Stage), Histogram>>,
+}
+
+pub static LATENCY: LazyLock<LatencyMonitor> = LazyLock::new(LatencyMonitor::default);
+
+impl LatencyMonitor {
+    /// Skipped if the start is unknown
+    pub fn record(&self, exchange: &Exchange, stage: Stage, from_ts: u128, to_ts: u128) {
+        if from_ts == 0 || to_ts < from_ts {
+            return;
+        }
+        let value = u64::try_from(to_ts - from_ts).unwrap_or(u64::MAX);
+        self.histograms.lock().expect("Can't get the lock")
+            .entry((exchange.clone(), stage))
+            .or_default()
+            .record(value);
+    }
+
+    /// Copy of the histograms, reset if `reset`
+    pub fn snapshot(&self, reset: bool) -> HashMap<(Exchange, Stage), Histogram> {
+        let mut histograms = self.histograms.lock().expect("Can't get the lock");
+        if reset {
+            std::mem::take(&mut *histograms)
+        } else {
+            histograms.clone()
+        }
+    }
+
+    /// Log the percentiles of the interval in µs
+    pub fn report(&self) {
+        let histograms = self.snapshot(true);
+        let mut keys: Vec<&(Exchange, Stage)> = histograms.keys().collect();
+        keys.sort_by_key(|(exchange, stage)| (exchange.as_str(), *stage));
+        for key in keys {
+            let histogram = &histograms[key];
+            let us = |value: u64| value as f64 / 1000.0;
+            log::info!(
+                "Latency {:?} {:?}: count {} p50 {:.1}us p90 {:.1}us p99 {:.1}us p99.9 {:.1}us max {:.1}us",
+                key.0, key.1, histogram.count(),
+                us(histogram.percentile(50.0)), us(histogram.percentile(90.0)), us(histogram.percentile(99.0)),
+                us(histogram.percentile(99.9)), us(histogram.max()),
+            );
+        }
+    }
+
+    pub fn spawn_reporter(interval: Duration) {
+        thread::Builder::new().name("latency_report".to_string()).spawn(move || loop {
+            thread::sleep(interval);
+            LATENCY.report();
+        }).expect("Failed to spawn latency report thread");
+    }
+}
@@ -0,0 +1,125 @@
+use std::env;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use json::{object, JsonValue};
+use crate::core::dto::Order;
+use crate::core::utils::{decimal_to_json, time};
+
+/// Target of the event records, `RUST_LOG=untitled::event=off` disables them
+pub const EVENT_TARGET: &str = "untitled::event";
+
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    // one json object per line
+    Json,
+}
+
+impl LogFormat {
+    /// `LOG_FORMAT=json`, text otherwise
+    pub fn from_env() -> Self {
+        match env::var("LOG_FORMAT").map(|value| value.to_lowercase()).as_deref() {
+            Ok("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+pub fn log_format() -> LogFormat {
+    if JSON_FORMAT.load(Ordering::Relaxed) { LogFormat::Json } else { LogFormat::Text }
+}
+
+pub fn init(format: LogFormat) {
+    JSON_FORMAT.store(format == LogFormat::Json, Ordering::Relaxed);
+    env_logger::builder()
+        .format(move |buf, record| {
+            let current_thread = thread::current();
+            let thread_name = current_thread.name().unwrap_or("");
+            if format == LogFormat::Json {
+                let line = format_json_record(time() / 1_000_000, record.level().as_str(), thread_name, record.target(), &record.args().to_string());
+                return writeln!(buf, "{line}");
+            }
+            let ts = buf.timestamp_millis();
+            let style = buf.default_level_style(record.level());
+            let level = record.level();
+            let target = record.target();
+            let args = record.args();
+            writeln!(
+                buf,
+                "[{ts} {style}{level}{style:#} {thread_name}] {target}] {args}",
+            )
+        })
+        .init();
+}
+
+/// The record json line, the fields of the event records are merged into the top level object
+pub fn format_json_record(ts_millis: u128, level: &str, thread: &str, target: &str, message: &str) -> String {
+    let mut line = object! {
+        ts: ts_millis as u64,
+        level: level,
+        thread: thread,
+    };
+    if target == EVENT_TARGET && message.starts_with('{') && message.len() > 2 {
+        let head = line.dump();
+        return format!("{},{}", &head[..head.len() - 1], &message[1..]);
+    }
+    line["target"] = target.into();
+    line["message"] = message.into();
+    line.dump()
+}
+
+/// The event json object in the json format, `event key=value ...` in the text one
+pub fn format_event(format: LogFormat, event: &str, fields: &JsonValue) -> String {
+    match format {
+        LogFormat::Json => {
+            let mut data = object! { event: event };
+            for (key, value) in fields.entries() {
+                data[key] = value.clone();
+            }
+            data.dump()
+        }
+        LogFormat::Text => {
+            let mut text = event.to_string();
+            for (key, value) in fields.entries() {
+                match value.as_str() {
+                    Some(value) => text.push_str(&format!(" {key}={value}")),
+                    None => text.push_str(&format!(" {key}={}", value.dump())),
+                }
+            }
+            text
+        }
+    }
+}
+
+/// Log the key event with the typed fields
+pub fn log_event(level: log::Level, event: &str, fields: JsonValue) {
+    if log::log_enabled!(target: EVENT_TARGET, level) {
+        log::log!(target: EVENT_TARGET, level, "{}", format_event(log_format(), event, &fields));
+    }
+}
+
+pub fn order_fields(order: &Order) -> JsonValue {
+    let fees: Vec<JsonValue> = order.fees.iter()
+        .map(|(currency, fee)| object! { currency: currency.as_str(), amount: decimal_to_json(*fee) })
+        .collect();
+    object! {
+        timestamp: order.timestamp as u64,
+        exchange: order.instrument.exchange.as_str(),
+        symbol: order.instrument.symbol.as_str(),
+        strategy_id: order.strategy_id.as_str(),
+        client_order_id: order.client_order_id.as_str(),
+        exchange_order_id: order.exchange_order_id.as_str(),
+        side: format!("{:?}", order.side),
+        order_type: format!("{:?}", order.order_type),
+        status: format!("{:?}", order.status),
+        price: decimal_to_json(order.price),
+        amount: decimal_to_json(order.amount),
+        amount_quote: decimal_to_json(order.amount_quote),
+        amount_filled: decimal_to_json(order.amount_filled),
+        fees: fees,
+        error: order.error.as_str(),
+    }
+}
@@ -1,55 +1,197 @@
 use std::collections::HashMap;
 use std::fs;
-use std::sync::Arc;
-use crate::core::dto::{Exchange, Instrument};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use json::JsonValue;
+use crate::core::bus::EventBus;
+use crate::core::dto::{Exchange, Instrument, InstrumentId, InstrumentsUpdate, DTO};
+use crate::core::utils::{json_to_decimal, time};
 
 #[derive(Debug)]
 pub struct InstrumentsMap {
     pub map: HashMap<Exchange, HashMap<String, Arc<Instrument>>>,
+    // ids are never reused, even for the removed instruments
+    next_id: u32,
 }
 
 impl InstrumentsMap {
     pub fn from_json(path: &str) -> Self {
         let raw = fs::read_to_string(path).expect("Failed to read tickers file");
         let data = json::parse(&raw).expect("Failed to parse tickers");
+        Self::from_instruments(Self::parse_instruments(&data).expect("Failed to parse instruments"))
+    }
+
+    pub fn from_instruments(instruments: Vec<Instrument>) -> Self {
+        Self::build(instruments, None)
+    }
+
+    /// New map from the instruments, the ones known by this map keep their ids
+    pub fn rebuild(&self, instruments: Vec<Instrument>) -> Self {
+        Self::build(instruments, Some(self))
+    }
 
+    fn build(instruments: Vec<Instrument>, previous: Option<&InstrumentsMap>) -> Self {
         let mut map = HashMap::new();
+        let mut next_id = previous.map_or(1, |p| p.next_id);
 
         for exchange in Exchange::iterator() {
             map.insert((*exchange).clone(), HashMap::default());
         }
 
-        for raw in data.members() {
-            let exchange = Exchange::from_str(raw["exchange"].as_str().unwrap());
-            let exchange_map = map.get_mut(&exchange).unwrap();
-
-            let symbol = raw["symbol"].as_str().unwrap().to_string();
-            let base = raw["base"].as_str().unwrap().to_string();
-            let quote = raw["quote"].as_str().unwrap().to_string();
-            let instrument = Arc::new(Instrument {
-                exchange: exchange,
-                symbol: symbol.clone(),
-                base: base.clone(),
-                quote: quote.clone(),
-                amount_precision: raw["amount_precision"].as_usize().unwrap(),
-                price_precision: raw["price_precision"].as_usize().unwrap(),
-                order_amount_max: raw["order_amount_max"].as_f64().unwrap(),
-                order_amount_min: raw["order_amount_min"].as_f64().unwrap(),
-                order_notional_min: raw["order_notional_min"].as_f64().unwrap(),
-                order_notional_max: raw["order_notional_max"].as_f64().unwrap(),
-                maker_fee: raw["maker_fee"].as_f64().unwrap(),
-                taker_fee: raw["taker_fee"].as_f64().unwrap(),
-            });
-            exchange_map.insert(symbol.clone(), Arc::clone(&instrument));
+        for mut instrument in instruments {
+            instrument.id = match previous.and_then(|p| p.get(&instrument.exchange, &instrument.symbol)) {
+                Some(known) => known.id,
+                None => {
+                    let id = InstrumentId(next_id);
+                    next_id += 1;
+                    id
+                }
+            };
+            let instrument = Arc::new(instrument);
+            let exchange_map = map.get_mut(&instrument.exchange).unwrap();
+            let (base, quote) = (&instrument.base, &instrument.quote);
+            exchange_map.insert(instrument.symbol.clone(), Arc::clone(&instrument));
             exchange_map.insert(format!("{base}/{quote}").to_uppercase(), Arc::clone(&instrument));
             exchange_map.insert(format!("{base}{quote}").to_uppercase(), Arc::clone(&instrument));
             exchange_map.insert(format!("{base}/{quote}").to_lowercase(), Arc::clone(&instrument));
             exchange_map.insert(format!("{base}{quote}").to_lowercase(), Arc::clone(&instrument));
         }
-        Self { map }
+        Self { map, next_id }
+    }
+
+    pub fn parse_instruments(data: &JsonValue) -> Result<Vec<Instrument>, String> {
+        data.members().map(|raw| {
+            let invalid = |field: &str| format!("Invalid '{field}': {raw}");
+            let str_field = |field: &str| raw[field].as_str().map(str::to_string).ok_or(invalid(field));
+            let usize_field = |field: &str| raw[field].as_usize().ok_or(invalid(field));
+            let decimal_field = |field: &str| json_to_decimal(&raw[field]).ok_or(invalid(field));
+            let f64_field = |field: &str| raw[field].as_f64().ok_or(invalid(field));
+
+            let exchange = str_field("exchange")?;
+            Ok(Instrument {
+                id: InstrumentId::default(),
+                exchange: Exchange::iterator().find(|e| e.as_str() == exchange).cloned().ok_or(invalid("exchange"))?,
+                symbol: str_field("symbol")?,
+                base: str_field("base")?,
+                quote: str_field("quote")?,
+                amount_precision: usize_field("amount_precision")?,
+                price_precision: usize_field("price_precision")?,
+                order_amount_max: decimal_field("order_amount_max")?,
+                order_amount_min: decimal_field("order_amount_min")?,
+                order_notional_min: decimal_field("order_notional_min")?,
+                order_notional_max: decimal_field("order_notional_max")?,
+                maker_fee: f64_field("maker_fee")?,
+                taker_fee: f64_field("taker_fee")?,
+            })
+        }).collect()
     }
 
     pub fn get(&self, exchange: &Exchange, symbol: &str) -> Option<&Arc<Instrument>> {
         self.map.get(exchange)?.get(symbol)
     }
+
+    /// Unique instruments, without the symbol aliases
+    pub fn instruments(&self) -> impl Iterator<Item = &Arc<Instrument>> {
+        self.map.values().flat_map(|m| m.iter().filter(|(k, v)| **k == v.symbol).map(|(_, v)| v))
+    }
+
+    /// Symbols of the exchange, one per instrument
+    pub fn symbols(&self, exchange: &Exchange) -> Vec<String> {
+        self.instruments().filter(|i| i.exchange == *exchange).map(|i| i.symbol.clone()).collect()
+    }
+}
+
+/// Instruments map that can be replaced at runtime. Readers keep the snapshot
+/// and refresh it when the version changes.
+pub struct InstrumentsRegistry {
+    path: String,
+    current: RwLock<Arc<InstrumentsMap>>,
+    version: AtomicUsize,
+}
+
+impl InstrumentsRegistry {
+    pub fn new(path: &str, map: InstrumentsMap) -> Self {
+        Self { path: path.to_string(), current: RwLock::new(Arc::new(map)), version: AtomicUsize::new(0) }
+    }
+
+    pub fn from_json(path: &str) -> Self {
+        Self::new(path, InstrumentsMap::from_json(path))
+    }
+
+    pub fn load(&self) -> Arc<InstrumentsMap> {
+        Arc::clone(&self.current.read().expect("Can't get the lock"))
+    }
+
+    pub fn version(&self) -> usize {
+        self.version.load(Ordering::Acquire)
+    }
+
+    pub fn get(&self, exchange: &Exchange, symbol: &str) -> Option<Arc<Instrument>> {
+        self.current.read().expect("Can't get the lock").get(exchange, symbol).cloned()
+    }
+
+    /// Replace the instruments, returns the difference with the previous ones
+    pub fn swap(&self, instruments: Vec<Instrument>) -> InstrumentsUpdate {
+        let mut current = self.current.write().expect("Can't get the lock");
+        let map = current.rebuild(instruments);
+        let mut update = InstrumentsUpdate { timestamp: time(), added: vec![], removed: vec![], changed: vec![] };
+        for instrument in map.instruments() {
+            match current.get(&instrument.exchange, &instrument.symbol) {
+                None => update.added.push(Arc::clone(instrument)),
+                Some(old) if !old.has_same_params(instrument) => update.changed.push(Arc::clone(instrument)),
+                _ => {}
+            }
+        }
+        for instrument in current.instruments() {
+            if map.get(&instrument.exchange, &instrument.symbol).is_none() {
+                update.removed.push(Arc::clone(instrument));
+            }
+        }
+
+        if !update.is_empty() {
+            *current = Arc::new(map);
+            self.version.fetch_add(1, Ordering::Release);
+        }
+        update
+    }
+
+    /// Re-read the instruments file
+    pub fn reload(&self) -> Result<InstrumentsUpdate, String> {
+        let raw = fs::read_to_string(&self.path).map_err(|err| format!("Failed to read {}: {err}", self.path))?;
+        let data = json::parse(&raw).map_err(|err| format!("Failed to parse {}: {err}", self.path))?;
+        Ok(self.swap(InstrumentsMap::parse_instruments(&data)?))
+    }
+
+    /// Reload the instruments on the file change and notify via the bus
+    pub fn watch(registry: &Arc<Self>, bus: &Arc<EventBus>, interval: Duration) {
+        let registry = Arc::clone(registry);
+        let bus = Arc::clone(bus);
+        let modified = |path: &str| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        thread::Builder::new().name("instruments_watcher".to_string()).spawn(move || {
+            let mut last_modified: Option<SystemTime> = modified(&registry.path);
+            loop {
+                thread::sleep(interval);
+                let current_modified = modified(&registry.path);
+                if current_modified == last_modified {
+                    continue;
+                }
+                last_modified = current_modified;
+
+                match registry.reload() {
+                    Ok(update) if update.is_empty() => log::info!("Instruments file changed, no updates"),
+                    Ok(update) => {
+                        log::info!(
+                            "Instruments reloaded: {} added, {} removed, {} changed",
+                            update.added.len(), update.removed.len(), update.changed.len()
+                        );
+                        bus.publish(DTO::InstrumentsUpdate(update));
+                    }
+                    Err(err) => log::error!("Instruments reload failed: {err}"),
+                }
+            }
+        }).expect("Failed to spawn instruments watcher thread");
+    }
 }
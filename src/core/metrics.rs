@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use crate::core::http::{self, HttpResponse};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+/// Monotonic counter, cheap to clone and update from the hot path
+#[derive(Clone, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Gauge, the f64 is stored by bits
+#[derive(Clone, Default)]
+pub struct Gauge(Arc<AtomicU64>);
+
+impl Gauge {
+    pub fn set(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+enum Series {
+    Counter(Counter),
+    Gauge(Gauge),
+    // evaluated on the scrape
+    GaugeFn(Box<dyn Fn() -> f64 + Send + Sync>),
+}
+
+struct Family {
+    kind: MetricKind,
+    help: String,
+    // by the rendered labels
+    series: BTreeMap<String, Series>,
+}
+
+/// Counters and gauges exposed in the Prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    families: Mutex<BTreeMap<String, Family>>,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels.iter().map(|(name, value)| format!("{name}=\"{}\"", escape(value))).collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+fn render_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf".to_string() } else { "-Inf".to_string() }
+    } else {
+        value.to_string()
+    }
+}
+
+impl Metrics {
+    /// The series of the family, registered on the first use
+    fn series<T>(&self, name: &str, help: &str, kind: MetricKind, labels: &[(&str, &str)], get: impl FnOnce(&mut Family, String) -> T) -> T {
+        let mut families = self.families.lock().expect("Can't get the lock");
+        let family = families.entry(name.to_string()).or_insert_with(|| Family {
+            kind,
+            help: help.to_string(),
+            series: BTreeMap::new(),
+        });
+        if family.kind != kind {
+            panic!("Metric {name} is registered as {:?}", family.kind);
+        }
+        get(family, render_labels(labels))
+    }
+
+    pub fn counter(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> Counter {
+        self.series(name, help, MetricKind::Counter, labels, |family, labels| {
+            match family.series.entry(labels).or_insert_with(|| Series::Counter(Counter::default())) {
+                Series::Counter(counter) => counter.clone(),
+                _ => unreachable!(),
+            }
+        })
+    }
+
+    pub fn gauge(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> Gauge {
+        self.series(name, help, MetricKind::Gauge, labels, |family, labels| {
+            match family.series.entry(labels).or_insert_with(|| Series::Gauge(Gauge::default())) {
+                Series::Gauge(gauge) => gauge.clone(),
+                _ => panic!("Gauge {name} is registered as the function"),
+            }
+        })
+    }
+
+    /// Gauge read on every scrape, replaces the previous function of the labels
+    pub fn gauge_fn(&self, name: &str, help: &str, labels: &[(&str, &str)], value: impl Fn() -> f64 + Send + Sync + 'static) {
+        self.series(name, help, MetricKind::Gauge, labels, |family, labels| {
+            family.series.insert(labels, Series::GaugeFn(Box::new(value)));
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let families = self.families.lock().expect("Can't get the lock");
+        let mut text = String::new();
+        for (name, family) in families.iter() {
+            text.push_str(&format!("# HELP {name} {}\n", family.help));
+            text.push_str(&format!("# TYPE {name} {}\n", family.kind.as_str()));
+            for (labels, series) in family.series.iter() {
+                let value = match series {
+                    Series::Counter(counter) => counter.get().to_string(),
+                    Series::Gauge(gauge) => render_value(gauge.get()),
+                    Series::GaugeFn(value) => render_value(value()),
+                };
+                text.push_str(&format!("{name}{labels} {value}\n"));
+            }
+        }
+        text
+    }
+
+    /// Serve `GET /metrics` on the address, returns the bound one
+    pub fn serve(&'static self, address: &str) -> SocketAddr {
+        http::serve("metrics", address, move |request| {
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/metrics") => HttpResponse::new("200 OK", "text/plain; version=0.0.4", self.render()),
+                _ => HttpResponse::not_found(),
+            }
+        })
+    }
+}
@@ -1,12 +1,28 @@
+pub mod alerting;
 pub mod api;
+pub mod arbitration;
+pub mod bus;
+pub mod control;
+pub mod decimal;
 pub mod dto;
+pub mod fees;
 pub mod handlers;
+pub mod health;
+pub mod http;
+pub mod instruments_fetcher;
+pub mod latency;
+pub mod logging;
 pub mod map;
+pub mod metrics;
 pub mod utils;
 pub mod streams;
 pub mod oes;
 pub mod strategies;
 pub mod oms;
+pub mod order_builder;
+pub mod order_tracker;
 pub mod order_sizing;
 pub mod price_tickers_graph;
 pub mod price_ticker_collector;
+pub mod rate_limit;
+pub mod risk;
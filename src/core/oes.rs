@@ -1,21 +1,29 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use crate::core::dto::{Instrument, Order, OrderSide, OrderStatus, OrderType, PriceTicker};
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Instrument, InstrumentId, Order, OrderSide, OrderStatus, OrderType, PriceTicker, TimeInForce};
+use crate::core::fees::{FeeModel, Liquidity};
+
+const FEE_SCALE: u32 = 8;
 
 pub struct OrderExecutionSimulator {
-    pub balances: HashMap<String, f64>,
-    fee: f64
+    pub balances: HashMap<String, Decimal>,
+    // resting LIMIT/LIMIT_MAKER orders
+    pub open_orders: Vec<Order>,
+    fee_model: FeeModel,
+    // (token, quote) -> last token bid, to value the fees paid with the token
+    token_prices: HashMap<(String, String), Decimal>,
 }
 
 impl OrderExecutionSimulator {
     pub fn new(
-        balances: HashMap<String, f64>,
-        fee: f64,
+        balances: HashMap<String, Decimal>,
+        fee_model: FeeModel,
     ) -> Self {
-        Self { balances, fee }
+        Self { balances, open_orders: vec![], fee_model, token_prices: HashMap::new() }
     }
 
-    pub fn execute_orders_chain(&mut self, orders_direction: Vec<&(Arc<Instrument>, OrderSide)>, _tickers_map: &HashMap<Arc<Instrument>, PriceTicker>) -> Vec<Order>  {
+    pub fn execute_orders_chain(&mut self, orders_direction: Vec<&(Arc<Instrument>, OrderSide)>, _tickers_map: &HashMap<InstrumentId, PriceTicker>) -> Vec<Order>  {
         let mut executed_orders = Vec::new();
         log::info!("orders_direction: {orders_direction:?}");
         for (instrument, order_side) in orders_direction {
@@ -26,17 +34,17 @@ impl OrderExecutionSimulator {
             log::info!("instrument: {instrument:?} order_side: {order_side:?} balance: {balances:?}");
 
             if *order_side == OrderSide::Buy {  // we have quote on balance
-                amount = 0.;
-                amount_quote = self.balances.get(&instrument.quote).unwrap().clone();
+                amount = Decimal::ZERO;
+                amount_quote = *self.balances.get(&instrument.quote).unwrap();
             } else {  // we have base on balance
-                amount = self.balances.get(&instrument.base).unwrap().clone();
-                amount_quote = 0.;
+                amount = *self.balances.get(&instrument.base).unwrap();
+                amount_quote = Decimal::ZERO;
             }
 
             let mut order = Order::new();
-            order.instrument = Arc::clone(&instrument);
+            order.instrument = Arc::clone(instrument);
             order.order_type = OrderType::Market;
-            order.side = order_side.clone();
+            order.side = *order_side;
             order.amount = amount;
             order.amount_quote = amount_quote;
 
@@ -49,13 +57,14 @@ impl OrderExecutionSimulator {
     }
 
     /// Simulate execution of a batch of MARKET orders, update balances, and return the updated orders
-    pub fn execute_market_orders(&mut self, orders: Vec<Order>, tickers_map: &HashMap<Arc<Instrument>, PriceTicker>) -> Vec<Order> {
+    pub fn execute_market_orders(&mut self, orders: Vec<Order>, tickers_map: &HashMap<InstrumentId, PriceTicker>) -> Vec<Order> {
+        tickers_map.values().for_each(|ticker| self.update_token_price(ticker));
         let mut executed_orders = Vec::new();
         for mut order in orders {
             // Find the price ticker for the instrument in the order
-            if let Some(ticker) = tickers_map.get(&order.instrument) {
+            if let Some(ticker) = tickers_map.get(&order.instrument.id) {
                 // Handle the case where amount is 0 but amount_quote is provided
-                if order.amount == 0.0 && order.amount_quote > 0.0 {
+                if order.amount.is_zero() && order.amount_quote > Decimal::ZERO {
                     // Calculate amount from amount_quote based on the current price
                     if order.side == OrderSide::Buy {
                         order.amount = order.amount_quote / ticker.ask; // Calculate how much to buy
@@ -63,54 +72,11 @@ impl OrderExecutionSimulator {
                         order.amount = order.amount_quote / ticker.bid; // Calculate how much to sell
                     }
                 }
-                match order.side {
-                    OrderSide::Buy => {
-                        // BUY: Fill at the ask price
-                        let total_cost = order.amount * ticker.ask; // amount * ask price in quote currency
-
-                        // Check if the user has enough balance in the quote currency (e.g., USDT)
-                        let quote_balance = self
-                            .balances
-                            .entry(order.instrument.quote.clone())
-                            .or_insert(0.0);
-                        if *quote_balance >= total_cost {
-                            *quote_balance -= total_cost; // Deduct the total cost from the quote balance
-                            let base_balance = self
-                                .balances
-                                .entry(order.instrument.base.clone())
-                                .or_insert(0.0);
-                            *base_balance += order.amount * (1. - self.fee); // Add the bought base amount to the balance
-                            order.amount_filled = order.amount; // Fully fill the order
-                            order.amount_quote = total_cost;
-                            order.status = OrderStatus::Filled;
-                        } else {
-                            order.status = OrderStatus::Canceled; // Not enough balance, cancel the order
-                        }
-                    }
-                    OrderSide::Sell => {
-                        // SELL: Fill at the bid price
-                        let total_proceeds = order.amount * ticker.bid; // amount * bid price in quote currency
-
-                        // Check if the user has enough balance in the base currency (e.g., BTC)
-                        let base_balance = self
-                            .balances
-                            .entry(order.instrument.base.clone())
-                            .or_insert(0.0);
-                        if *base_balance >= order.amount {
-                            *base_balance -= order.amount; // Deduct the sold amount from the base balance
-                            let quote_balance = self
-                                .balances
-                                .entry(order.instrument.quote.clone())
-                                .or_insert(0.0);
-                            *quote_balance += total_proceeds * (1. - self.fee); // Add the proceeds to the quote balance
-                            order.amount_filled = order.amount; // Fully fill the order
-                            order.amount_quote = total_proceeds;
-                            order.status = OrderStatus::Filled;
-                        } else {
-                            order.status = OrderStatus::Canceled; // Not enough base currency, cancel the order
-                        }
-                    }
-                }
+                let price = match order.side {
+                    OrderSide::Buy => ticker.ask, // BUY: Fill at the ask price
+                    OrderSide::Sell => ticker.bid, // SELL: Fill at the bid price
+                };
+                self.fill(&mut order, price, Liquidity::Taker);
             } else {
                 // If no ticker data is found for the instrument, mark the order as CANCELED
                 order.status = OrderStatus::Canceled;
@@ -122,4 +88,141 @@ impl OrderExecutionSimulator {
 
         executed_orders
     }
+
+    /// Simulate placing of LIMIT/LIMIT_MAKER orders. Marketable orders are filled immediately,
+    /// the rest stay in `open_orders` (GTC) or get canceled (IOC/FOK)
+    pub fn execute_limit_orders(&mut self, orders: Vec<Order>, tickers_map: &HashMap<InstrumentId, PriceTicker>) -> Vec<Order> {
+        tickers_map.values().for_each(|ticker| self.update_token_price(ticker));
+        let mut executed_orders = Vec::new();
+        for mut order in orders {
+            if order.amount.is_zero() && order.amount_quote > Decimal::ZERO {
+                order.amount = order.amount_quote / order.price;
+            }
+
+            let ticker = match tickers_map.get(&order.instrument.id) {
+                Some(ticker) => ticker,
+                None => {
+                    order.status = OrderStatus::Canceled;
+                    executed_orders.push(order);
+                    continue;
+                }
+            };
+
+            let crossing_price = match order.side {
+                OrderSide::Buy if ticker.ask <= order.price => Some(ticker.ask),
+                OrderSide::Sell if ticker.bid >= order.price => Some(ticker.bid),
+                _ => None,
+            };
+
+            match crossing_price {
+                Some(_) if order.order_type == OrderType::LimitMaker => {
+                    order.status = OrderStatus::Error;
+                    order.error = "Order would immediately match and take.".to_string();
+                }
+                Some(price) => {
+                    self.fill(&mut order, price, Liquidity::Taker);
+                }
+                None if order.time_in_force == TimeInForce::GTC => {
+                    order.status = OrderStatus::New;
+                    self.open_orders.push(order.clone());
+                }
+                None => {
+                    order.status = OrderStatus::Canceled;
+                }
+            }
+            executed_orders.push(order);
+        }
+
+        executed_orders
+    }
+
+    /// Fill resting orders crossed by the price ticker at their limit price
+    pub fn on_price_ticker(&mut self, ticker: &PriceTicker) -> Vec<Order> {
+        self.update_token_price(ticker);
+        let mut crossed = Vec::new();
+        let mut i = 0;
+        while i < self.open_orders.len() {
+            let order = &self.open_orders[i];
+            let is_crossed = order.instrument.id == ticker.instrument.id && match order.side {
+                OrderSide::Buy => ticker.ask <= order.price,
+                OrderSide::Sell => ticker.bid >= order.price,
+            };
+            if is_crossed {
+                crossed.push(self.open_orders.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+
+        for order in crossed.iter_mut() {
+            let price = order.price;
+            self.fill(order, price, Liquidity::Maker);
+        }
+        crossed
+    }
+
+    pub fn cancel_order(&mut self, client_order_id: &str) -> Option<Order> {
+        let pos = self.open_orders.iter().position(|o| o.client_order_id == client_order_id)?;
+        let mut order = self.open_orders.remove(pos);
+        order.status = OrderStatus::Canceled;
+        Some(order)
+    }
+
+    fn fill(&mut self, order: &mut Order, price: Decimal, liquidity: Liquidity) {
+        let instrument = Arc::clone(&order.instrument);
+        let notional = order.amount * price; // amount * price in quote currency
+        let (spent, spent_amount, received, received_amount) = match order.side {
+            OrderSide::Buy => (&instrument.quote, notional, &instrument.base, order.amount),
+            OrderSide::Sell => (&instrument.base, order.amount, &instrument.quote, notional),
+        };
+
+        let spent_balance = self.balances.entry(spent.clone()).or_insert(Decimal::ZERO);
+        if *spent_balance < spent_amount {
+            order.status = OrderStatus::Canceled; // Not enough balance, cancel the order
+            return;
+        }
+        *spent_balance -= spent_amount;
+        *self.balances.entry(received.clone()).or_insert(Decimal::ZERO) += received_amount;
+
+        let (fee_currency, fee) = self.fee(&instrument, order.side, liquidity, notional, received_amount);
+        if !fee.is_zero() {
+            *self.balances.entry(fee_currency.clone()).or_insert(Decimal::ZERO) -= fee;
+            order.fees.push((fee_currency, fee));
+        }
+        order.amount_filled = order.amount; // Fully fill the order
+        order.amount_quote = notional;
+        order.status = OrderStatus::Filled;
+    }
+
+    /// Fee currency and amount of the fill. The token fee falls back to the proceeds
+    /// when the token price or balance is unknown, as the exchanges do
+    fn fee(&self, instrument: &Instrument, side: OrderSide, liquidity: Liquidity, notional: Decimal, received_amount: Decimal) -> (String, Decimal) {
+        let rate = Decimal::from_f64(self.fee_model.rate(instrument, liquidity), FEE_SCALE);
+        if let Some(token) = self.fee_model.token(&instrument.exchange) {
+            let token_fee = if token == instrument.quote {
+                Some(notional * rate)
+            } else {
+                self.token_prices.get(&(token.to_string(), instrument.quote.clone())).map(|price| notional * rate / *price)
+            };
+            match token_fee {
+                Some(fee) if self.balances.get(token).is_some_and(|balance| *balance >= fee) => return (token.to_string(), fee),
+                _ => log::warn!("Can't pay the fee with {token}, deduct it from the proceeds"),
+            }
+        }
+        let currency = match side {
+            OrderSide::Buy => instrument.base.clone(),
+            OrderSide::Sell => instrument.quote.clone(),
+        };
+        // no discount without the token
+        let rate = Decimal::from_f64(self.fee_model.full_rate(instrument, liquidity), FEE_SCALE);
+        (currency, received_amount * rate)
+    }
+
+    fn update_token_price(&mut self, ticker: &PriceTicker) {
+        if let Some(token) = self.fee_model.token(&ticker.instrument.exchange) {
+            if ticker.instrument.base == token && ticker.bid > Decimal::ZERO {
+                self.token_prices.insert((token.to_string(), ticker.instrument.quote.clone()), ticker.bid);
+            }
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use std::{fs, io, thread};
+use std::collections::VecDeque;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::path::Path;
@@ -7,7 +8,6 @@ use std::time::Duration;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
 use chrono::NaiveDateTime;
-use crossbeam_queue::ArrayQueue;
 use ed25519_dalek::pkcs8::DecodePrivateKey;
 use ed25519_dalek::{Signer, SigningKey};
 use fefix::definitions::{fix44, HardCodedFixFieldDefinition};
@@ -18,42 +18,84 @@ use fefix::tagvalue::{Decoder, DecoderStreaming, Encoder, EncoderHandle, Message
 use rustls::{ClientConnection, RootCertStore};
 use uuid::Uuid;
 use crate::core::api::{OrderListener};
-use crate::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order, OrderSide, OrderStatus, OrderType, DTO};
-use crate::core::map::InstrumentsMap;
-use crate::core::utils::time;
+use crate::core::bus::{Channel, EventBus};
+use crate::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order, OrderSide, OrderStatus, OrderType, TimeInForce, DTO};
+use crate::core::latency::{Stage, LATENCY};
+use crate::core::logging::{log_event, order_fields};
+use crate::core::map::InstrumentsRegistry;
+use crate::core::rate_limit::{RateLimitType, RateLimiter};
+use crate::core::decimal::Decimal;
+use crate::core::utils::{time, RoundingMode};
+
+// the limits are re-read from the exchange counters
+const LIMITS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
 
 pub struct OMS {
-    in_queue: Arc<ArrayQueue<DTO>>,
-    out_queue: Arc<ArrayQueue<DTO>>,
-    instruments_map: Arc<InstrumentsMap>,
+    in_queue: Arc<Channel<Order>>,
+    out_bus: Arc<EventBus>,
+    instruments: Arc<InstrumentsRegistry>,
     signing_key_path: String,
     api_key: String,
+    // orders waiting for the rate limit longer are rejected
+    max_throttle_delay: Duration,
 }
 
 
 impl OMS {
     pub fn new(
-        in_queue: Arc<ArrayQueue<DTO>>,
-        out_queue: Arc<ArrayQueue<DTO>>,
-        instruments_map: Arc<InstrumentsMap>,
+        in_queue: Arc<Channel<Order>>,
+        out_bus: Arc<EventBus>,
+        instruments: Arc<InstrumentsRegistry>,
         signing_key_path: String,
         api_key: String,
     ) -> Self {
-        Self { in_queue, out_queue, instruments_map, signing_key_path, api_key }
+        Self {
+            in_queue,
+            out_bus,
+            instruments,
+            signing_key_path,
+            api_key,
+            max_throttle_delay: Duration::from_secs(1),
+        }
+    }
+
+    fn reject_order(&self, mut order: Order, reason: &str) {
+        if order.status == OrderStatus::Canceling {
+            // the order itself may be still open
+            log::error!("Cancel of the order {} dropped: {reason}", order.client_order_id);
+            return;
+        }
+        log::warn!("Reject order {}: {reason}", order.client_order_id);
+        order.timestamp = time();
+        order.status = OrderStatus::Error;
+        order.error = reason.to_string();
+        self.out_bus.publish(DTO::Order(order));
+    }
+
+    fn push_rate_limit_status(&self, status: MonitoringStatus) {
+        self.out_bus.publish(
+            DTO::MonitoringMessage(MonitoringMessage::new(
+                time(),
+                status,
+                MonitoringEntity::OrderRateLimit,
+                Exchange::Binance,
+                1,
+            ))
+        );
     }
 
     pub fn start(
-        in_queue: Arc<ArrayQueue<DTO>>,
-        out_queue: Arc<ArrayQueue<DTO>>,
-        instruments_map: Arc<InstrumentsMap>,
+        in_queue: Arc<Channel<Order>>,
+        out_bus: Arc<EventBus>,
+        instruments: Arc<InstrumentsRegistry>,
         signing_key_path: String,
         api_key: String,
     ) {
         thread::Builder::new().name("OMS".to_string()).spawn(move || {
             let mut oms = OMS::new(
                 in_queue,
-                out_queue,
-                instruments_map,
+                out_bus,
+                instruments,
                 signing_key_path,
                 api_key
             );
@@ -67,7 +109,7 @@ impl OMS {
                 "./data/binance-spot-fix-oe.xml",
                 &self.signing_key_path,
                 &self.api_key,
-                Arc::clone(&self.instruments_map),
+                Arc::clone(&self.instruments),
             );
             conn.logon();
 
@@ -77,7 +119,7 @@ impl OMS {
                     Some(DTO::MonitoringMessage(msg)) => {
                         match msg.status {
                             MonitoringStatus::Ok => {
-                                let _ = self.out_queue.push(DTO::MonitoringMessage(msg));
+                                self.out_bus.publish(DTO::MonitoringMessage(msg));
                                 break;
                             },
                             MonitoringStatus::Error => {
@@ -96,32 +138,68 @@ impl OMS {
             log::info!("OMS connected");
 
 
+            let mut pending_orders: VecDeque<(u128, Order)> = VecDeque::new();
+            let mut throttled = false;
+            let mut next_limits_ts = time() + LIMITS_REFRESH_INTERVAL.as_nanos();
+
             // process in/out messages
             loop {
-                match self.in_queue.pop() {
-                    Some(DTO::Order(order)) => {
-                        conn.on_order(&order);
-                    }
-                    None => {}
-                    other => {
-                        panic!("Unexpected message type: {:?}", other)
+                if next_limits_ts < time() {
+                    next_limits_ts = time() + LIMITS_REFRESH_INTERVAL.as_nanos();
+                    conn.request_limits();
+                }
+                if let Some(order) = self.in_queue.pop() {
+                    pending_orders.push_back((time(), order));
+                }
+
+                while let Some((received_ts, order)) = pending_orders.pop_front() {
+                    let ts = time();
+                    // cancels don't count to the orders limit
+                    let limits: &[RateLimitType] = if order.status == OrderStatus::Canceling {
+                        &[RateLimitType::Message]
+                    } else {
+                        &[RateLimitType::Order, RateLimitType::Message]
+                    };
+                    match conn.rate_limiter.try_acquire(limits, ts) {
+                        Ok(_) => {
+                            conn.on_order(&order);
+                        }
+                        Err(wait) => {
+                            if !throttled {
+                                log::warn!("Orders throttled for {}ms", wait.as_millis());
+                                throttled = true;
+                                self.push_rate_limit_status(MonitoringStatus::Error);
+                            }
+                            if Duration::from_nanos((ts - received_ts) as u64) + wait > self.max_throttle_delay {
+                                self.reject_order(order, "Rate limit exceeded");
+                                continue;
+                            }
+                            pending_orders.push_front((received_ts, order));
+                            break;
+                        }
                     }
-                };
+                }
+
+                if throttled && pending_orders.is_empty() {
+                    log::info!("Orders throttling finished");
+                    throttled = false;
+                    self.push_rate_limit_status(MonitoringStatus::Ok);
+                }
 
                 match conn.handle_stream() {
                     Some(DTO::MonitoringMessage(msg)) => {
                         match msg.status {
                             MonitoringStatus::Ok => {
-                                let _ = self.out_queue.push(DTO::MonitoringMessage(msg)).unwrap();
+                                self.out_bus.publish(DTO::MonitoringMessage(msg));
                             },
                             MonitoringStatus::Error => {
-                                let _ = self.out_queue.push(DTO::MonitoringMessage(msg)).unwrap();
+                                self.out_bus.publish(DTO::MonitoringMessage(msg));
                                 break;
                             }
                         };
                     },
                     Some(DTO::Order(order)) => {
-                        let _ = self.out_queue.push(DTO::Order(order)).unwrap();
+                        self.out_bus.publish(DTO::Order(order));
                     }
                     None => {},
                     other => {
@@ -130,6 +208,13 @@ impl OMS {
                 };
             }
 
+            for (_, order) in pending_orders {
+                self.reject_order(order, "OMS disconnected");
+            }
+            if throttled {
+                self.push_rate_limit_status(MonitoringStatus::Ok);
+            }
+
             log::warn!("OMS disconnected. Reconnect in 5 sec");
             thread::sleep(Duration::from_secs(5));
         }
@@ -191,7 +276,7 @@ impl StreamTLS {
     }
 }
 
-struct FixMessageEncoderHandler {
+pub struct FixMessageEncoderHandler {
     api_key: String,
     encoder: Encoder,
     signing_key: SigningKey,
@@ -201,6 +286,16 @@ struct FixMessageEncoderHandler {
 }
 
 impl FixMessageEncoderHandler {
+    pub fn new(signing_key: SigningKey, api_key: &str) -> Self {
+        Self {
+            encoder: Encoder::default(),
+            signing_key,
+            buffer: Default::default(),
+            msg_seq_num: 1,
+            api_key: api_key.to_string(),
+        }
+    }
+
     fn start_message(&mut self, msg_type: &[u8]) -> EncoderHandle<Vec<u8>> {
         let sender_comp_id = "EXAMPLE2";
         let target_comp_id = "SPOT";
@@ -287,21 +382,42 @@ impl FixMessageEncoderHandler {
 
         msg.set(fix44::CL_ORD_ID, order.client_order_id.as_str());
 
-        if order.amount > 0. {
-            msg.set(fix44::ORDER_QTY, order.amount);
+        if order.amount > Decimal::ZERO {
+            msg.set(fix44::ORDER_QTY, order.amount.to_string().as_str());
         }
 
         let order_type = match order.order_type {
             OrderType::Market => fix44::OrdType::Market,
             OrderType::Limit => fix44::OrdType::Limit,
-            OrderType::LimitMaker => fix44::OrdType::Market
+            OrderType::LimitMaker => fix44::OrdType::Limit
         };
 
         msg.set(fix44::ORD_TYPE, order_type);
 
+        if order.order_type != OrderType::Market {
+            // never cross the requested price because of the rounding
+            let rounding_mode = match order.side {
+                OrderSide::Buy => RoundingMode::Down,
+                OrderSide::Sell => RoundingMode::Up,
+            };
+            let price = order.price.round(order.instrument.price_precision as u32, rounding_mode);
+            msg.set(fix44::PRICE, price.to_string().as_str());
+        }
 
-        if order.order_type == OrderType::Limit {
-            msg.set(fix44::PRICE, order.price);
+        match order.order_type {
+            OrderType::Limit => {
+                let time_in_force = match order.time_in_force {
+                    TimeInForce::GTC => fix44::TimeInForce::GoodTillCancel,
+                    TimeInForce::IOC => fix44::TimeInForce::ImmediateOrCancel,
+                    TimeInForce::FOK => fix44::TimeInForce::FillOrKill,
+                };
+                msg.set(fix44::TIME_IN_FORCE, time_in_force);
+            }
+            OrderType::LimitMaker => {
+                // post-only
+                msg.set(fix44::EXEC_INST, "6");
+            }
+            OrderType::Market => {}
         }
 
         let side = match order.side {
@@ -312,18 +428,31 @@ impl FixMessageEncoderHandler {
         msg.set(fix44::SIDE, side);
         msg.set(fix44::SYMBOL, order.instrument.symbol.as_str());
 
-        if order.amount_quote > 0. {
-            msg.set(fix44::CASH_ORDER_QTY, order.amount_quote);
+        if order.amount_quote > Decimal::ZERO {
+            msg.set(fix44::CASH_ORDER_QTY, order.amount_quote.to_string().as_str());
         }
         msg.done().0
     }
+
+    /// The venue reports the canceled order with the original id in OrigClOrdID
+    pub fn create_cancel_message(&mut self, order: &Order) -> &[u8] {
+        let mut msg = self.start_message(b"F");
+
+        msg.set(fix44::CL_ORD_ID, Uuid::new_v4().to_string().as_str());
+        msg.set(fix44::ORIG_CL_ORD_ID, order.client_order_id.as_str());
+        msg.set(fix44::SYMBOL, order.instrument.symbol.as_str());
+        msg.done().0
+    }
 }
 
 pub struct BinanceFixConnection {
     stream: StreamTLS,
     encoder: FixMessageEncoderHandler,
     decoder: DecoderStreaming<Vec<u8>>,
-    instruments_map: Arc<InstrumentsMap>,
+    instruments: Arc<InstrumentsRegistry>,
+    pub rate_limiter: RateLimiter,
+    // the first limits response confirms the logon
+    logged_on: bool,
 }
 
 
@@ -332,7 +461,7 @@ impl BinanceFixConnection {
         spec_path: &str,
         signing_key_path: &str,
         api_key: &str,
-        instruments_map: Arc<InstrumentsMap>,
+        instruments: Arc<InstrumentsRegistry>,
     ) -> Self {
         let spec = fs::read_to_string(spec_path.to_string()).unwrap();
 
@@ -341,14 +470,10 @@ impl BinanceFixConnection {
         Self {
             stream: StreamTLS::new(),
             decoder: decoder,
-            encoder: FixMessageEncoderHandler {
-                encoder: Encoder::default(),
-                signing_key: SigningKey::read_pkcs8_pem_file(Path::new(signing_key_path)).unwrap(),
-                buffer: Default::default(),
-                msg_seq_num: 1,
-                api_key: api_key.to_string(),
-            },
-            instruments_map,
+            encoder: FixMessageEncoderHandler::new(SigningKey::read_pkcs8_pem_file(Path::new(signing_key_path)).unwrap(), api_key),
+            instruments,
+            rate_limiter: RateLimiter::new(),
+            logged_on: false,
         }
     }
 
@@ -357,27 +482,45 @@ impl BinanceFixConnection {
         self.stream.send_message(msg);
     }
 
+    /// The limits response reseeds the rate limiter
+    pub fn request_limits(&mut self) {
+        let msg = self.encoder.create_limit_message();
+        self.stream.send_message(msg);
+    }
+
 
     pub fn handle_stream(&mut self) -> Option<DTO> {
         Self::handle_incoming_message(
             &mut self.stream,
             &mut self.decoder,
             &mut self.encoder,
-            &self.instruments_map,
+            &self.instruments,
+            &mut self.rate_limiter,
+            &mut self.logged_on,
         )
     }
 
-    pub fn execution_report_to_order(msg: Message<&[u8]>, instruments_map: &Arc<InstrumentsMap>) -> Order {
+    /// The report of the canceled order comes under the cancel request id, mapped back to the original order.
+    /// Reports without the order id are dropped
+    pub fn execution_report_to_order(msg: Message<&[u8]>, instruments: &Arc<InstrumentsRegistry>) -> Option<Order> {
         let mut order = Order::new();
 
-        match msg.get(fix44::CL_ORD_ID) {
+        let client_order_id = match msg.get::<&[u8]>(fix44::ORIG_CL_ORD_ID) {
+            Err(FieldValueError::Missing) => msg.get::<&[u8]>(fix44::CL_ORD_ID),
+            value => value,
+        };
+        match client_order_id {
             Ok(value) => {
                 order.client_order_id = String::from_utf8_lossy(value).to_string();
             }
             Err(FieldValueError::Missing) => {
-                log::warn!("fix44::CL_ORD_ID missed");
+                log::error!("fix44::CL_ORD_ID missed, execution report dropped");
+                return None;
+            }
+            Err(_) => {
+                log::error!("fix44::CL_ORD_ID invalid, execution report dropped");
+                return None;
             }
-            _ => panic!("fix44::CL_ORD_ID err")
         }
 
         match msg.get(fix44::ORDER_ID) {
@@ -390,9 +533,9 @@ impl BinanceFixConnection {
             _ => panic!("fix44::ORDER_ID")
         }
 
-        match msg.get(fix44::ORDER_QTY) {
+        match msg.get::<&str>(fix44::ORDER_QTY) {
             Ok(value) => {
-                order.amount = value;
+                order.amount = value.parse().expect("fix44::ORDER_QTY isn't a decimal");
             }
             Err(FieldValueError::Missing) => {
                 log::warn!("fix44::ORDER_QTY missed");
@@ -400,9 +543,9 @@ impl BinanceFixConnection {
             _ => panic!("fix44::ORDER_QTY")
         }
 
-        match msg.get(25017) {
+        match msg.get::<&str>(25017) {
             Ok(value) => {
-                order.amount_quote = value;
+                order.amount_quote = value.parse().expect("25017:CumQuoteQty isn't a decimal");
             }
             Err(FieldValueError::Missing) => {
                 log::warn!("25017:CumQuoteQty missed");
@@ -413,9 +556,19 @@ impl BinanceFixConnection {
 
         order.order_type = match msg.get(fix44::ORD_TYPE).unwrap() {
             fix44::OrdType::Market => OrderType::Market,
-            fix44::OrdType::Limit => OrderType::Limit,
+            fix44::OrdType::Limit => match msg.get::<&str>(fix44::EXEC_INST) {
+                Ok("6") => OrderType::LimitMaker,
+                _ => OrderType::Limit,
+            },
             _ => panic!("Unexpected order type"),
         };
+        match msg.get(fix44::TIME_IN_FORCE) {
+            Ok(fix44::TimeInForce::GoodTillCancel) => order.time_in_force = TimeInForce::GTC,
+            Ok(fix44::TimeInForce::ImmediateOrCancel) => order.time_in_force = TimeInForce::IOC,
+            Ok(fix44::TimeInForce::FillOrKill) => order.time_in_force = TimeInForce::FOK,
+            Err(FieldValueError::Missing) => {}
+            _ => panic!("fix44::TIME_IN_FORCE")
+        }
         order.side = match msg.get(fix44::SIDE).unwrap() {
             fix44::Side::Buy => OrderSide::Buy,
             fix44::Side::Sell => OrderSide::Sell,
@@ -424,13 +577,11 @@ impl BinanceFixConnection {
 
 
         let symbol = String::from_utf8_lossy(msg.get(fix44::SYMBOL).unwrap()).to_string();
-        order.instrument = Arc::clone(
-            instruments_map.get(&Exchange::Binance, &symbol).unwrap()
-        );
+        order.instrument = instruments.get(&Exchange::Binance, &symbol).unwrap();
 
-        match msg.get(fix44::PRICE) {
+        match msg.get::<&str>(fix44::PRICE) {
             Ok(value) => {
-                order.price = value;
+                order.price = value.parse().expect("fix44::PRICE isn't a decimal");
             }
             Err(FieldValueError::Missing) => {
                 log::warn!("fix44::PRICE missed");
@@ -454,7 +605,7 @@ impl BinanceFixConnection {
             fix44::OrdStatus::Expired => OrderStatus::Error,
             _ => panic!("Unexpected order status"),
         };
-        order.amount_filled = msg.get(fix44::CUM_QTY).unwrap();
+        order.amount_filled = msg.get::<&str>(fix44::CUM_QTY).unwrap().parse().expect("fix44::CUM_QTY isn't a decimal");
 
         if order.status == OrderStatus::Error {
             match msg.get(fix44::TEXT) {
@@ -475,7 +626,7 @@ impl BinanceFixConnection {
                     order.fees.push(
                         (
                             fee_data.get::<&str>(fix44::MISC_FEE_CURR).unwrap().parse().unwrap(),
-                            fee_data.get(fix44::MISC_FEE_AMT).unwrap()
+                            fee_data.get::<&str>(fix44::MISC_FEE_AMT).unwrap().parse().expect("fix44::MISC_FEE_AMT isn't a decimal")
                         )
                     )
                 }
@@ -486,10 +637,17 @@ impl BinanceFixConnection {
             _ => panic!("fix44::NO_MISC_FEES")
         }
 
-        order
+        Some(order)
     }
 
-    fn handle_incoming_message(stream: &mut StreamTLS, decoder: &mut DecoderStreaming<Vec<u8>>, encoder: &mut FixMessageEncoderHandler, instruments_map: &Arc<InstrumentsMap>) -> Option<DTO> {
+    fn handle_incoming_message(
+        stream: &mut StreamTLS,
+        decoder: &mut DecoderStreaming<Vec<u8>>,
+        encoder: &mut FixMessageEncoderHandler,
+        instruments: &Arc<InstrumentsRegistry>,
+        rate_limiter: &mut RateLimiter,
+        logged_on: &mut bool,
+    ) -> Option<DTO> {
         let mut result = None;
         match stream.stream.read_exact(decoder.fillable()) {
             Ok(_) => {
@@ -504,12 +662,18 @@ impl BinanceFixConnection {
                             Ok(fix44::MsgType::ExecutionReport) => {
                                 log::info!("Handle:Execution report");
                                 // [2024-10-23T20:52:04Z INFO  untitled::core::oms] 8=FIX.4.49=000031335=849=SPOT56=EXAMPLE234=352=20241023-20:52:04.02209317=2461233511=dummy37=1105758938=0.0001000040=154=155=BTCUSDT59=160=20241023-20:52:04.02100025018=20241023-20:52:04.02100025001=3150=014=0.00000000151=0.0001000025017=0.000000001057=Y32=0.0000000039=0636=Y25023=20241023-20:52:04.02100010=218
-                                let order = Self::execution_report_to_order(msg, instruments_map);
-                                result = Some(DTO::Order(order));
+                                if let Some(order) = Self::execution_report_to_order(msg, instruments) {
+                                    log_event(log::Level::Info, "execution_report", order_fields(&order));
+                                    result = Some(DTO::Order(order));
+                                }
                             }
                             Ok(fix44::MsgType::Reject) => {
                                 panic!("Handle:Reject");
                             }
+                            Ok(fix44::MsgType::OrderCancelReject) => {
+                                // the order is already closed, its final report comes separately
+                                log::warn!("Handle:OrderCancelReject");
+                            }
                             Ok(fix44::MsgType::Logon) => {
                                 log::info!("Handle:Logon");
                                 let msg = encoder.create_limit_message();
@@ -536,8 +700,8 @@ impl BinanceFixConnection {
                                 for i in 0..limits_group.len() {
                                     let limit_data = limits_group.get(i).unwrap();
                                     let limit_type = match limit_data.get::<&str>(25004) {
-                                        Ok("1") => "ORDER",
-                                        Ok("2") => "MSG",
+                                        Ok("1") => RateLimitType::Order,
+                                        Ok("2") => RateLimitType::Message,
                                         Err(_) => {
                                             panic!("Invalid type");
                                         }
@@ -550,18 +714,27 @@ impl BinanceFixConnection {
                                     let reset_interval = limit_data.get::<usize>(25007).unwrap();
                                     let reset_interval_resolution = limit_data.get::<&str>(25008).unwrap();
 
-                                    log::info!("XLR:{limit_type} {current_count}/{max} reset: {reset_interval}{reset_interval_resolution}");
+                                    log::info!("XLR:{limit_type:?} {current_count}/{max} reset: {reset_interval}{reset_interval_resolution}");
+
+                                    match RateLimiter::parse_interval(reset_interval, reset_interval_resolution) {
+                                        Some(interval) => rate_limiter.update(limit_type, current_count, max, interval, time()),
+                                        None => log::warn!("Unknown reset interval resolution: {reset_interval_resolution}"),
+                                    }
                                 }
 
-                                // Create order
-                                result = Some(
-                                    DTO::MonitoringMessage(MonitoringMessage::new(
-                                        time(),
-                                        MonitoringStatus::Ok,
-                                        MonitoringEntity::OrderManagementSystem,
-                                        1,
-                                    ))
-                                );
+                                // the periodic refresh changes nothing for the monitoring
+                                if !*logged_on {
+                                    *logged_on = true;
+                                    result = Some(
+                                        DTO::MonitoringMessage(MonitoringMessage::new(
+                                            time(),
+                                            MonitoringStatus::Ok,
+                                            MonitoringEntity::OrderManagementSystem,
+                                            Exchange::Binance,
+                                            1,
+                                        ))
+                                    );
+                                }
                             }
                             t => {
                                 log::warn!("Unknown message type {t:?}");
@@ -593,6 +766,7 @@ impl BinanceFixConnection {
                         time(),
                         MonitoringStatus::Error,
                         MonitoringEntity::OrderManagementSystem,
+                        Exchange::Binance,
                         1,
                     ))
                 );
@@ -605,9 +779,17 @@ impl BinanceFixConnection {
 
 impl OrderListener for BinanceFixConnection {
     fn on_order(&mut self, order: &Order) {
-        log::info!("Execute new: {:?}", order);
+        if order.status == OrderStatus::Canceling {
+            log_event(log::Level::Info, "order_cancel_sent", order_fields(order));
+            let msg = self.encoder.create_cancel_message(order);
+            self.stream.send_message(msg);
+            return;
+        }
+        log_event(log::Level::Info, "order_sent", order_fields(order));
         let msg = self.encoder.create_order_message(order);
+        LATENCY.record(&order.instrument.exchange, Stage::OrderEncode, order.signal_ts, time());
         log::info!("Serialized: {:?}", String::from_utf8_lossy(msg).to_string());
         self.stream.send_message(msg);
+        LATENCY.record(&order.instrument.exchange, Stage::FixSend, order.signal_ts, time());
     }
 }
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::core::dto::{Instrument, Order, OrderSide, OrderType, TimeInForce};
+use crate::core::decimal::Decimal;
+use crate::core::utils::RoundingMode;
+
+#[derive(Debug, PartialEq)]
+pub enum OrderValidationError {
+    // neither amount nor amount_quote set
+    MissingSize,
+    // limit price for LIMIT orders, reference price for MARKET orders
+    MissingPrice,
+    AmountTooSmall { amount: Decimal, min: Decimal },
+    AmountTooLarge { amount: Decimal, max: Decimal },
+    NotionalTooSmall { notional: Decimal, min: Decimal },
+    NotionalTooLarge { notional: Decimal, max: Decimal },
+}
+
+pub struct OrderBuilder {
+    instrument: Arc<Instrument>,
+    side: OrderSide,
+    order_type: OrderType,
+    time_in_force: TimeInForce,
+    amount: Decimal,
+    amount_quote: Decimal,
+    price: Decimal,
+    reference_price: Decimal,
+    strategy_id: String,
+}
+
+impl OrderBuilder {
+    pub fn new(instrument: &Arc<Instrument>, side: OrderSide, order_type: OrderType) -> Self {
+        Self {
+            instrument: Arc::clone(instrument),
+            side,
+            order_type,
+            time_in_force: TimeInForce::GTC,
+            amount: Decimal::ZERO,
+            amount_quote: Decimal::ZERO,
+            price: Decimal::ZERO,
+            reference_price: Decimal::ZERO,
+            strategy_id: "".to_string(),
+        }
+    }
+
+    pub fn amount(mut self, amount: Decimal) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    pub fn amount_quote(mut self, amount_quote: Decimal) -> Self {
+        self.amount_quote = amount_quote;
+        self
+    }
+
+    pub fn price(mut self, price: Decimal) -> Self {
+        self.price = price;
+        self
+    }
+
+    /// Expected execution price of the MARKET order, used for the filters check
+    pub fn reference_price(mut self, price: Decimal) -> Self {
+        self.reference_price = price;
+        self
+    }
+
+    pub fn time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    pub fn strategy_id(mut self, strategy_id: &str) -> Self {
+        self.strategy_id = strategy_id.to_string();
+        self
+    }
+
+    pub fn check_filters(instrument: &Instrument, amount: Decimal, notional: Decimal) -> Result<(), OrderValidationError> {
+        if amount < instrument.order_amount_min {
+            return Err(OrderValidationError::AmountTooSmall { amount, min: instrument.order_amount_min });
+        }
+        if amount > instrument.order_amount_max {
+            return Err(OrderValidationError::AmountTooLarge { amount, max: instrument.order_amount_max });
+        }
+        if notional < instrument.order_notional_min {
+            return Err(OrderValidationError::NotionalTooSmall { notional, min: instrument.order_notional_min });
+        }
+        if notional > instrument.order_notional_max {
+            return Err(OrderValidationError::NotionalTooLarge { notional, max: instrument.order_notional_max });
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Order, OrderValidationError> {
+        let instrument = &self.instrument;
+        let price = match self.order_type {
+            OrderType::Market => self.reference_price,
+            // never cross the requested price because of the rounding
+            OrderType::Limit | OrderType::LimitMaker => self.price.round(
+                instrument.price_precision as u32,
+                if self.side == OrderSide::Buy { RoundingMode::Down } else { RoundingMode::Up },
+            ),
+        };
+        if price <= Decimal::ZERO {
+            return Err(OrderValidationError::MissingPrice);
+        }
+
+        let mut order = Order::new();
+        if self.amount > Decimal::ZERO {
+            order.amount = self.amount.round(instrument.amount_precision as u32, RoundingMode::Down);
+        } else if self.amount_quote > Decimal::ZERO {
+            if self.order_type == OrderType::Market {
+                order.amount_quote = self.amount_quote.round(instrument.price_precision as u32, RoundingMode::Down);
+            } else {
+                order.amount = (self.amount_quote / price).round(instrument.amount_precision as u32, RoundingMode::Down);
+            }
+        } else {
+            return Err(OrderValidationError::MissingSize);
+        }
+
+        let (amount, notional) = if order.amount > Decimal::ZERO {
+            (order.amount, order.amount * price)
+        } else {
+            (order.amount_quote / price, order.amount_quote)
+        };
+        Self::check_filters(instrument, amount, notional)?;
+
+        order.instrument = Arc::clone(instrument);
+        order.side = self.side;
+        order.order_type = self.order_type;
+        order.time_in_force = self.time_in_force;
+        if self.order_type != OrderType::Market {
+            order.price = price;
+        }
+        order.client_order_id = Uuid::new_v4().to_string();
+        order.strategy_id = self.strategy_id;
+        Ok(order)
+    }
+}
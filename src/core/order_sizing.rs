@@ -1,20 +1,22 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use crate::core::dto::{Instrument, OrderSide, PriceTicker};
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Instrument, InstrumentId, OrderSide, PriceTicker};
+use crate::core::fees::{FeeModel, Liquidity};
 
 #[derive(Debug, Clone)]
 pub struct SizingConfig {
     pub currency: String,
-    pub min_order_size: f64,
-    pub max_order_size: f64
+    pub min_order_size: Decimal,
+    pub max_order_size: Decimal
 }
 
 impl SizingConfig {
-    pub fn new(currency: String, min_order_size: f64, max_order_size: f64) -> Self {
+    pub fn new(currency: String, min_order_size: Decimal, max_order_size: Decimal) -> Self {
         Self {currency, min_order_size, max_order_size}
     }
 
-    pub fn adjust_value(&self, value: f64) -> Option<f64> {
+    pub fn adjust_value(&self, value: Decimal) -> Option<Decimal> {
         if value < self.min_order_size {
             return None;
         }
@@ -29,16 +31,19 @@ impl SizingConfig {
 
 
 pub fn max_chain_amount_quote(
-    tickers_map: &HashMap<Arc<Instrument>, PriceTicker>,
-    orders_direction: &Vec<(Arc<Instrument>, OrderSide)>
-) -> Option<f64> {
+    fee_model: &FeeModel,
+    tickers_map: &HashMap<InstrumentId, PriceTicker>,
+    orders_direction: &[(Arc<Instrument>, OrderSide)]
+) -> Option<Decimal> {
     // Initialize the maximum order size to a very large value
 
     let (instrument, side) = &orders_direction[orders_direction.len() - 1];
-    let ticker = tickers_map.get(instrument)?;
+    let ticker = tickers_map.get(&instrument.id)?;
+    // amounts passed along the chain are reduced only by the fees taken from the proceeds
+    let fee = fee_model.proceeds_rate(instrument, Liquidity::Taker);
     let mut amount_quote = match side {
         OrderSide::Buy => {
-            ticker.ask_amount * ticker.effective_ask(instrument.taker_fee)
+            ticker.ask_amount * ticker.effective_ask(fee)
         },
         OrderSide::Sell => {
             ticker.bid_amount
@@ -54,15 +59,16 @@ pub fn max_chain_amount_quote(
 
     // Traverse the chain in reverse
     for (instrument, side) in orders_direction.iter().rev().skip(1) {
-        let ticker = tickers_map.get(instrument)?;
+        let ticker = tickers_map.get(&instrument.id)?;
+        let fee = fee_model.proceeds_rate(instrument, Liquidity::Taker);
 
         match side {
             OrderSide::Buy => {
-                amount_quote = amount_quote.min(ticker.ask_amount) * ticker.effective_ask(instrument.taker_fee);
+                amount_quote = amount_quote.min(ticker.ask_amount) * ticker.effective_ask(fee);
             },
             OrderSide::Sell => {
                 if prev_instrument.base != instrument.base {
-                    amount_quote /= ticker.effective_bid(instrument.taker_fee);
+                    amount_quote = amount_quote / ticker.effective_bid(fee);
                 }
                 amount_quote = amount_quote.min(ticker.bid_amount);
             },
@@ -80,10 +86,11 @@ pub fn max_chain_amount_quote(
 
 pub fn chain_amount_quote(
     sizing_config: &SizingConfig,
-    tickers_map: &HashMap<Arc<Instrument>, PriceTicker>,
-    orders_direction: &Vec<(Arc<Instrument>, OrderSide)>,
-) -> Option<f64> {
-    let amount_quote = max_chain_amount_quote(tickers_map, orders_direction)?;
+    fee_model: &FeeModel,
+    tickers_map: &HashMap<InstrumentId, PriceTicker>,
+    orders_direction: &[(Arc<Instrument>, OrderSide)],
+) -> Option<Decimal> {
+    let amount_quote = max_chain_amount_quote(fee_model, tickers_map, orders_direction)?;
     log::info!("max_chain_amount_quote: {amount_quote}");
     sizing_config.adjust_value(amount_quote)
 }
@@ -0,0 +1,161 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Exchange, InstrumentId, Order, OrderStatus};
+use crate::core::utils::time;
+
+// closed orders are kept for the late reports and the lookups
+const CLOSED_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OrderUpdateError {
+    // client_order_id is already tracked
+    AlreadyRegistered,
+    // the same execution report received twice
+    Duplicate,
+    // report goes back in the status or the filled amount
+    OutOfOrder { from: OrderStatus, to: OrderStatus },
+}
+
+#[derive(Debug)]
+pub struct OrderTracker {
+    orders: HashMap<String, Order>,
+    // client ids of the orders not in the final state
+    open: HashSet<String>,
+    // client ids with the local close time, in the closing order
+    closed: VecDeque<(u128, String)>,
+}
+
+impl Default for OrderTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderTracker {
+    pub fn new() -> Self {
+        Self { orders: HashMap::new(), open: HashSet::new(), closed: VecDeque::new() }
+    }
+
+    pub fn is_transition_allowed(from: OrderStatus, to: OrderStatus) -> bool {
+        use OrderStatus::*;
+        match from {
+            Scheduled => to != Scheduled,
+            ScheduledSent => !matches!(to, Scheduled | ScheduledSent),
+            New | Open => !matches!(to, Scheduled | ScheduledSent | New),
+            PartiallyFilled => matches!(to, PartiallyFilled | Filled | Canceling | CancelingSent | Canceled | Error),
+            Canceling | CancelingSent => matches!(to, CancelingSent | PartiallyFilled | Filled | Canceled | Error),
+            Filled | Canceled | Error => false,
+        }
+    }
+
+    /// Start tracking an order created locally (before it's sent to the exchange)
+    pub fn register(&mut self, order: &Order) -> Result<(), OrderUpdateError> {
+        if self.orders.contains_key(&order.client_order_id) {
+            return Err(OrderUpdateError::AlreadyRegistered);
+        }
+        self.orders.insert(order.client_order_id.clone(), order.clone());
+        self.open.insert(order.client_order_id.clone());
+        self.close_if_final(order);
+        Ok(())
+    }
+
+    /// Apply the execution report to the tracked order and return the updated state
+    pub fn update(&mut self, report: &Order) -> Result<&Order, OrderUpdateError> {
+        if !self.orders.contains_key(&report.client_order_id) {
+            // placed outside of the current session or not registered by the producer
+            log::info!("Tracking order {} first seen in execution report", report.client_order_id);
+            let mut order = report.clone();
+            order.fees = vec![];
+            self.orders.insert(report.client_order_id.clone(), order);
+            self.open.insert(report.client_order_id.clone());
+        } else {
+            let tracked = &self.orders[&report.client_order_id];
+            if tracked.status == report.status && tracked.amount_filled == report.amount_filled {
+                return Err(OrderUpdateError::Duplicate);
+            }
+
+            // the report time is the exchange clock, the order follows the status and the cumulative fill
+            let out_of_order = report.amount_filled < tracked.amount_filled
+                || (tracked.status != report.status && !Self::is_transition_allowed(tracked.status, report.status));
+            if out_of_order {
+                return Err(OrderUpdateError::OutOfOrder { from: tracked.status, to: report.status });
+            }
+        }
+
+        let tracked = self.orders.get_mut(&report.client_order_id).unwrap();
+        tracked.timestamp = report.timestamp;
+        tracked.status = report.status;
+        tracked.amount_filled = report.amount_filled;
+        if !report.exchange_order_id.is_empty() {
+            tracked.exchange_order_id = report.exchange_order_id.clone();
+        }
+        if report.amount > Decimal::ZERO {
+            tracked.amount = report.amount;
+        }
+        if report.amount_quote > Decimal::ZERO {
+            tracked.amount_quote = report.amount_quote;
+        }
+        if report.price > Decimal::ZERO {
+            tracked.price = report.price;
+        }
+        if !report.error.is_empty() {
+            tracked.error = report.error.clone();
+        }
+
+        // fees are reported per execution, accumulate them by currency
+        for (currency, amount) in &report.fees {
+            match tracked.fees.iter_mut().find(|(c, _)| c == currency) {
+                Some(fee) => fee.1 += *amount,
+                None => tracked.fees.push((currency.clone(), *amount)),
+            }
+        }
+
+        self.close_if_final(report);
+        self.prune(time());
+        Ok(&self.orders[&report.client_order_id])
+    }
+
+    fn close_if_final(&mut self, order: &Order) {
+        if order.status.is_final() && self.open.remove(&order.client_order_id) {
+            self.closed.push_back((time(), order.client_order_id.clone()));
+        }
+    }
+
+    /// Forget the orders closed longer than the retention before `ts`
+    pub fn prune(&mut self, ts: u128) {
+        while let Some((closed_ts, _)) = self.closed.front() {
+            if closed_ts + CLOSED_RETENTION.as_nanos() > ts {
+                break;
+            }
+            let (_, client_order_id) = self.closed.pop_front().unwrap();
+            self.orders.remove(&client_order_id);
+        }
+    }
+
+    pub fn get(&self, client_order_id: &str) -> Option<&Order> {
+        self.orders.get(client_order_id)
+    }
+
+    pub fn orders(&self) -> impl Iterator<Item = &Order> {
+        self.orders.values()
+    }
+
+    pub fn open_orders(&self) -> impl Iterator<Item = &Order> {
+        self.open.iter().filter_map(|client_order_id| self.orders.get(client_order_id))
+    }
+
+    pub fn open_orders_by_exchange<'a>(&'a self, exchange: &'a Exchange) -> impl Iterator<Item = &'a Order> {
+        self.open_orders().filter(move |o| &o.instrument.exchange == exchange)
+    }
+
+    pub fn open_orders_by_instrument(&self, instrument_id: InstrumentId) -> impl Iterator<Item = &Order> {
+        self.open_orders().filter(move |o| o.instrument.id == instrument_id)
+    }
+
+    /// Forget orders in the final state
+    pub fn remove_closed(&mut self) {
+        self.orders.retain(|_, o| !o.status.is_final());
+        self.closed.clear();
+    }
+}
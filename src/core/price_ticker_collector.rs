@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::path::Path;
-use std::sync::Arc;
 use csv::Writer;
-use crate::core::api::{BalanceListener, BaseStrategy, MonitoringMessageListener, OrderListener, PriceTickerListener};
-use crate::core::dto::{Balance, Exchange, Instrument, MonitoringMessage, Order, PriceTicker};
+use json::{object, JsonValue};
+use crate::core::api::{BalanceListener, BaseStrategy, ControlListener, HealthListener, InstrumentsListener, OrderListener, PriceTickerListener};
+use crate::core::control::StrategyCommand;
+use crate::core::dto::{Balance, Exchange, InstrumentId, InstrumentsUpdate, Order, PriceTicker};
+use crate::core::health::HealthEvent;
 
 pub struct PriceTickerCollector {
     wtr: Writer<File>,
@@ -21,11 +23,11 @@ impl PriceTickerCollector {
 }
 
 impl PriceTickerListener for PriceTickerCollector {
-    fn on_price_ticker(&mut self, price_ticker: &PriceTicker, tickers_map: &HashMap<Exchange, HashMap<Arc<Instrument>, PriceTicker>>) {
-        let price_ticker = tickers_map.get(&price_ticker.instrument.exchange).unwrap().get(&price_ticker.instrument).unwrap();
+    fn on_price_ticker(&mut self, price_ticker: &PriceTicker, tickers_map: &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>) {
+        let price_ticker = tickers_map.get(&price_ticker.instrument.exchange).unwrap().get(&price_ticker.instrument.id).unwrap();
         self.wtr.write_record(
-            &[
-                price_ticker.instrument.exchange.as_str().clone(), &price_ticker.instrument.symbol,
+            [
+                price_ticker.instrument.exchange.as_str(), &price_ticker.instrument.symbol,
                 &price_ticker.timestamp.to_string(),
                 &price_ticker.bid.to_string(),
                 &price_ticker.bid_amount.to_string(),
@@ -43,17 +45,33 @@ impl PriceTickerListener for PriceTickerCollector {
 }
 
 impl OrderListener for PriceTickerCollector {
-    fn on_order(&mut self, order: &Order) {
+    fn on_order(&mut self, _order: &Order) {
     }
 }
 
-impl MonitoringMessageListener for PriceTickerCollector {
-    fn on_monitoring_message(&mut self, message: &MonitoringMessage) {
-    }
+impl HealthListener for PriceTickerCollector {
+    fn on_health_event(&mut self, _event: &HealthEvent) {}
 }
 
 impl BalanceListener for PriceTickerCollector {
-    fn on_balance(&mut self, order: &Balance) {
+    fn on_balance(&mut self, _balance: &Balance) {
+    }
+}
+
+impl InstrumentsListener for PriceTickerCollector {
+    fn on_instruments_update(&mut self, _update: &InstrumentsUpdate) {}
+}
+
+impl ControlListener for PriceTickerCollector {
+    fn strategy_id(&self) -> &str {
+        "collector"
+    }
+
+    fn on_control(&mut self, command: &StrategyCommand) -> Result<JsonValue, String> {
+        match command {
+            StrategyCommand::State => Ok(object! { id: self.strategy_id(), rows_unflushed: self.rows_counter }),
+            _ => Err(format!("{command:?} isn't supported by the collector")),
+        }
     }
 }
 
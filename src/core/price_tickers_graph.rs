@@ -3,63 +3,74 @@ use std::sync::Arc;
 use petgraph::Graph;
 use petgraph::graph::NodeIndex;
 use petgraph::algo::find_negative_cycle;
-use crate::core::dto::{Exchange, Instrument, OrderSide, PriceTicker};
+use json::{object, JsonValue};
+use crate::core::dto::{Exchange, Instrument, InstrumentId, OrderSide, PriceTicker};
+use crate::core::fees::{FeeModel, Liquidity};
+use crate::core::logging::log_event;
 
 
 pub struct ArbGraph {
     graph: Graph<String, f64>,
     edge_to_order_direction_map: HashMap<(NodeIndex, NodeIndex), (Arc<Instrument>, OrderSide)>,
+    // base -> quote edge of the instrument, skips the symbols lookup on the ticker update
+    instrument_to_edge_map: HashMap<InstrumentId, (NodeIndex, NodeIndex)>,
 
     symbol_to_node_map: HashMap<String, NodeIndex>,
     node_to_symbol_map: HashMap<NodeIndex, String>,
+    fee_model: Arc<FeeModel>,
 }
 
 impl ArbGraph {
-    pub fn new() -> Self {
-
+    pub fn new(fee_model: Arc<FeeModel>) -> Self {
         Self {
             graph: Graph::new(),
             symbol_to_node_map: Default::default(),
             node_to_symbol_map: Default::default(),
             edge_to_order_direction_map: Default::default(),
+            instrument_to_edge_map: Default::default(),
+            fee_model,
         }
     }
 
     pub fn get_node_by_symbol(&mut self, symbol: String) -> NodeIndex {
         match self.symbol_to_node_map.get(&symbol) {
             Some(node) => {
-                node.clone()
+                *node
             }
             _ => {
                 let node = self.graph.add_node(symbol.clone());
-                self.symbol_to_node_map.insert(symbol.clone(), node.clone());
+                self.symbol_to_node_map.insert(symbol.clone(), node);
                 self.node_to_symbol_map.insert(node, symbol);
-                node.clone()
+                node
             }
         }
     }
 
     pub fn get_nodes_by_instrument(&mut self, instrument: &Arc<Instrument>) -> (NodeIndex, NodeIndex) {
+        if let Some(edge) = self.instrument_to_edge_map.get(&instrument.id) {
+            return *edge;
+        }
         let base = self.get_node_by_symbol(instrument.base.clone());
         let quote = self.get_node_by_symbol(instrument.quote.clone());
 
-        let edge = &(base.clone(), quote.clone());
+        let edge = &(base, quote);
 
         if !self.edge_to_order_direction_map.contains_key(edge) {
             self.edge_to_order_direction_map.insert(
-                edge.clone(),
+                *edge,
                 (Arc::clone(instrument), OrderSide::Sell),
             );
             self.edge_to_order_direction_map.insert(
-                (edge.1, edge.0).clone(),
+                (edge.1, edge.0),
                 (Arc::clone(instrument), OrderSide::Buy),
             );
         }
+        self.instrument_to_edge_map.insert(instrument.id, (base, quote));
 
         (base, quote)
     }
 
-    pub fn calculate_path_profit(&self, path: &Vec<NodeIndex>) -> f64 {
+    pub fn calculate_path_profit(&self, path: &[NodeIndex]) -> f64 {
         let mut cycle_sum = 0.0;
         for window in path.windows(2) {
             let (u, v) = (window[0], window[1]);
@@ -74,24 +85,25 @@ impl ArbGraph {
 
     pub fn find_arb_path(&mut self, currency: &str, verbose: bool) -> Option<Vec<NodeIndex>> {
         let node_id = self.symbol_to_node_map.get(currency)?;
-        let vec = find_negative_cycle(&self.graph, node_id.clone())?;
+        let vec = find_negative_cycle(&self.graph, *node_id)?;
         let pos = vec.iter().position(|x| x == node_id)?;
         let mut path = Vec::new();
 
         path.extend_from_slice(&vec[pos..]);
         path.extend_from_slice(&vec[..pos]);
-        path.push(node_id.clone());
+        path.push(*node_id);
 
         if verbose {
-            let res = path.iter().map(|&node_index| { self.node_to_symbol_map.get(&node_index).unwrap().as_str() }).collect::<Vec<&str>>().join("->");
+            let currencies: Vec<&str> = path.iter().map(|&node_index| { self.node_to_symbol_map.get(&node_index).unwrap().as_str() }).collect();
             let profit = self.calculate_path_profit(&path);
-            let mut exchange= Exchange::Any;
-            for (instrument, _) in self.edge_to_order_direction_map.values() {
-                exchange = instrument.exchange.clone();
-                break;
-            }
-
-            log::info!("{exchange:?} Arb found {res} expected profit {profit}%");
+            let exchange = self.edge_to_order_direction_map.values().next()
+                .map_or(Exchange::Any, |(instrument, _)| instrument.exchange.clone());
+
+            log_event(log::Level::Info, "arb_found", object! {
+                exchange: exchange.as_str(),
+                path: currencies,
+                expected_profit_pct: profit,
+            });
         }
 
         Some(path)
@@ -101,6 +113,7 @@ impl ArbGraph {
         if self.graph.node_count() > 0 {
             self.graph.clear();
             self.edge_to_order_direction_map.clear();
+            self.instrument_to_edge_map.clear();
             self.symbol_to_node_map.clear();
             self.node_to_symbol_map.clear();
         }
@@ -108,8 +121,12 @@ impl ArbGraph {
 
     pub fn update(&mut self, price_ticker: &PriceTicker) {
         let (base, quote) = self.get_nodes_by_instrument(&price_ticker.instrument);
-        self.graph.update_edge(base, quote, -price_ticker.effective_bid(price_ticker.instrument.taker_fee).ln());
-        self.graph.update_edge(quote, base, -(1. / price_ticker.effective_ask(price_ticker.instrument.taker_fee)).ln());
+        // full trade cost, also when the fee is paid with the token
+        let fee = self.fee_model.rate(&price_ticker.instrument, Liquidity::Taker);
+        let (bid, ask) = (price_ticker.effective_bid(fee).to_f64(), price_ticker.effective_ask(fee).to_f64());
+        // the empty side of the book can't be traded
+        self.graph.update_edge(base, quote, if bid > 0. { -bid.ln() } else { f64::INFINITY });
+        self.graph.update_edge(quote, base, if ask > 0. { -(1. / ask).ln() } else { f64::INFINITY });
     }
 
     pub fn contains_currency_data(&self, currency: &str) -> bool {
@@ -120,4 +137,23 @@ impl ArbGraph {
         let dir = self.edge_to_order_direction_map.get(pair)?;
         Some(dir.clone())
     }
+
+    /// Edges with the conversion rates after the fees
+    pub fn to_json(&self) -> JsonValue {
+        let mut edges = JsonValue::new_array();
+        for edge in self.graph.edge_indices() {
+            let (from, to) = self.graph.edge_endpoints(edge).unwrap();
+            let Some((instrument, side)) = self.edge_to_order_direction_map.get(&(from, to)) else {
+                continue;
+            };
+            edges.push(object! {
+                from: self.graph[from].as_str(),
+                to: self.graph[to].as_str(),
+                symbol: instrument.symbol.as_str(),
+                side: format!("{side:?}"),
+                rate: (-self.graph[edge]).exp(),
+            }).unwrap();
+        }
+        object! { nodes: self.graph.node_count(), edges: edges }
+    }
 }
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitType {
+    Order,
+    Message,
+}
+
+/// Orders count of the exchange window, the windows are aligned to the interval since the epoch
+#[derive(Debug)]
+pub struct RateWindow {
+    pub limit_type: RateLimitType,
+    pub capacity: usize,
+    pub interval: Duration,
+    used: usize,
+    window_start: u128,
+}
+
+impl RateWindow {
+    pub fn new(limit_type: RateLimitType, capacity: usize, interval: Duration, used: usize, ts: u128) -> Self {
+        let mut window = Self { limit_type, capacity, interval, used, window_start: 0 };
+        window.window_start = window.start(ts);
+        window
+    }
+
+    fn start(&self, ts: u128) -> u128 {
+        ts - ts % self.interval.as_nanos()
+    }
+
+    // the count is reset by the next window
+    fn roll(&mut self, ts: u128) {
+        let start = self.start(ts);
+        if start > self.window_start {
+            self.window_start = start;
+            self.used = 0;
+        }
+    }
+
+    pub fn available(&mut self, ts: u128) -> usize {
+        self.roll(ts);
+        self.capacity.saturating_sub(self.used)
+    }
+
+    /// Time to wait until the next request is allowed
+    pub fn wait_time(&mut self, ts: u128) -> Duration {
+        if self.available(ts) > 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.window_start + self.interval.as_nanos() - ts) as u64)
+    }
+
+    fn take(&mut self) {
+        self.used += 1;
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    windows: Vec<RateWindow>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self { windows: vec![] }
+    }
+
+    pub fn parse_interval(interval: usize, resolution: &str) -> Option<Duration> {
+        let secs = match resolution {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => return None,
+        };
+        Some(Duration::from_secs(interval as u64 * secs))
+    }
+
+    /// Seed (or reseed) the window with the exchange counters
+    pub fn update(&mut self, limit_type: RateLimitType, used: usize, capacity: usize, interval: Duration, ts: u128) {
+        let window = RateWindow::new(limit_type, capacity, interval, used, ts);
+        match self.windows.iter_mut().find(|w| w.limit_type == limit_type && w.interval == interval) {
+            Some(existing) => *existing = window,
+            None => self.windows.push(window),
+        }
+    }
+
+    /// Count the request in every window of the given types or return the time to wait
+    pub fn try_acquire(&mut self, limit_types: &[RateLimitType], ts: u128) -> Result<(), Duration> {
+        let wait = self.windows.iter_mut()
+            .filter(|w| limit_types.contains(&w.limit_type))
+            .map(|w| w.wait_time(ts))
+            .max()
+            .unwrap_or(Duration::ZERO);
+
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+
+        for window in self.windows.iter_mut().filter(|w| limit_types.contains(&w.limit_type)) {
+            window.take();
+        }
+        Ok(())
+    }
+}
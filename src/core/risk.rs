@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::core::api::OrderListener;
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Exchange, Instrument, InstrumentId, Order, OrderSide, OrderStatus, OrderType, PriceTicker};
+use crate::core::map::InstrumentsRegistry;
+use crate::core::order_builder::OrderBuilder;
+use crate::core::utils::time;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+// the rejection reason prefix, alerted as critical
+pub const DAILY_LOSS_LIMIT_REASON: &str = "Daily loss limit reached";
+
+#[derive(Debug, Clone)]
+pub struct RiskConfig {
+    // currency the notional and PnL limits are expressed in
+    pub currency: String,
+    pub max_notional: Decimal,
+    pub instrument_max_notional: HashMap<InstrumentId, Decimal>,
+    pub daily_loss_limit: Decimal,
+    pub max_open_orders: usize,
+    // max allowed deviation of the limit price from the last ticker, 0.05 = 5%
+    pub price_collar: f64,
+}
+
+impl RiskConfig {
+    pub fn new(currency: String, max_notional: Decimal, daily_loss_limit: Decimal, max_open_orders: usize, price_collar: f64) -> Self {
+        Self {
+            currency,
+            max_notional,
+            instrument_max_notional: HashMap::new(),
+            daily_loss_limit,
+            max_open_orders,
+            price_collar,
+        }
+    }
+
+    pub fn max_notional(&self, instrument: &Arc<Instrument>) -> Decimal {
+        *self.instrument_max_notional.get(&instrument.id).unwrap_or(&self.max_notional)
+    }
+}
+
+/// Currency flows of the strategy fills since the start of the UTC day
+#[derive(Debug, Default)]
+struct DailyFlows {
+    day_start: u128,
+    flows: HashMap<(Exchange, String), Decimal>,
+}
+
+pub struct RiskGate {
+    pub config: RiskConfig,
+    kill_switch: Arc<AtomicBool>,
+    // conversion pairs are looked up by the symbol
+    instruments: Arc<InstrumentsRegistry>,
+    flows: HashMap<String, DailyFlows>,
+    // the latest applied fill of the orders, the closed ones are kept for the day against the repeated reports
+    fills: HashMap<String, Order>,
+    // client ids of the orders passed the check or reported open
+    open_orders: HashSet<String>,
+}
+
+impl RiskGate {
+    pub fn new(config: RiskConfig, instruments: Arc<InstrumentsRegistry>) -> Self {
+        Self {
+            config,
+            kill_switch: Arc::new(AtomicBool::new(false)),
+            instruments,
+            flows: HashMap::new(),
+            fills: HashMap::new(),
+            open_orders: HashSet::new(),
+        }
+    }
+
+    pub fn kill_switch(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.kill_switch)
+    }
+
+    /// Validate the order against the limits, returns the reject reason
+    pub fn check(
+        &mut self,
+        order: &Order,
+        tickers_map: &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>,
+    ) -> Result<(), String> {
+        if self.kill_switch.load(Ordering::Relaxed) {
+            return Err("Kill switch is active".to_string());
+        }
+
+        let instrument = &order.instrument;
+        let ticker = tickers_map.get(&instrument.exchange)
+            .and_then(|tickers| tickers.get(&instrument.id))
+            .ok_or(format!("No price ticker for {}", instrument.symbol))?;
+
+        let (reference_price, book_side) = match order.side {
+            OrderSide::Buy => (ticker.ask, "ask"),
+            OrderSide::Sell => (ticker.bid, "bid"),
+        };
+        // the empty side of the book
+        if reference_price <= Decimal::ZERO {
+            return Err(format!("No {book_side} price for {}", instrument.symbol));
+        }
+
+        if order.order_type != OrderType::Market {
+            let deviation = ((order.price - reference_price).abs() / reference_price).to_f64();
+            if deviation > self.config.price_collar {
+                return Err(format!(
+                    "Price {} deviates from the last ticker {reference_price} by {:.2}%", order.price, deviation * 100.
+                ));
+            }
+        }
+
+        let price = if order.order_type == OrderType::Market { reference_price } else { order.price };
+        if price <= Decimal::ZERO {
+            return Err(format!("Invalid price {price}"));
+        }
+        let (amount, notional) = if order.amount > Decimal::ZERO {
+            (order.amount, order.amount * price)
+        } else {
+            (order.amount_quote / price, order.amount_quote)
+        };
+
+        OrderBuilder::check_filters(instrument, amount, notional).map_err(|err| format!("{err:?}"))?;
+
+        let notional_value = self.value(&instrument.exchange, &instrument.quote, notional, tickers_map)
+            .ok_or(format!("Can't value {} in {}", instrument.quote, self.config.currency))?;
+        let max_notional = self.config.max_notional(instrument);
+        if notional_value > max_notional {
+            return Err(format!("Notional {notional_value}{} exceeds the limit {max_notional}", self.config.currency));
+        }
+
+        let open_orders = self.open_orders.len() - self.open_orders.contains(&order.client_order_id) as usize;
+        if open_orders >= self.config.max_open_orders {
+            return Err(format!("Open orders limit reached: {open_orders}"));
+        }
+
+        let pnl = self.daily_pnl(&order.strategy_id, time(), tickers_map);
+        if pnl <= -self.config.daily_loss_limit {
+            return Err(format!("{DAILY_LOSS_LIMIT_REASON} by {}: {pnl}{}", order.strategy_id, self.config.currency));
+        }
+
+        self.open_orders.insert(order.client_order_id.clone());
+        Ok(())
+    }
+
+    /// Strategies with the fills of the current day
+    pub fn strategies(&self) -> impl Iterator<Item = &String> {
+        self.flows.keys()
+    }
+
+    /// Mark-to-market PnL of the strategy fills since the start of the UTC day of `ts`
+    pub fn daily_pnl(
+        &self,
+        strategy_id: &str,
+        ts: u128,
+        tickers_map: &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>,
+    ) -> Decimal {
+        let Some(daily) = self.flows.get(strategy_id) else {
+            return Decimal::ZERO;
+        };
+        if daily.day_start != ts - ts % DAY.as_nanos() {
+            return Decimal::ZERO;
+        }
+
+        let mut pnl = Decimal::ZERO;
+        for ((exchange, currency), amount) in daily.flows.iter() {
+            match self.value(exchange, currency, *amount, tickers_map) {
+                Some(value) => pnl += value,
+                None => log::warn!("Can't value {amount}{currency} on {exchange:?} in {}", self.config.currency),
+            }
+        }
+        pnl
+    }
+
+    /// Convert the amount of the currency to the config currency using the last ticker of the pair
+    fn value(
+        &self,
+        exchange: &Exchange,
+        currency: &str,
+        amount: Decimal,
+        tickers_map: &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>,
+    ) -> Option<Decimal> {
+        if currency == self.config.currency || amount.is_zero() {
+            return Some(amount);
+        }
+        let tickers = tickers_map.get(exchange)?;
+        let ticker = |base: &str, quote: &str| {
+            self.instruments.get(exchange, &format!("{base}{quote}").to_uppercase())
+                .filter(|instrument| instrument.base == base && instrument.quote == quote)
+                .and_then(|instrument| tickers.get(&instrument.id))
+        };
+        // the empty side of the book leaves the value unknown
+        if let Some(ticker) = ticker(currency, &self.config.currency).filter(|t| t.bid > Decimal::ZERO) {
+            return Some(amount * ticker.bid);
+        }
+        ticker(&self.config.currency, currency)
+            .filter(|t| t.ask > Decimal::ZERO)
+            .map(|ticker| amount / ticker.ask)
+    }
+}
+
+impl OrderListener for RiskGate {
+    // the tracked order, the change of its fill and fees goes to the strategy flows
+    fn on_order(&mut self, order: &Order) {
+        if order.status.is_final() {
+            self.open_orders.remove(&order.client_order_id);
+        } else {
+            self.open_orders.insert(order.client_order_id.clone());
+        }
+        if !matches!(order.status, OrderStatus::PartiallyFilled | OrderStatus::Filled | OrderStatus::Canceled) {
+            return;
+        }
+
+        let previous = self.fills.insert(order.client_order_id.clone(), order.clone());
+        let (filled, quote) = previous.as_ref().map_or((Decimal::ZERO, Decimal::ZERO), |p| (p.amount_filled, p.amount_quote));
+        let previous_fee = |currency: &str| previous.as_ref()
+            .and_then(|p| p.fees.iter().find(|(c, _)| c == currency))
+            .map_or(Decimal::ZERO, |(_, fee)| *fee);
+
+        let day_start = order.timestamp - order.timestamp % DAY.as_nanos();
+        let daily = self.flows.entry(order.strategy_id.clone()).or_default();
+        if daily.day_start < day_start {
+            *daily = DailyFlows { day_start, flows: HashMap::new() };
+            self.fills.retain(|_, o| !o.status.is_final() || o.timestamp >= day_start);
+        } else if daily.day_start > day_start {
+            // the late report of the previous day
+            return;
+        }
+
+        let instrument = &order.instrument;
+        let (base_flow, quote_flow) = match order.side {
+            OrderSide::Buy => (order.amount_filled - filled, quote - order.amount_quote),
+            OrderSide::Sell => (filled - order.amount_filled, order.amount_quote - quote),
+        };
+        *daily.flows.entry((instrument.exchange.clone(), instrument.base.clone())).or_insert(Decimal::ZERO) += base_flow;
+        *daily.flows.entry((instrument.exchange.clone(), instrument.quote.clone())).or_insert(Decimal::ZERO) += quote_flow;
+        for (currency, fee) in &order.fees {
+            *daily.flows.entry((instrument.exchange.clone(), currency.clone())).or_insert(Decimal::ZERO) -= *fee - previous_fee(currency);
+        }
+    }
+}
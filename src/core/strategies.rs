@@ -1,76 +1,121 @@
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use crossbeam_queue::ArrayQueue;
-use uuid::Uuid;
-use crate::core::api::{BalanceListener, BaseStrategy, MonitoringMessageListener, OrderListener, PriceTickerListener};
-use crate::core::dto::{Balance, MonitoringMessage, Instrument, Order, OrderSide, PriceTicker, MonitoringEntity, MonitoringStatus, OrderStatus, DTO, Exchange};
+use crate::core::bus::Channel;
+use crate::core::api::{BalanceListener, BaseStrategy, ControlListener, HealthListener, InstrumentsListener, OrderListener, PriceTickerListener};
+use json::{object, JsonValue};
+use crate::core::control::StrategyCommand;
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Balance, Instrument, InstrumentId, InstrumentsUpdate, Order, OrderSide, PriceTicker, MonitoringEntity, OrderStatus, OrderType, Exchange};
+use crate::core::fees::FeeModel;
+use crate::core::health::{HealthEvent, HealthStatus};
+use crate::core::latency::{Stage, LATENCY};
+use crate::core::logging::log_event;
+use crate::core::metrics::{Counter, METRICS};
+use crate::core::order_builder::{OrderBuilder, OrderValidationError};
 use crate::core::order_sizing::{chain_amount_quote, SizingConfig};
+use crate::core::order_tracker::OrderTracker;
 use crate::core::price_tickers_graph::ArbGraph;
-use crate::core::utils::{round, time, RoundingMode};
+use crate::core::utils::{decimal_to_json, time};
 
 pub struct ArbStrategy {
+    id: String,
     exchange: Exchange,
     graph: ArbGraph,
     next_check_ts: u128,
 
     sizing_config: SizingConfig,
+    fee_model: Arc<FeeModel>,
     orders_direction: Vec<(Arc<Instrument>, OrderSide)>,
-    tickers: HashMap<Arc<Instrument>, PriceTicker>,
+    tickers: HashMap<InstrumentId, PriceTicker>,
     monitoring_only: bool,
-    out_queue: Arc<ArrayQueue<DTO>>,
+    // no new paths, set by the control API
+    paused: bool,
+    out_queue: Arc<Channel<Order>>,
+    order_tracker: Arc<RwLock<OrderTracker>>,
 
     // cooldown
     skips_until_cooldown: usize,
     skips_in_a_row: usize,
     cooldown_duration: Duration,
 
-    // management
-    managements_entities_errored_ids: HashMap<MonitoringEntity, HashSet<usize>>,
+    // status of the exchange entities groups, unknown ones are ok
+    health: HashMap<MonitoringEntity, HealthStatus>,
+
+    opportunities: Counter,
+    executions: Counter,
 }
 
 impl ArbStrategy {
-    pub fn new(out_queue: Arc<ArrayQueue<DTO>>, exchange: Exchange, sizing_config: SizingConfig, monitoring_only: bool) -> Self {
-
-        let mut managements_entities_errored_ids = HashMap::new();
-        managements_entities_errored_ids.insert(MonitoringEntity::PriceTicker, HashSet::new());
-        managements_entities_errored_ids.insert(MonitoringEntity::OrderManagementSystem, HashSet::new());
-        managements_entities_errored_ids.insert(MonitoringEntity::AccountUpdate, HashSet::new());
+    pub fn new(
+        out_queue: Arc<Channel<Order>>,
+        order_tracker: Arc<RwLock<OrderTracker>>,
+        exchange: Exchange,
+        sizing_config: SizingConfig,
+        fee_model: Arc<FeeModel>,
+        monitoring_only: bool,
+    ) -> Self {
+
+        let id = format!("arb_{}", exchange.as_str());
+        let labels = [("strategy", id.as_str())];
+        let opportunities = METRICS.counter("arbot_arb_opportunities_total", "Arbitrage paths found", &labels);
+        let executions = METRICS.counter("arbot_arb_executions_total", "Arbitrage paths entered with the first order", &labels);
         Self {
-            graph: ArbGraph::new(),
+            id,
+            graph: ArbGraph::new(Arc::clone(&fee_model)),
             next_check_ts: 0,
             orders_direction: vec![],
-            managements_entities_errored_ids,
+            health: HashMap::new(),
             out_queue,
+            order_tracker,
             skips_until_cooldown: 3,
             skips_in_a_row: 0,
             exchange,
             monitoring_only,
+            paused: false,
             sizing_config,
+            fee_model,
             cooldown_duration: Duration::from_millis(5),
             tickers: HashMap::default(),
+            opportunities,
+            executions,
         }
     }
 
     fn push_order(&mut self, order: Order) {
-        self.out_queue.push(DTO::Order(order)).unwrap()
+        if let Err(err) = self.order_tracker.write().expect("Can't get the lock").register(&order) {
+            log::error!("Can't register the order {}: {err:?}. Drop orders direction {:?}", order.client_order_id, self.orders_direction);
+            self.orders_direction.clear();
+            self.tickers.clear();
+            return;
+        }
+        if !self.out_queue.push(order.clone()) {
+            // dropped before the risk gate, no report comes
+            let mut order = order;
+            order.timestamp = time();
+            order.status = OrderStatus::Error;
+            order.error = "Strategy orders queue is full".to_string();
+            let _ = self.order_tracker.write().expect("Can't get the lock").update(&order);
+            self.on_order(&order);
+        }
     }
 
-    fn create_order_from_direction(&self, mut amount: f64, amount_quote: f64) -> Order {
-        let mut order = Order::new();
-        order.instrument = Arc::clone(&self.orders_direction[0].0);
-        order.side = self.orders_direction[0].1.clone();
-        if amount == 0. {
-            let price_ticker = self.tickers.get(&order.instrument).unwrap();
-            amount = amount_quote / if order.side == OrderSide::Buy {
-                price_ticker.ask
-            } else {
-                price_ticker.bid
-            };
-        }
-        order.amount = round(amount, order.instrument.amount_precision, RoundingMode::Down);
-        order.client_order_id = Uuid::new_v4().to_string();
-        order
+    fn create_order_from_direction(&self, mut amount: Decimal, amount_quote: Decimal) -> Result<Order, OrderValidationError> {
+        let (instrument, side) = &self.orders_direction[0];
+        let price_ticker = self.tickers.get(&instrument.id).unwrap();
+        let price = if *side == OrderSide::Buy {
+            price_ticker.ask
+        } else {
+            price_ticker.bid
+        };
+        if amount.is_zero() {
+            amount = amount_quote / price;
+        }
+        OrderBuilder::new(instrument, *side, OrderType::Market)
+            .amount(amount)
+            .reference_price(price)
+            .strategy_id(&self.id)
+            .build()
     }
 
     fn skip(&mut self) {
@@ -83,6 +128,35 @@ impl ArbStrategy {
         }
     }
 
+    fn has_open_orders(&self) -> bool {
+        let order_tracker = self.order_tracker.read().expect("Can't get the lock");
+        let mut open_orders = order_tracker.open_orders_by_exchange(&self.exchange).peekable();
+        if let Some(order) = open_orders.peek() {
+            log::warn!("Open order on {:?} blocks new path: {order:?}", self.exchange);
+            return true;
+        }
+        false
+    }
+
+    fn state(&self) -> JsonValue {
+        object! {
+            id: self.id.as_str(),
+            exchange: self.exchange.as_str(),
+            paused: self.paused,
+            monitoring_only: self.monitoring_only,
+            currency: self.sizing_config.currency.as_str(),
+            min_order_size: self.sizing_config.min_order_size.to_string(),
+            max_order_size: self.sizing_config.max_order_size.to_string(),
+            path_in_progress: !self.orders_direction.is_empty(),
+            opportunities: self.opportunities.get(),
+            executions: self.executions.get(),
+        }
+    }
+
+    fn is_ok(&self, entity: MonitoringEntity) -> bool {
+        self.health.get(&entity).is_none_or(|status| *status == HealthStatus::Ok)
+    }
+
     fn cooldown(&mut self) {
         self.next_check_ts = time() + self.cooldown_duration.as_nanos();
     }
@@ -95,7 +169,7 @@ impl ArbStrategy {
 
 impl OrderListener for ArbStrategy {
     fn on_order(&mut self, order: &Order) {
-        if self.monitoring_only {
+        if self.monitoring_only || order.strategy_id != self.id {
             return;
         }
         log::info!("Order received {order:?}");
@@ -105,20 +179,37 @@ impl OrderListener for ArbStrategy {
         }
 
         if !(order.instrument == self.orders_direction[0].0 && order.side == self.orders_direction[0].1) {
-            panic!("Missmatch order");
+            log::warn!("Order doesn't match the direction {:?}, dropped", self.orders_direction[0]);
+            return;
         }
 
         match &order.status {
             OrderStatus::Error => {
-                panic!("Errored order status");
+                log::error!("Order rejected: {}. Drop orders direction {:?}", order.error, self.orders_direction);
+                self.orders_direction.clear();
+                self.tickers.clear();
+                self.cooldown_with_duration(Duration::from_secs(3));
             },
 
+            OrderStatus::Canceled => {
+                log::warn!("Order canceled. Drop orders direction {:?}", self.orders_direction);
+                self.orders_direction.clear();
+                self.tickers.clear();
+                self.cooldown_with_duration(Duration::from_secs(3));
+            }
+
             OrderStatus::Filled => {
                 self.orders_direction.remove(0);
                 if self.orders_direction.is_empty() {
                     let profit = order.balance().unwrap().1 - self.sizing_config.min_order_size;
-                    let profit_pct = profit / self.sizing_config.min_order_size * 100.;
-                    log::info!("Filled orders directions profit: {profit} ({profit_pct}%)");
+                    let profit_pct = (profit / self.sizing_config.min_order_size).to_f64() * 100.;
+                    log_event(log::Level::Info, "arb_completed", object! {
+                        strategy_id: self.id.as_str(),
+                        exchange: self.exchange.as_str(),
+                        currency: self.sizing_config.currency.as_str(),
+                        profit: decimal_to_json(profit),
+                        profit_pct: profit_pct,
+                    });
                     // panic!("The good one.");
                     return;
                 }
@@ -126,16 +217,23 @@ impl OrderListener for ArbStrategy {
                 let balance = order.balance().expect("Should be balance");
 
                 let (amount, amount_quote) = if balance.0 == self.orders_direction[0].0.base {
-                    (balance.1, 0.)
+                    (balance.1, Decimal::ZERO)
                 } else if balance.0 == self.orders_direction[0].0.quote {
-                    (0., balance.1)
+                    (Decimal::ZERO, balance.1)
                 } else {
-                    panic!("Invalid sequence: {:?}", self.orders_direction[0]);
+                    log::error!("Invalid sequence: {balance:?} for {:?}. Drop orders direction", self.orders_direction[0]);
+                    self.orders_direction.clear();
+                    self.tickers.clear();
+                    return;
                 };
 
-                self.push_order(
-                    self.create_order_from_direction(amount, amount_quote)
-                );
+                match self.create_order_from_direction(amount, amount_quote) {
+                    Ok(order) => self.push_order(order),
+                    Err(err) => {
+                        log::error!("Can't create the next order {err:?}. Drop orders direction {:?}", self.orders_direction);
+                        self.orders_direction.clear();
+                    }
+                }
             }
             t => {
                 log::info!("Unexpected order status: {t:?}");
@@ -148,63 +246,78 @@ impl BalanceListener for ArbStrategy {
     fn on_balance(&mut self, _balance: &Balance) {}
 }
 
-impl MonitoringMessageListener for ArbStrategy {
+impl HealthListener for ArbStrategy {
+    fn on_health_event(&mut self, event: &HealthEvent) {
+        if event.exchange != self.exchange {
+            return;
+        }
+        log::info!("{} health event {event:?}", self.id);
+        if event.entity == MonitoringEntity::PriceTicker && event.status != HealthStatus::Ok {
+            // unfortunately we should reset our graph and its dependencies
+            self.graph.reset();
+        }
+        self.health.insert(event.entity, event.status);
+    }
+}
 
-    fn on_monitoring_message(&mut self, message: &MonitoringMessage) {
-        log::info!("Monitoring message received {message:?}");
-        let entities_ids = self.managements_entities_errored_ids.get_mut(&message.entity).unwrap();
-        match message.status {
-            MonitoringStatus::Ok => {
-                entities_ids.remove(&message.entity_id);
-            }
-            MonitoringStatus::Error => {
-                entities_ids.insert(message.entity_id);
-
-                match message.entity {
-                    MonitoringEntity::PriceTicker => {
-                        // unfortunately we should reset our graph and its dependencies
-                        self.graph.reset();
-                    },
-                    _ => {}
-                }
-            }
+impl InstrumentsListener for ArbStrategy {
+    fn on_instruments_update(&mut self, update: &InstrumentsUpdate) {
+        let is_affected = |instrument: &Arc<Instrument>| instrument.exchange == self.exchange;
+        if !update.removed.iter().chain(update.changed.iter()).any(is_affected) {
+            return; // new instruments join the graph with their tickers
         }
-        for (k, v) in self.managements_entities_errored_ids.iter() {
-            log::info!("Current management state: {k:?} {v:?}")
+        // edges weights depend on the fees, rebuild the graph from the next tickers
+        self.graph.reset();
+
+        let removed: Vec<&Arc<Instrument>> = update.removed.iter().filter(|i| is_affected(i)).collect();
+        if self.orders_direction.iter().any(|(instrument, _)| removed.contains(&instrument)) {
+            log::warn!("Instruments {removed:?} removed. Drop orders direction {:?}", self.orders_direction);
+            self.orders_direction.clear();
+            self.tickers.clear();
         }
     }
 }
 
 impl PriceTickerListener for ArbStrategy {
-    fn on_price_ticker(&mut self, price_ticker: &PriceTicker, tickers_map: &HashMap<Exchange, HashMap<Arc<Instrument>, PriceTicker>>) {
+    fn on_price_ticker(&mut self, price_ticker: &PriceTicker, tickers_map: &HashMap<Exchange, HashMap<InstrumentId, PriceTicker>>) {
         if price_ticker.instrument.exchange != self.exchange {
             return; // single exchange strategy
         }
         let tickers_map = tickers_map.get(&self.exchange).unwrap();
-        // if !self.managements_entities_errored_ids[&MonitoringEntity::PriceTicker].is_empty() {
+        // if !self.is_ok(MonitoringEntity::PriceTicker) {
         //     return; // we have the broken price ticker stream, since we reset graph might be ok.
         // }
-        let price_ticker = tickers_map.get(&price_ticker.instrument).unwrap();
+        let price_ticker = tickers_map.get(&price_ticker.instrument.id).unwrap();
 
         self.graph.update(price_ticker);
 
-        if !self.managements_entities_errored_ids[&MonitoringEntity::OrderManagementSystem].is_empty() {
+        if !self.is_ok(MonitoringEntity::OrderManagementSystem) {
             return; // we have the broken OMS.
         }
 
+        if !self.is_ok(MonitoringEntity::OrderRateLimit) {
+            return; // OMS throttles orders, back off.
+        }
+
         if !self.orders_direction.is_empty() {  // processing of the path
-            if self.tickers.contains_key(&price_ticker.instrument) {
-                self.tickers.get_mut(&price_ticker.instrument).unwrap().update(price_ticker);
+            if let Some(ticker) = self.tickers.get_mut(&price_ticker.instrument.id) {
+                ticker.update(price_ticker);
             }
             return;
         }
 
-        if self.next_check_ts > time() {
+        if self.paused || self.next_check_ts > time() {
+            return;
+        }
+
+        if !self.monitoring_only && self.has_open_orders() {
+            self.cooldown_with_duration(Duration::from_secs(1));
             return;
         }
 
         if self.graph.contains_currency_data(&self.sizing_config.currency) {
             if let Some(path) = self.graph.find_arb_path(&self.sizing_config.currency, true) {
+                self.opportunities.inc();
                 for window in path.windows(2) {
                     if let Some(dir) = self.graph.get_direction(&(window[0], window[1])) {
                         self.orders_direction.push(dir);
@@ -215,22 +328,43 @@ impl PriceTickerListener for ArbStrategy {
                     }
                 }
 
-                if let Some(enter_amount) = chain_amount_quote(&self.sizing_config, tickers_map, &self.orders_direction) {
+                if let Some(enter_amount) = chain_amount_quote(&self.sizing_config, &self.fee_model, tickers_map, &self.orders_direction) {
+                    LATENCY.record(&self.exchange, Stage::Decision, price_ticker.timestamp, time());
+                    let legs: Vec<JsonValue> = self.orders_direction.iter()
+                        .map(|(instrument, side)| object! { symbol: instrument.symbol.as_str(), side: format!("{side:?}") })
+                        .collect();
+                    log_event(log::Level::Info, "arb_entered", object! {
+                        strategy_id: self.id.as_str(),
+                        exchange: self.exchange.as_str(),
+                        currency: self.sizing_config.currency.as_str(),
+                        enter_amount: decimal_to_json(enter_amount),
+                        monitoring_only: self.monitoring_only,
+                        legs: legs,
+                    });
                     // send first order
                     if !self.monitoring_only {
                         for (instrument, _) in &self.orders_direction {
-                            self.tickers.insert(
-                                Arc::clone(instrument), tickers_map.get(instrument).unwrap().copy()
-                            );
+                            self.tickers.insert(instrument.id, tickers_map.get(&instrument.id).unwrap().copy());
                         }
 
-                        let enter_order;
-                        if &self.orders_direction[0].0.base.to_uppercase() == &self.sizing_config.currency.to_uppercase() {
-                            enter_order = self.create_order_from_direction(enter_amount, 0.);
+                        let enter_order = if self.orders_direction[0].0.base.to_uppercase() == self.sizing_config.currency.to_uppercase() {
+                            self.create_order_from_direction(enter_amount, Decimal::ZERO)
                         } else {
-                            enter_order = self.create_order_from_direction(0., enter_amount);
+                            self.create_order_from_direction(Decimal::ZERO, enter_amount)
+                        };
+                        match enter_order {
+                            Ok(mut order) => {
+                                order.signal_ts = price_ticker.timestamp;
+                                self.push_order(order);
+                                self.executions.inc();
+                            }
+                            Err(err) => {
+                                log::warn!("Invalid initial order {err:?}");
+                                self.orders_direction.clear();
+                                self.skip();
+                                return;
+                            }
                         }
-                        self.push_order(enter_order);
                     }
                     self.skips_in_a_row = 0;
                 } else {
@@ -249,4 +383,32 @@ impl PriceTickerListener for ArbStrategy {
     }
 }
 
+impl ControlListener for ArbStrategy {
+    fn strategy_id(&self) -> &str {
+        &self.id
+    }
+
+    fn on_control(&mut self, command: &StrategyCommand) -> Result<JsonValue, String> {
+        match command {
+            StrategyCommand::State => {}
+            StrategyCommand::Pause => self.paused = true,
+            StrategyCommand::Resume => self.paused = false,
+            StrategyCommand::MonitoringOnly(monitoring_only) => {
+                // the orders of the path in progress are followed by the executing strategy only
+                if *monitoring_only && !self.orders_direction.is_empty() {
+                    return Err("Path is in progress".to_string());
+                }
+                self.monitoring_only = *monitoring_only;
+            }
+            StrategyCommand::Sizing { min_order_size, max_order_size } => {
+                self.sizing_config.min_order_size = *min_order_size;
+                self.sizing_config.max_order_size = *max_order_size;
+            }
+            StrategyCommand::Graph => return Ok(self.graph.to_json()),
+        }
+        log::info!("{} control {command:?}: {}", self.id, self.state());
+        Ok(self.state())
+    }
+}
+
 impl BaseStrategy for ArbStrategy {}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use std::time::Duration;
+use json::{object, JsonValue};
+use crate::core::{
+    dto::PriceTicker,
+    map::InstrumentsMap,
+    utils::json_millis_to_nanos,
+};
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Exchange, TICKER_PRICE_NOT_CHANGED};
+use crate::core::streams::connector::{PingPolicy, StreamConnector, Venue};
+
+pub type PriceTickerStream = StreamConnector<BybitVenue>;
+
+const TOPIC_PREFIX: &str = "orderbook.1.";
+
+#[derive(Default)]
+pub struct BybitVenue;
+
+impl BybitVenue {
+    fn request(op: &str, channels: &[String], request_id: usize) -> JsonValue {
+        object! { op: op, args: channels, req_id: request_id.to_string() }
+    }
+
+    // empty side - not changed since the previous message
+    fn parse_level(levels: &JsonValue) -> Result<(Decimal, Decimal), String> {
+        let level = &levels[0];
+        if level.is_null() {
+            return Ok((TICKER_PRICE_NOT_CHANGED, TICKER_PRICE_NOT_CHANGED));
+        }
+        let parse = |value: &JsonValue| value.as_str().and_then(|v| v.parse::<Decimal>().ok()).ok_or(format!("Invalid level: {level}"));
+        Ok((parse(&level[0])?, parse(&level[1])?))
+    }
+}
+
+impl Venue for BybitVenue {
+    const EXCHANGE: Exchange = Exchange::Bybit;
+    const URL: &'static str = "wss://stream.bybit.com/v5/public/spot";
+    const MAX_CHANNELS_PER_STREAM: usize = 100;
+    // spot topics per request
+    const MAX_CHANNELS_PER_REQUEST: usize = 10;
+    const REQUEST_DELAY: Duration = Duration::from_millis(100);
+
+    fn ticker_to_channel(ticker: &str) -> String {
+        let mut s = TOPIC_PREFIX.to_string();
+        s.push_str(&ticker.to_uppercase().replace("/", ""));
+        s
+    }
+
+    fn channel_to_symbol(channel: &str) -> &str {
+        channel.trim_start_matches(TOPIC_PREFIX)
+    }
+
+    fn subscribe_message(&self, channels: &[String], request_id: usize) -> JsonValue {
+        Self::request("subscribe", channels, request_id)
+    }
+
+    fn unsubscribe_message(&self, channels: &[String], request_id: usize) -> JsonValue {
+        Self::request("unsubscribe", channels, request_id)
+    }
+
+    fn ack(&mut self, request: &JsonValue, raw: &str) -> Option<Result<JsonValue, String>> {
+        if !raw.starts_with("{\"success\"") {
+            return None;
+        }
+        let data = json::parse(raw).ok()?;
+        if data["op"] != request["op"] || data["req_id"] != request["req_id"] {
+            return None;
+        }
+        if data["success"] == true {
+            Some(Ok(data))
+        } else {
+            Some(Err(data["ret_msg"].to_string()))
+        }
+    }
+
+    fn ping_policy(&self) -> PingPolicy {
+        PingPolicy::Client { message: object! { op: "ping" }.dump(), interval: Duration::from_secs(20) }
+    }
+
+    fn is_pong(&self, raw: &str) -> bool {
+        raw.starts_with("{\"success\"") && raw.contains("\"op\":\"ping\"")
+    }
+
+    fn parse(&mut self, ts: u128, raw: &str, instruments: &InstrumentsMap) -> Result<Option<PriceTicker>, String> {
+        let data = &json::parse(raw).map_err(|err| err.to_string())?;
+        let Some(topic) = data["topic"].as_str() else {
+            return Ok(None);
+        };
+        let symbol = Self::channel_to_symbol(topic);
+        let instrument = instruments.get(&Exchange::Bybit, symbol).ok_or(format!("No instrument: {symbol}"))?;
+        let book = &data["data"];
+        let (bid, bid_amount) = Self::parse_level(&book["b"])?;
+        let (ask, ask_amount) = Self::parse_level(&book["a"])?;
+        Ok(Some(PriceTicker {
+            timestamp: ts,
+            instrument: Arc::clone(instrument),
+            bid,
+            bid_amount,
+            ask,
+            ask_amount,
+            // "u" restarts from 1 with the service restart, the cross sequence doesn't
+            update_id: book["seq"].as_u64().unwrap_or(0),
+            exchange_ts: json_millis_to_nanos(&data["cts"]),
+            connection: 0,
+        }))
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::{HashMap, HashSet};
+use std::net::TcpStream;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use itertools::Itertools;
+use json::{object, JsonValue};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{connect, Error, Message, WebSocket};
+use crate::core::bus::EventBus;
+use crate::core::dto::{Exchange, InstrumentId, MonitoringEntity, MonitoringMessage, MonitoringStatus, PriceTicker, DTO};
+use crate::core::latency::{Stage, LATENCY};
+use crate::core::logging::log_event;
+use crate::core::map::{InstrumentsMap, InstrumentsRegistry};
+use crate::core::metrics::{Counter, METRICS};
+use crate::core::streams::sequence::{Sequence, SequenceTracker};
+use crate::core::streams::sharding::{Shards, REBALANCE_INTERVAL};
+use crate::core::streams::watchdog::{Silence, Watchdog};
+use crate::core::utils::time;
+
+pub type Res = Result<(), Box<dyn std::error::Error>>;
+
+const READ_TIMEOUT: Duration = Duration::from_millis(1000);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_RETRIES: usize = 5;
+const RECONNECT_BACKOFF_STEP: u64 = 5;
+const RECONNECT_BACKOFF_MAX: u64 = 60;
+// tickers count reports to the shards
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+// the sockets of the appended shards are spawned that soon
+const SHARDS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How the connection is kept alive
+pub enum PingPolicy {
+    // the server sends ping frames, answered with pong
+    Server,
+    // the client sends the text message every interval
+    Client { message: String, interval: Duration },
+}
+
+/// Exchange specific part of the price ticker stream
+pub trait Venue: Default + Send + 'static {
+    const EXCHANGE: Exchange;
+    const URL: &'static str;
+    // the sockets are sharded within the limits
+    const MAX_CHANNELS_PER_STREAM: usize = usize::MAX;
+    const MAX_CHANNELS_PER_REQUEST: usize = usize::MAX;
+    // pause after each request, shared by the streams of the venue
+    const REQUEST_DELAY: Duration;
+    // pause between the streams start
+    const SPAWN_DELAY: Duration = Duration::ZERO;
+    // update ids of the symbol go one by one, a skipped one is the gap
+    const SEQUENTIAL_UPDATE_IDS: bool = false;
+    // the silent socket is reconnected
+    const SOCKET_SILENCE_TIMEOUT: Duration = Duration::from_secs(60);
+    // the silent instrument is resubscribed
+    const INSTRUMENT_SILENCE_TIMEOUT: Option<Duration> = Some(Duration::from_secs(600));
+
+    fn ticker_to_channel(ticker: &str) -> String;
+
+    fn channel_to_symbol(channel: &str) -> &str;
+
+    fn subscribe_message(&self, channels: &[String], request_id: usize) -> JsonValue;
+
+    fn unsubscribe_message(&self, channels: &[String], request_id: usize) -> JsonValue;
+
+    /// None if the message isn't the response to the request, otherwise the response or the reject reason
+    fn ack(&mut self, request: &JsonValue, raw: &str) -> Option<Result<JsonValue, String>>;
+
+    fn ping_policy(&self) -> PingPolicy {
+        PingPolicy::Server
+    }
+
+    fn is_pong(&self, _raw: &str) -> bool {
+        false
+    }
+
+    /// Request of the active subscriptions, checked against the channels after subscribing
+    fn list_subscriptions(&self, _request_id: usize) -> Option<JsonValue> {
+        None
+    }
+
+    fn subscriptions(&self, _response: &JsonValue) -> HashSet<String> {
+        HashSet::new()
+    }
+
+    /// Called on every (re)connect, the stream state is reset here
+    fn on_connect(&mut self) {}
+
+    /// Channels with the corrupted local state, the stream resubscribes them
+    fn take_resubscribe(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Price ticker of the message, None for the service messages
+    fn parse(&mut self, ts: u128, raw: &str, instruments: &InstrumentsMap) -> Result<Option<PriceTicker>, String>;
+}
+
+/// Channels of the request acknowledged one by one, the request is done when all of them are
+#[derive(Default)]
+pub struct BatchAcks {
+    request: Option<JsonValue>,
+    pending: HashSet<String>,
+}
+
+impl BatchAcks {
+    /// Starts waiting for the channels of the new request, the same request keeps its acked channels
+    pub fn track(&mut self, request: &JsonValue, channels: impl IntoIterator<Item = String>) {
+        if self.request.as_ref() != Some(request) {
+            self.request = Some(request.clone());
+            self.pending = channels.into_iter().collect();
+        }
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &String> {
+        self.pending.iter()
+    }
+
+    /// True when the last channel of the request is acked
+    pub fn ack(&mut self, channel: &str) -> bool {
+        self.pending.remove(channel);
+        if self.pending.is_empty() {
+            self.clear();
+            return true;
+        }
+        false
+    }
+
+    pub fn clear(&mut self) {
+        self.request = None;
+        self.pending.clear();
+    }
+}
+
+/// Subscribed channel of the instrument, the channel symbol may be an alias of the instrument one
+#[derive(Default)]
+pub struct ChannelIndex {
+    channels: HashMap<InstrumentId, String>,
+    // tickers per channel since the last report to the shards
+    messages: HashMap<String, u64>,
+}
+
+impl ChannelIndex {
+    pub fn build<V: Venue>(channels: &[String], instruments: &InstrumentsMap) -> Self {
+        let mut index = Self::default();
+        index.update::<V>(channels, instruments);
+        index
+    }
+
+    /// Follow the subscribed channels, the counts of the dropped ones are forgotten
+    pub fn update<V: Venue>(&mut self, channels: &[String], instruments: &InstrumentsMap) {
+        self.channels = channels.iter()
+            .filter_map(|c| instruments.get(&V::EXCHANGE, V::channel_to_symbol(c)).map(|instrument| (instrument.id, c.clone())))
+            .collect();
+        self.messages.retain(|channel, _| channels.contains(channel));
+    }
+
+    pub fn channel(&self, instrument: InstrumentId) -> Option<&String> {
+        self.channels.get(&instrument)
+    }
+
+    /// Channel of the ticker instrument, the ticker is counted for the rebalance
+    pub fn on_ticker(&mut self, instrument: InstrumentId) -> Option<&String> {
+        let channel = self.channels.get(&instrument)?;
+        *self.messages.entry(channel.clone()).or_insert(0) += 1;
+        Some(channel)
+    }
+
+    pub fn take_messages(&mut self) -> HashMap<String, u64> {
+        std::mem::take(&mut self.messages)
+    }
+}
+
+pub struct StreamConnector<V: Venue> {
+    venue: V,
+    // the shard id
+    entity_id: usize,
+    // index of the redundant socket set
+    connection: usize,
+    bus: Arc<EventBus>,
+    instruments: Arc<InstrumentsRegistry>,
+    instruments_version: usize,
+    // snapshot of the registry
+    instruments_map: Arc<InstrumentsMap>,
+    shards: Arc<Shards>,
+    shards_version: usize,
+    channels: Vec<String>,
+    index: ChannelIndex,
+
+    socket: Option<WebSocket<MaybeTlsStream<TcpStream>>>,
+
+    request_latest_ts: Arc<RwLock<u128>>,
+    request_id: usize,
+
+    latest_ticker_ts: u128,
+    next_ping_ts: u128,
+    sequences: SequenceTracker,
+    // channels with the update ids gap or silent, resubscribed
+    pending_resubscribe: Vec<String>,
+    watchdog: Watchdog,
+    next_report_ts: u128,
+
+    tickers_received: Counter,
+    reconnects: Counter,
+}
+
+impl<V: Venue> StreamConnector<V> {
+    pub fn new(
+        entity_id: usize,
+        connection: usize,
+        bus: Arc<EventBus>,
+        shards: Arc<Shards>,
+        instruments: Arc<InstrumentsRegistry>,
+        request_latest_ts: Arc<RwLock<u128>>,
+    ) -> Self {
+        let (connection_label, socket_label) = (connection.to_string(), entity_id.to_string());
+        let labels = [("exchange", V::EXCHANGE.as_str()), ("connection", &connection_label), ("socket", &socket_label)];
+        Self {
+            venue: V::default(),
+            entity_id,
+            connection,
+            bus,
+            instruments_version: instruments.version(),
+            instruments_map: instruments.load(),
+            instruments,
+            shards,
+            shards_version: 0,
+            channels: Vec::new(),
+            index: ChannelIndex::default(),
+            socket: None,
+            request_latest_ts,
+            request_id: 0,
+            latest_ticker_ts: 0,
+            next_ping_ts: 0,
+            sequences: SequenceTracker::new(),
+            pending_resubscribe: Vec::new(),
+            watchdog: Watchdog::new(V::SOCKET_SILENCE_TIMEOUT, V::INSTRUMENT_SILENCE_TIMEOUT),
+            next_report_ts: 0,
+            tickers_received: METRICS.counter("arbot_tickers_received_total", "Price tickers published to the bus", &labels),
+            reconnects: METRICS.counter("arbot_stream_reconnects_total", "Price ticker stream reconnects", &labels),
+        }
+    }
+
+    /// Spread the tickers over the sockets within the venue limits, rebalanced by the observed message rates
+    pub fn listen_from_tickers(
+        bus: Arc<EventBus>,
+        tickers: Vec<String>,
+        instruments: Arc<InstrumentsRegistry>,
+    ) -> Arc<Shards> {
+        Self::listen_connection(bus, tickers, instruments, 0, Arc::new(RwLock::new(0)))
+    }
+
+    /// Every ticker is subscribed on the independent socket sets, the filter takes the first copy of the update
+    pub fn listen_from_tickers_redundant(
+        bus: Arc<EventBus>,
+        tickers: Vec<String>,
+        instruments: Arc<InstrumentsRegistry>,
+        connections: usize,
+    ) -> Vec<Arc<Shards>> {
+        // the requests rate is limited per venue
+        let request_latest_ts = Arc::new(RwLock::new(0));
+        (0..connections)
+            .map(|connection| Self::listen_connection(Arc::clone(&bus), tickers.clone(), Arc::clone(&instruments), connection, Arc::clone(&request_latest_ts)))
+            .collect()
+    }
+
+    fn listen_connection(
+        bus: Arc<EventBus>,
+        tickers: Vec<String>,
+        instruments: Arc<InstrumentsRegistry>,
+        connection: usize,
+        request_latest_ts: Arc<RwLock<u128>>,
+    ) -> Arc<Shards> {
+        let channels = tickers.iter().map(|ticker| V::ticker_to_channel(ticker)).collect_vec();
+        let shards = Arc::new(Shards::new(V::EXCHANGE, channels, V::MAX_CHANNELS_PER_STREAM));
+
+        for id in 0..shards.len() {
+            Self::spawn_stream(id, connection, &bus, &shards, &instruments, &request_latest_ts);
+            thread::sleep(V::SPAWN_DELAY);
+        }
+
+        let shards_ref = Arc::clone(&shards);
+        let mut spawned = shards.len();
+        let mut next_rebalance_ts = time() + REBALANCE_INTERVAL.as_nanos();
+        thread::Builder::new().name(format!("{:?}_shards_{connection}", V::EXCHANGE)).spawn(move || loop {
+            thread::sleep(SHARDS_CHECK_INTERVAL);
+            if time() >= next_rebalance_ts {
+                shards_ref.rebalance(time());
+                next_rebalance_ts = time() + REBALANCE_INTERVAL.as_nanos();
+            }
+            // appended by the rebalance or by the added instruments
+            let count = shards_ref.len();
+            for id in spawned..count {
+                log::info!("{:?} spawn the socket for the shard {id}", V::EXCHANGE);
+                Self::spawn_stream(id, connection, &bus, &shards_ref, &instruments, &request_latest_ts);
+                thread::sleep(V::SPAWN_DELAY);
+            }
+            spawned = count;
+        }).expect("Failed to spawn shards thread");
+        shards
+    }
+
+    /// Assign the tickers of the added instruments to the running shards, the sockets subscribe them paced
+    pub fn add_tickers(shards: &[Arc<Shards>], tickers: &[String]) {
+        let channels = tickers.iter().map(|ticker| V::ticker_to_channel(ticker)).collect_vec();
+        for shards in shards {
+            let added = shards.add(channels.clone());
+            log::info!("{:?} added {} channels, {added} new sockets", V::EXCHANGE, channels.len());
+        }
+    }
+
+    fn spawn_stream(socket_id: usize, connection: usize, bus: &Arc<EventBus>, shards: &Arc<Shards>, instruments: &Arc<InstrumentsRegistry>, request_latest_ts: &Arc<RwLock<u128>>) {
+        let bus_ref = Arc::clone(bus);
+        let shards_ref = Arc::clone(shards);
+        let instruments_ref = Arc::clone(instruments);
+        let request_latest_ts_ref = Arc::clone(request_latest_ts);
+
+        thread::Builder::new().name(format!("{:?}_pt_{connection}_{socket_id}", V::EXCHANGE)).spawn(move || {
+            Self::new(
+                socket_id,
+                connection,
+                bus_ref,
+                shards_ref,
+                instruments_ref,
+                request_latest_ts_ref,
+            ).run()
+        }).expect("Failed to spawn price ticker thread");
+    }
+
+    fn connect(&mut self) -> Res {
+        let (mut socket, response) = connect(V::URL)?;
+        log::info!("Connected to {}. Response HTTP code: {}", V::URL, response.status());
+
+        if !(100..400).contains(&response.status().as_u16()) {
+            return Err(response.status().as_str().into());
+        }
+        match socket.get_mut() {
+            MaybeTlsStream::Rustls(ref mut t) => t.get_mut().set_read_timeout(Some(READ_TIMEOUT))?,
+            MaybeTlsStream::Plain(ref mut t) => t.set_read_timeout(Some(READ_TIMEOUT))?,
+            _ => return Err(format!("Unsupported stream type of {}", V::URL).into()),
+        }
+
+        self.request_id = 0;
+        self.next_ping_ts = 0;
+        self.sequences.clear();
+        self.pending_resubscribe.clear();
+        self.venue.on_connect();
+        self.socket = Some(socket);
+        Ok(())
+    }
+
+    fn connect_with_retries(&mut self) -> Res {
+        for i in 1..=CONNECT_RETRIES {
+            match self.connect() {
+                Ok(_) => return Ok(()),
+                Err(err) => {
+                    log::warn!("Failed to connect {:?} stream: {err}. Retry {i}/{CONNECT_RETRIES}", V::EXCHANGE);
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+        }
+        Err("Failed to connect".into())
+    }
+
+    fn run(&mut self) {
+        let mut reconnect_sleep = 0;
+        loop {
+            (self.shards_version, self.channels) = self.shards.channels(self.entity_id);
+            let result = self.connect_with_retries()
+                .and_then(|_| self.sync_instruments(false))
+                .and_then(|_| self.subscribe())
+                .and_then(|_| {
+                    log::info!("Subscription done");
+                    self.push_status(MonitoringStatus::Ok);
+                    reconnect_sleep = 0;
+                    self.handle()
+                });
+            let reason = match result {
+                Ok(_) => {
+                    log::warn!("Handling messages stopped");
+                    "stopped".to_string()
+                }
+                Err(err) => {
+                    log::error!("{:?} stream {} failed: {err}", V::EXCHANGE, self.entity_id);
+                    err.to_string()
+                }
+            };
+
+            self.close_socket();
+            self.shards.record_disconnect(self.entity_id, time());
+            self.push_status(MonitoringStatus::Error);
+            self.reconnects.inc();
+
+            reconnect_sleep = (reconnect_sleep + RECONNECT_BACKOFF_STEP).min(RECONNECT_BACKOFF_MAX);
+            log_event(log::Level::Warn, "stream_reconnect", object! {
+                exchange: V::EXCHANGE.as_str(),
+                connection: self.connection,
+                socket: self.entity_id,
+                reason: reason,
+                delay_s: reconnect_sleep,
+            });
+            thread::sleep(Duration::from_secs(reconnect_sleep));
+        }
+    }
+
+    fn push_status(&self, status: MonitoringStatus) {
+        let mut message = MonitoringMessage::new(time(), status, MonitoringEntity::PriceTicker, V::EXCHANGE, self.entity_id);
+        message.connection = self.connection;
+        self.bus.publish(DTO::MonitoringMessage(message));
+    }
+
+    fn send_text(&mut self, text: String, delay: Duration) -> Res {
+        let mut ts = self.request_latest_ts.write().expect("Can't get the lock");
+        self.socket.as_mut().unwrap().send(Message::Text(text))?;
+        thread::sleep(delay);
+        *ts = time();
+        Ok(())
+    }
+
+    fn next_id(&mut self) -> usize {
+        let id = self.request_id;
+        self.request_id += 1;
+        id
+    }
+
+    fn subscribe(&mut self) -> Res {
+        let batches = self.channels.chunks(V::MAX_CHANNELS_PER_REQUEST).map(<[String]>::to_vec).collect_vec();
+        for (i, items) in batches.iter().enumerate() {
+            log::info!("Subscribe the batch: {i}");
+            let id = self.next_id();
+            let request = self.venue.subscribe_message(items, id);
+            self.send_message_and_handle(request)?;
+            log::info!("Subscribed the batch: {}/{}", i + 1, batches.len());
+        }
+
+        let id = self.next_id();
+        if let Some(request) = self.venue.list_subscriptions(id) {
+            let response = self.send_message_and_handle(request)?;
+            let subscriptions = self.venue.subscriptions(&response);
+            if subscriptions != HashSet::from_iter(self.channels.iter().cloned()) {
+                return Err(format!("Subscriptions mismatch: {subscriptions:?}").into());
+            }
+            log::info!("Subs checked");
+        }
+        self.index.update::<V>(&self.channels, &self.instruments_map);
+        self.watchdog.reset(time(), self.channels.iter().map(String::as_str));
+        Ok(())
+    }
+
+    /// Resubscribe the channels the venue reported as corrupted or with the updates gap, the stream is errored meanwhile
+    fn resubscribe(&mut self) -> Res {
+        let mut channels = self.venue.take_resubscribe();
+        channels.append(&mut self.pending_resubscribe);
+        if channels.is_empty() {
+            return Ok(());
+        }
+        channels.sort();
+        channels.dedup();
+        for channel in channels.iter() {
+            self.sequences.remove(channel);
+            self.watchdog.watch(time(), channel);
+        }
+        log::warn!("{:?} stream {} resubscribes {channels:?}", V::EXCHANGE, self.entity_id);
+        self.push_status(MonitoringStatus::Error);
+        for items in channels.chunks(V::MAX_CHANNELS_PER_REQUEST) {
+            let id = self.next_id();
+            let request = self.venue.unsubscribe_message(items, id);
+            self.send_message_and_handle(request)?;
+            let id = self.next_id();
+            let request = self.venue.subscribe_message(items, id);
+            self.send_message_and_handle(request)?;
+        }
+        self.push_status(MonitoringStatus::Ok);
+        Ok(())
+    }
+
+    /// Follow the channels moved by the shards rebalance
+    fn sync_shard(&mut self) -> Res {
+        if self.shards.version() == self.shards_version {
+            return Ok(());
+        }
+        let (version, channels) = self.shards.channels(self.entity_id);
+        let removed = self.channels.iter().filter(|c| !channels.contains(c)).cloned().collect_vec();
+        let added = channels.iter().filter(|c| !self.channels.contains(c)).cloned().collect_vec();
+        if !removed.is_empty() {
+            log::info!("{:?} stream {} moves off {removed:?}", V::EXCHANGE, self.entity_id);
+            for channel in removed.iter() {
+                self.watchdog.unwatch(channel);
+            }
+            for items in removed.chunks(V::MAX_CHANNELS_PER_REQUEST) {
+                let id = self.next_id();
+                let request = self.venue.unsubscribe_message(items, id);
+                self.send_message_and_handle(request)?;
+            }
+        }
+        if !added.is_empty() {
+            log::info!("{:?} stream {} takes over {added:?}", V::EXCHANGE, self.entity_id);
+            for items in added.chunks(V::MAX_CHANNELS_PER_REQUEST) {
+                let id = self.next_id();
+                let request = self.venue.subscribe_message(items, id);
+                self.send_message_and_handle(request)?;
+            }
+            for channel in added.iter() {
+                self.watchdog.watch(time(), channel);
+            }
+        }
+        self.channels = channels;
+        self.index.update::<V>(&self.channels, &self.instruments_map);
+        self.shards_version = version;
+        Ok(())
+    }
+
+    /// Report the tickers count per channel for the rebalance
+    fn report_messages(&mut self, ts: u128) {
+        if self.next_report_ts > ts {
+            return;
+        }
+        self.next_report_ts = ts + REPORT_INTERVAL.as_nanos();
+        self.shards.record_messages(self.entity_id, self.index.take_messages());
+    }
+
+    /// Refresh the instruments snapshot, drop the channels of the removed instruments
+    fn sync_instruments(&mut self, subscribed: bool) -> Res {
+        let version = self.instruments.version();
+        if version == self.instruments_version {
+            return Ok(());
+        }
+        let instruments_map = self.instruments.load();
+        let removed = self.channels.iter()
+            .filter(|c| instruments_map.get(&V::EXCHANGE, V::channel_to_symbol(c)).is_none())
+            .cloned()
+            .collect_vec();
+
+        if !removed.is_empty() {
+            if subscribed {
+                log::info!("Unsubscribe removed instruments: {removed:?}");
+                for items in removed.chunks(V::MAX_CHANNELS_PER_REQUEST) {
+                    let id = self.next_id();
+                    let request = self.venue.unsubscribe_message(items, id);
+                    self.send_message_and_handle(request)?;
+                }
+            }
+            self.channels.retain(|c| !removed.contains(c));
+            self.shards.remove(self.entity_id, &removed);
+            for channel in removed.iter() {
+                self.watchdog.unwatch(channel);
+            }
+        }
+        // swap after the unsubscription, the removed tickers are still in flight till then
+        self.instruments_map = instruments_map;
+        self.instruments_version = version;
+        self.index.update::<V>(&self.channels, &self.instruments_map);
+        Ok(())
+    }
+
+    /// Send the request and wait for the response, the tickers received meanwhile are handled
+    fn send_message_and_handle(&mut self, request: JsonValue) -> Result<JsonValue, Box<dyn std::error::Error>> {
+        self.send_text(request.dump(), V::REQUEST_DELAY)?;
+        let deadline = time() + RESPONSE_TIMEOUT.as_nanos();
+        loop {
+            let ts = time();
+            if ts > deadline {
+                return Err(format!("No response to {request}").into());
+            }
+            match self.read()? {
+                Some(Message::Text(raw)) => match self.venue.ack(&request, &raw) {
+                    Some(response) => return response.map_err(|err| format!("Request {request} rejected: {err}").into()),
+                    None => self.handle_text(ts, &raw)?,
+                },
+                Some(Message::Ping(payload)) => self.handle_ping(ts, payload)?,
+                Some(Message::Close(reason)) => return Err(format!("Got the close frame: {reason:?}").into()),
+                Some(msg) => log::warn!("Unexpected msg type: {msg:?}"),
+                None => {}
+            }
+        }
+    }
+
+    /// Next message, None on the read timeout
+    fn read(&mut self) -> Result<Option<Message>, Box<Error>> {
+        match self.socket.as_mut().unwrap().read() {
+            Ok(msg) => Ok(Some(msg)),
+            // That occurs when no messages are to be read
+            Err(Error::Io(ref e)) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => Ok(None),
+            Err(err) => Err(Box::new(err)),
+        }
+    }
+
+    fn handle_ping(&mut self, ts: u128, payload: Vec<u8>) -> Res {
+        let price_ticker_lag = Duration::from_nanos((ts - self.latest_ticker_ts) as u64).as_millis();
+        log::info!("Ping received. Latest ticker {price_ticker_lag}ms");
+        self.socket.as_mut().unwrap().send(Message::Pong(payload))?;
+        Ok(())
+    }
+
+    fn send_client_ping(&mut self, ts: u128) -> Res {
+        if let PingPolicy::Client { message, interval } = self.venue.ping_policy() {
+            if self.next_ping_ts == 0 {
+                self.next_ping_ts = ts + interval.as_nanos();
+            } else if self.next_ping_ts < ts {
+                log::info!("Send ping");
+                self.send_text(message, Duration::ZERO)?;
+                self.next_ping_ts = ts + interval.as_nanos();
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_text(&mut self, ts: u128, raw: &str) -> Res {
+        if self.venue.is_pong(raw) {
+            let price_ticker_lag = Duration::from_nanos((ts - self.latest_ticker_ts) as u64).as_millis();
+            log::info!("Pong received. Latest price ticker: {price_ticker_lag}ms");
+            return Ok(());
+        }
+        match self.venue.parse(ts, raw, &self.instruments_map) {
+            Ok(Some(mut price_ticker)) => {
+                if price_ticker.update_id != 0 {
+                    let symbol = &price_ticker.instrument.symbol;
+                    let channel = self.index.channel(price_ticker.instrument.id);
+                    match self.sequences.check(channel.unwrap_or(symbol), price_ticker.update_id, V::SEQUENTIAL_UPDATE_IDS) {
+                        Sequence::Next => {}
+                        Sequence::Stale => {
+                            log::debug!("{:?} {symbol} stale update {}", V::EXCHANGE, price_ticker.update_id);
+                            return Ok(());
+                        }
+                        Sequence::Gap(missed) => {
+                            log::warn!("{:?} {symbol} missed {missed} updates before {}", V::EXCHANGE, price_ticker.update_id);
+                            self.pending_resubscribe.extend(channel.cloned());
+                            return Ok(());
+                        }
+                    }
+                }
+                self.latest_ticker_ts = ts;
+                // the ticker of the channel moving between the shards refreshes the socket only
+                let channel = self.index.on_ticker(price_ticker.instrument.id).unwrap_or(&price_ticker.instrument.symbol);
+                self.watchdog.on_update(ts, channel);
+                price_ticker.connection = self.connection;
+                LATENCY.record(&V::EXCHANGE, Stage::SocketRead, price_ticker.exchange_ts, ts);
+                LATENCY.record(&V::EXCHANGE, Stage::QueuePush, ts, time());
+                self.bus.publish(DTO::PriceTicker(price_ticker));
+                self.tickers_received.inc();
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(err) => Err(format!("Can't parse price ticker {raw}: {err}").into()),
+        }
+    }
+
+    /// The silent socket fails and reconnects, the silent instruments are resubscribed
+    fn check_silence(&mut self, ts: u128) -> Res {
+        match self.watchdog.check(ts) {
+            Some(Silence::Socket(silence)) => Err(format!("No price tickers for {}s", silence.as_secs()).into()),
+            Some(Silence::Instruments(channels)) => {
+                log::warn!("{:?} stream {} silent channels: {channels:?}", V::EXCHANGE, self.entity_id);
+                self.pending_resubscribe.extend(channels);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn handle(&mut self) -> Res {
+        loop {
+            let msg = self.read()?;
+            let ts = time();
+            match msg {
+                Some(Message::Text(raw)) => self.handle_text(ts, &raw)?,
+                Some(Message::Ping(payload)) => self.handle_ping(ts, payload)?,
+                Some(Message::Close(reason)) => {
+                    log::warn!("Got the close frame: {reason:?}");
+                    return Ok(());
+                }
+                Some(msg) => log::warn!("Unexpected msg: {msg}"),
+                None => {}
+            }
+            self.sync_instruments(true)?;
+            self.check_silence(ts)?;
+            self.resubscribe()?;
+            self.sync_shard()?;
+            self.report_messages(ts);
+            self.send_client_ping(ts)?;
+        }
+    }
+
+    fn close_socket(&mut self) {
+        if let Some(mut socket) = self.socket.take() {
+            match socket.close(None) {
+                Ok(_) => {
+                    log::info!("Socket closed successfully");
+                }
+                Err(err) => {
+                    log::warn!("Error during the socket closing: {}", err);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::DateTime;
+use json::{object, JsonValue};
+use crate::core::{
+    dto::PriceTicker,
+    map::InstrumentsMap,
+    utils::json_to_decimal,
+};
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Exchange, Instrument, TICKER_PRICE_NOT_CHANGED};
+use crate::core::streams::connector::{BatchAcks, PingPolicy, StreamConnector, Venue};
+use crate::core::utils::RoundingMode;
+
+pub type PriceTickerStream = StreamConnector<KrakenVenue>;
+
+// levels per side, the checksum covers all of them
+const DEPTH: usize = 10;
+
+/// Local top of the book, kept to validate the exchange checksum
+#[derive(Default)]
+pub struct KrakenBook {
+    pub bids: BTreeMap<Decimal, Decimal>,
+    pub asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl KrakenBook {
+    fn apply(side: &mut BTreeMap<Decimal, Decimal>, levels: &JsonValue) -> Result<(), String> {
+        for level in levels.members() {
+            let price = json_to_decimal(&level["price"]).ok_or(format!("Invalid price: {level}"))?;
+            let qty = json_to_decimal(&level["qty"]).ok_or(format!("Invalid qty: {level}"))?;
+            if qty.is_zero() {
+                side.remove(&price);
+            } else {
+                side.insert(price, qty);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn update(&mut self, data: &JsonValue) -> Result<(), String> {
+        Self::apply(&mut self.bids, &data["bids"])?;
+        Self::apply(&mut self.asks, &data["asks"])?;
+        // levels out of the subscribed depth aren't updated anymore
+        while self.bids.len() > DEPTH {
+            self.bids.pop_first();
+        }
+        while self.asks.len() > DEPTH {
+            self.asks.pop_last();
+        }
+        Ok(())
+    }
+
+    /// CRC32 of the asks from the best, then the bids from the best.
+    /// Each level is the price and qty in the instrument precision without the point and leading zeros
+    pub fn checksum(&self, instrument: &Instrument) -> u32 {
+        let digits = |value: &Decimal, precision: usize| value.round(precision as u32, RoundingMode::Down).mantissa().to_string();
+        let mut payload = String::new();
+        for (price, qty) in self.asks.iter().chain(self.bids.iter().rev()) {
+            payload.push_str(&digits(price, instrument.price_precision));
+            payload.push_str(&digits(qty, instrument.amount_precision));
+        }
+        crc32fast::hash(payload.as_bytes())
+    }
+}
+
+#[derive(Default)]
+pub struct KrakenVenue {
+    books: HashMap<String, KrakenBook>,
+    // symbols waiting for the new snapshot
+    corrupted: HashSet<String>,
+    resubscribe: Vec<String>,
+    acks: BatchAcks,
+}
+
+impl KrakenVenue {
+    fn request(method: &str, channels: &[String], request_id: usize) -> JsonValue {
+        object! {
+            method: method,
+            params: object! { channel: "book", symbol: channels, depth: DEPTH },
+            req_id: request_id,
+        }
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&KrakenBook> {
+        self.books.get(symbol)
+    }
+}
+
+impl Venue for KrakenVenue {
+    const EXCHANGE: Exchange = Exchange::Kraken;
+    const URL: &'static str = "wss://ws.kraken.com/v2";
+    const MAX_CHANNELS_PER_STREAM: usize = 100;
+    const MAX_CHANNELS_PER_REQUEST: usize = 20;
+    const REQUEST_DELAY: Duration = Duration::from_millis(100);
+
+    fn ticker_to_channel(ticker: &str) -> String {
+        ticker.to_uppercase()
+    }
+
+    fn channel_to_symbol(channel: &str) -> &str {
+        channel
+    }
+
+    fn subscribe_message(&self, channels: &[String], request_id: usize) -> JsonValue {
+        Self::request("subscribe", channels, request_id)
+    }
+
+    fn unsubscribe_message(&self, channels: &[String], request_id: usize) -> JsonValue {
+        Self::request("unsubscribe", channels, request_id)
+    }
+
+    // every symbol is acknowledged separately, the request is done when all of them are
+    fn ack(&mut self, request: &JsonValue, raw: &str) -> Option<Result<JsonValue, String>> {
+        if !raw.contains("\"req_id\"") {
+            return None;
+        }
+        let data = json::parse(raw).ok()?;
+        if data["method"] != request["method"] || data["req_id"] != request["req_id"] {
+            return None;
+        }
+        let symbols = request["params"]["symbol"].members().filter_map(|symbol| symbol.as_str().map(String::from));
+        self.acks.track(request, symbols);
+        if data["success"] != true {
+            self.acks.clear();
+            return Some(Err(data["error"].to_string()));
+        }
+        if !self.acks.ack(data["result"]["symbol"].as_str()?) {
+            return None;
+        }
+        Some(Ok(data))
+    }
+
+    fn ping_policy(&self) -> PingPolicy {
+        PingPolicy::Client { message: object! { method: "ping" }.dump(), interval: Duration::from_secs(30) }
+    }
+
+    fn is_pong(&self, raw: &str) -> bool {
+        raw.contains("\"method\":\"pong\"")
+    }
+
+    fn on_connect(&mut self) {
+        self.books.clear();
+        self.corrupted.clear();
+        self.resubscribe.clear();
+        self.acks.clear();
+    }
+
+    fn take_resubscribe(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.resubscribe)
+    }
+
+    fn parse(&mut self, ts: u128, raw: &str, instruments: &InstrumentsMap) -> Result<Option<PriceTicker>, String> {
+        let data = &json::parse(raw).map_err(|err| err.to_string())?;
+        if data["channel"] != "book" {
+            return Ok(None);
+        }
+        let is_snapshot = data["type"] == "snapshot";
+        // the snapshot has no timestamp
+        let exchange_ts = |item: &JsonValue| item["timestamp"].as_str()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .and_then(|ts| ts.timestamp_nanos_opt())
+            .map_or(0, |ts| ts as u128);
+        let mut price_ticker = None;
+        for item in data["data"].members() {
+            let symbol = item["symbol"].as_str().ok_or("No symbol")?;
+            let instrument = instruments.get(&Exchange::Kraken, symbol).ok_or(format!("No instrument: {symbol}"))?;
+            if is_snapshot {
+                self.books.remove(symbol);
+                self.corrupted.remove(symbol);
+            } else if self.corrupted.contains(symbol) {
+                continue;
+            }
+            let book = self.books.entry(symbol.to_string()).or_default();
+            book.update(item)?;
+
+            let exchange_ts = exchange_ts(item);
+            let checksum = book.checksum(instrument);
+            if item["checksum"].as_u32() != Some(checksum) {
+                log::error!("Kraken {symbol} book checksum mismatch: {} != {checksum}", item["checksum"]);
+                self.books.remove(symbol);
+                self.corrupted.insert(symbol.to_string());
+                self.resubscribe.push(Self::ticker_to_channel(symbol));
+                continue;
+            }
+
+            let (bid, bid_amount) = book.bids.last_key_value().map(|(p, q)| (*p, *q)).unwrap_or((TICKER_PRICE_NOT_CHANGED, TICKER_PRICE_NOT_CHANGED));
+            let (ask, ask_amount) = book.asks.first_key_value().map(|(p, q)| (*p, *q)).unwrap_or((TICKER_PRICE_NOT_CHANGED, TICKER_PRICE_NOT_CHANGED));
+            price_ticker = Some(PriceTicker {
+                timestamp: ts,
+                instrument: Arc::clone(instrument),
+                bid,
+                bid_amount,
+                ask,
+                ask_amount,
+                update_id: 0,
+                exchange_ts,
+                connection: 0,
+            });
+        }
+        Ok(price_ticker)
+    }
+}
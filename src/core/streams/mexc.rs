@@ -1,335 +1,102 @@
-use std::net::TcpStream;
-use std::sync::{Arc, RwLock};
-use std::thread;
+use std::sync::Arc;
 use std::time::Duration;
-use crossbeam_queue::ArrayQueue;
-use itertools::Itertools;
 use json::{object, JsonValue};
-use tungstenite::stream::{MaybeTlsStream};
-use tungstenite::{connect, Error, Message, WebSocket};
 use crate::core::{
     dto::PriceTicker,
     map::InstrumentsMap,
-    utils::{time},
+    utils::json_millis_to_nanos,
 };
-use crate::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus, DTO, TICKER_PRICE_NOT_CHANGED};
+use crate::core::decimal::Decimal;
+use crate::core::dto::{Exchange, TICKER_PRICE_NOT_CHANGED};
+use crate::core::streams::connector::{PingPolicy, StreamConnector, Venue};
 
-#[allow(dead_code)]
-pub struct PriceTickerStream {
-    entity_id: usize,
-    queue: Arc<ArrayQueue<DTO>>,
-    instruments_map: Arc<InstrumentsMap>,
-    channels: Vec<String>,
-    channels_per_request: usize,
+pub type PriceTickerStream = StreamConnector<MexcVenue>;
 
-    socket: Option<WebSocket<MaybeTlsStream<TcpStream>>>,
+const PONG: &str = "{\"id\":0,\"code\":0,\"msg\":\"PONG\"}";
 
-    request_latency: u64,
-    request_latest_ts: Arc<RwLock<u128>>,
-    request_id: usize,
+#[derive(Default)]
+pub struct MexcVenue;
 
-    latest_ticker_ts: u128,
-
-    next_ping_ts: u128,
-}
-
-#[allow(dead_code)]
-impl PriceTickerStream {
-    pub fn new(
-        entity_id: usize,
-        queue: Arc<ArrayQueue<DTO>>,
-        channels: Vec<String>,
-        instruments_map: Arc<InstrumentsMap>,
-        channels_per_request: usize,
-        request_latency: u64,
-        request_latest_ts: Arc<RwLock<u128>>,
-    ) -> Self {
-        Self {
-            entity_id,
-            queue,
-            instruments_map,
-            channels_per_request,
-            channels,
-            request_latency,
-            request_latest_ts,
-            socket: None,
-            request_id: 0,
-            latest_ticker_ts: 0,
-            next_ping_ts: time() + Duration::from_secs(25).as_nanos(),
+impl MexcVenue {
+    // empty price - the side isn't changed
+    fn parse_side(data: &JsonValue, price: &str, amount: &str) -> Result<(Decimal, Decimal), String> {
+        let raw_price = data[price].as_str().ok_or(format!("No '{price}'"))?;
+        if raw_price.is_empty() {
+            return Ok((TICKER_PRICE_NOT_CHANGED, TICKER_PRICE_NOT_CHANGED));
         }
+        let raw_amount = data[amount].as_str().ok_or(format!("No '{amount}'"))?;
+        let parse = |raw: &str| raw.parse::<Decimal>().map_err(|err| format!("{err:?}"));
+        Ok((parse(raw_price)?, parse(raw_amount)?))
     }
+}
 
-    fn connect(&mut self) {
-        let (mut socket, response) = connect("wss://wbs.mexc.com/ws").expect("Can't connect");
-        log::info!("Connected to the server. Response HTTP code: {}", response.status());
-
-        match socket.get_mut() {
-            MaybeTlsStream::Rustls(ref mut t) => {
-                // -- use either one or another
-                //t.get_mut().set_nonblocking(true);
-                t.get_mut().set_read_timeout(Some(Duration::from_millis(10000))).expect("Error: cannot set read-timeout to underlying stream");
-            }
-            // handle more cases as necessary, this one only focuses on native-tls
-            _ => unimplemented!()
-        }
-
-        assert!((100..400).contains(&response.status().as_u16()));
-        self.request_id = 0;
-        self.socket = Some(socket);
-    }
+impl Venue for MexcVenue {
+    const EXCHANGE: Exchange = Exchange::Mexc;
+    const URL: &'static str = "wss://wbs.mexc.com/ws";
+    const MAX_CHANNELS_PER_STREAM: usize = 30;
+    const MAX_CHANNELS_PER_REQUEST: usize = 30;
+    const REQUEST_DELAY: Duration = Duration::from_millis(250);
+    const SPAWN_DELAY: Duration = Duration::from_millis(1000);
 
-    pub fn ticker_to_channel(ticker: &String) -> String {
+    fn ticker_to_channel(ticker: &str) -> String {
         let mut s = "spot@public.bookTicker.v3.api@".to_string();
         s.push_str(&ticker.to_uppercase().replace("/", ""));
         s
     }
 
-    pub fn listen_from_tickers_split(
-        queue: Arc<ArrayQueue<DTO>>,
-        tickers: Vec<String>,
-        instruments_map: Arc<InstrumentsMap>,
-        channels_per_stream: usize,
-        channels_per_request: usize,
-    ) -> usize {
-        assert!(channels_per_stream <= 30);
-        assert!(channels_per_request <= 30);
-        let request_latest_ts = Arc::new(RwLock::new(0));
-
-        let mut sockets_count = 0;
-        for (_, channels) in tickers.iter()
-            .map(Self::ticker_to_channel)
-            .chunks(channels_per_stream)
-            .into_iter()
-            .map(|chunk| chunk.collect_vec())
-            .enumerate() {
-            Self::spawn_stream(sockets_count, channels, &queue, &instruments_map, channels_per_request, &request_latest_ts);
-            thread::sleep(Duration::from_millis(1000));
-            sockets_count += 1
-        }
-        sockets_count
+    fn channel_to_symbol(channel: &str) -> &str {
+        channel.rsplit('@').next().unwrap()
     }
 
-    pub fn listen_from_tickers_group(
-        queue: Arc<ArrayQueue<DTO>>,
-        tickers_groups: Vec<Vec<String>>,
-        instruments_map: Arc<InstrumentsMap>,
-        channels_per_request: usize,
-    ) -> usize {
-        let request_latest_ts = Arc::new(RwLock::new(0));
-
-        let mut sockets_count = 0;
-        for channels in tickers_groups.iter().map(|v| v.iter().map(Self::ticker_to_channel).collect_vec()) {
-            Self::spawn_stream(sockets_count, channels, &queue, &instruments_map, channels_per_request, &request_latest_ts);
-            sockets_count += 1
-        }
-        sockets_count
+    fn subscribe_message(&self, channels: &[String], _request_id: usize) -> JsonValue {
+        object! {method: "SUBSCRIPTION", params: channels}
     }
 
-    fn spawn_stream(socket_id: usize, channels: Vec<String>, queue: &Arc<ArrayQueue<DTO>>, instruments_map: &Arc<InstrumentsMap>, channels_per_request: usize, request_latest_ts: &Arc<RwLock<u128>>) {
-        let queue_ref = Arc::clone(queue);
-        let instruments_map_ref = Arc::clone(instruments_map);
-        let request_latest_ts_ref = Arc::clone(request_latest_ts);
-
-        thread::Builder::new().name(format!("{:?}_pt_{}", Exchange::Mexc, socket_id)).spawn(move || {
-            Self::new(
-                socket_id,
-                queue_ref,
-                channels,
-                instruments_map_ref,
-                channels_per_request,
-                250,
-                request_latest_ts_ref,
-            ).run()
-        }).expect("Failed to spawn price ticker thread");
-    }
-
-    fn send_json(&mut self, data: JsonValue) {
-        self.send_json_with_sleep(data, Duration::from_millis(250));
+    fn unsubscribe_message(&self, channels: &[String], _request_id: usize) -> JsonValue {
+        object! {method: "UNSUBSCRIPTION", params: channels}
     }
 
-    fn send_json_with_sleep(&mut self, data: JsonValue, duration: Duration) {
-        let mut ts = self.request_latest_ts.write().expect("Can't get the lock");
-        self.socket.as_mut().unwrap().send(Message::Text(json::stringify(data))).expect("Can't send the data");
-        thread::sleep(duration);
-        *ts = time();
-    }
-
-    fn run(&mut self) {
-        let mut reconnect_sleep = 0;
-        loop {
-            self.connect();
-            self.subscribe();
-            log::info!("Subscription done");
-            self.handle();
-            self.close_socket();
-            self.queue.push(
-                DTO::MonitoringMessage(MonitoringMessage::new(
-                    time(),
-                    MonitoringStatus::Error, MonitoringEntity::PriceTicker,
-                    self.entity_id,
-                ))
-            ).expect("Can't push error message");
-
-            reconnect_sleep += 15;
-            log::warn!("Reconnect stream in {reconnect_sleep}s...");
-            thread::sleep(Duration::from_secs(reconnect_sleep));
+    fn ack(&mut self, _request: &JsonValue, raw: &str) -> Option<Result<JsonValue, String>> {
+        if !raw.starts_with("{\"id") || self.is_pong(raw) {
+            return None;
         }
-    }
-
-    fn subscribe(&mut self) {
-        for (i, items) in self.channels.clone()
-            .chunks(self.channels_per_request)
-            .into_iter()
-            .enumerate()
-        {
-            log::info!("Subscribe the batch: {i}");
-            self.send_message_and_handle(
-                object! {method: "SUBSCRIPTION", params: items},
-                |_, data: JsonValue| assert!(data["code"].as_usize().unwrap() == 0),
-            );
-            log::info!("Subscribed the batch: {i}");
-        }
-
-        // self.send_message_and_handle(
-        //     object! {method: "LIST_SUBSCRIPTIONS"},
-        //     |this, data|
-        //         assert_eq!(
-        //             HashSet::<String>::from_iter(
-        //                 data["result"]
-        //                     .members()
-        //                     .map(|item| item.to_string())  // Ensure item is a string and convert
-        //             ),
-        //             HashSet::from_iter(this.channels.clone()),
-        //         ),
-        // );
-        // log::info!("Subs checked")
-    }
-
-    fn parse_price_ticker(&mut self, ts: u128, raw: &str) -> Option<DTO> {
-        let data = &json::parse(&raw).expect("Can't parse json");
-        let symbol = data["s"].as_str().expect(&format!("No symbol: {raw}"));
-        let instrument_arc = self.instruments_map.get(&Exchange::Mexc, symbol).expect(&format!("No instrument: {symbol}"));
-        let ticker_data = &data["d"];
-
-        let bid = &ticker_data["b"].as_str()?;
-        let ask = &ticker_data["a"].as_str()?;
-
-        let bid_price;
-        let bid_amount;
-        let ask_price;
-        let ask_amount;
-        if bid.is_empty() {
-            bid_price = TICKER_PRICE_NOT_CHANGED;
-            bid_amount = TICKER_PRICE_NOT_CHANGED;
-        } else {
-            bid_price = bid.parse().unwrap();
-            bid_amount = ticker_data["B"].as_str()?.parse().unwrap();
-        }
-        if ask.is_empty() {
-            ask_price = TICKER_PRICE_NOT_CHANGED;
-            ask_amount = TICKER_PRICE_NOT_CHANGED;
-        } else {
-            ask_price = ask.parse().unwrap();
-            ask_amount = ticker_data["A"].as_str()?.parse().unwrap();
-        }
-        Some(DTO::PriceTicker(PriceTicker {
-            timestamp: ts,
-            instrument: Arc::clone(instrument_arc),
-            bid: bid_price,
-            bid_amount: bid_amount,
-            ask: ask_price,
-            ask_amount: ask_amount,
-        }))
-    }
-
-    fn handle_raw_price_ticker(&mut self, ts: u128, raw: String) {
-        if let Some(price_ticker) = self.parse_price_ticker(ts, &raw) {
-            self.queue.push(price_ticker).expect("Can't add price ticker to queue");
+        let data = json::parse(raw).ok()?;
+        if data["code"].as_usize() == Some(0) {
+            Some(Ok(data))
         } else {
-            panic!("Can't parse price ticker: {raw}");
+            Some(Err(data["msg"].to_string()))
         }
     }
 
-    fn handle(&mut self) {
-        loop {
-            let result = self.socket.as_mut().unwrap().read();
-            let ts = time();
-
-            match result {
-                Ok(msg) => match msg {
-                    Message::Text(raw) => {
-                        if raw == "{\"id\":0,\"code\":0,\"msg\":\"PONG\"}" {
-                            let price_ticker_lag = Duration::from_nanos((ts - self.latest_ticker_ts) as u64).as_millis();
-                            log::info!("Pong received. Latest price ticker: {price_ticker_lag}ms");
-                        } else {
-                            self.latest_ticker_ts = ts;
-                            self.handle_raw_price_ticker(ts, raw);
-                        }
-                    }
-                    msg => log::warn!("Unexpected msg: {msg}")
-                }
-                Err(err) => {
-                    log::error!("Can't read socket: {}", err);
-                    return;
-                }
-            };
-
-            if self.next_ping_ts < ts {
-                log::info!("Send ping");
-                self.send_json_with_sleep(
-                    object! { method: "PING" },
-                    Duration::from_nanos(0),
-                );
-                self.next_ping_ts = ts + Duration::from_secs(29).as_nanos();
-            }
-        }
+    fn ping_policy(&self) -> PingPolicy {
+        PingPolicy::Client { message: object! { method: "PING" }.dump(), interval: Duration::from_secs(29) }
     }
 
-    fn close_socket(&mut self) {
-        if let Some(mut socket) = self.socket.take() {
-            match socket.close(None) {
-                Ok(_) => {
-                    log::info!("Socket closed successfully");
-                }
-                Err(err) => {
-                    log::warn!("Error during the socket closing: {}", err);
-                }
-            }
-        }
+    fn is_pong(&self, raw: &str) -> bool {
+        raw == PONG
     }
 
-    fn next_id(&mut self) -> usize {
-        let id = self.request_id;
-        self.request_id += 1;
-        id
-    }
-
-    fn send_message_and_handle(&mut self, data: JsonValue, result_handler: fn(&PriceTickerStream, JsonValue)) {
-        self.send_json(data);
-        loop {
-            let msg = self.socket.as_mut().unwrap().read();
-            match msg {
-                Ok(Message::Text(a)) => {
-                    log::info!("Message during sub: {a}");
-                    if a.starts_with("{\"id") {
-                        let data = json::parse(&a).expect("Can't parse json");
-                        result_handler(self, data);
-                        return;
-                    }
-                },
-                Err(err) => {
-                    match err {
-                        // Silently drop the error: Processing error: IO error: Resource temporarily unavailable (os error 11)
-                        // That occurs when no messages are to be read
-                        Error::Io(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                            thread::sleep(Duration::from_millis(100));
-                        },
-                        _ => panic!("{}", err),
-                    }
-                }
+    fn parse(&mut self, ts: u128, raw: &str, instruments: &InstrumentsMap) -> Result<Option<PriceTicker>, String> {
+        let data = &json::parse(raw).map_err(|err| err.to_string())?;
+        let Some(symbol) = data["s"].as_str() else {
+            return Ok(None);
+        };
+        let instrument = instruments.get(&Exchange::Mexc, symbol).ok_or(format!("No instrument: {symbol}"))?;
+        let ticker_data = &data["d"];
 
-                t => log::warn!("Unexpected msg type: {t:?}")
-            }
-        }
+        let (bid, bid_amount) = Self::parse_side(ticker_data, "b", "B")?;
+        let (ask, ask_amount) = Self::parse_side(ticker_data, "a", "A")?;
+        Ok(Some(PriceTicker {
+            timestamp: ts,
+            instrument: Arc::clone(instrument),
+            bid,
+            bid_amount,
+            ask,
+            ask_amount,
+            // version, when sent
+            update_id: ticker_data["r"].as_str().and_then(|r| r.parse().ok()).unwrap_or(0),
+            exchange_ts: json_millis_to_nanos(&data["t"]),
+            connection: 0,
+        }))
     }
 }
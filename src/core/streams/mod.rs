@@ -1,3 +1,10 @@
 pub mod binance;
 pub mod bit2me;
+pub mod bybit;
+pub mod connector;
+pub mod kraken;
 pub mod mexc;
+pub mod okx;
+pub mod sequence;
+pub mod sharding;
+pub mod watchdog;
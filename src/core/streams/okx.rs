@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use std::time::Duration;
+use json::{object, JsonValue};
+use crate::core::{
+    dto::PriceTicker,
+    map::InstrumentsMap,
+    utils::json_millis_to_nanos,
+};
+use crate::core::decimal::Decimal;
+use crate::core::dto::Exchange;
+use crate::core::streams::connector::{BatchAcks, PingPolicy, StreamConnector, Venue};
+
+pub type PriceTickerStream = StreamConnector<OkxVenue>;
+
+const CHANNEL: &str = "bbo-tbt";
+
+#[derive(Default)]
+pub struct OkxVenue {
+    acks: BatchAcks,
+}
+
+impl OkxVenue {
+    fn request(op: &str, channels: &[String]) -> JsonValue {
+        let args: Vec<JsonValue> = channels.iter().map(|inst_id| object! { channel: CHANNEL, instId: inst_id.as_str() }).collect();
+        object! { op: op, args: args }
+    }
+
+    // level is [price, size, deprecated, orders count], the empty side of the snapshot is cleared
+    fn parse_level(levels: &JsonValue) -> Result<(Decimal, Decimal), String> {
+        let level = &levels[0];
+        if level.is_null() {
+            return Ok((Decimal::ZERO, Decimal::ZERO));
+        }
+        let parse = |value: &JsonValue| value.as_str().and_then(|v| v.parse::<Decimal>().ok()).ok_or(format!("Invalid level: {level}"));
+        Ok((parse(&level[0])?, parse(&level[1])?))
+    }
+}
+
+impl Venue for OkxVenue {
+    const EXCHANGE: Exchange = Exchange::Okx;
+    const URL: &'static str = "wss://ws.okx.com:8443/ws/v5/public";
+    const MAX_CHANNELS_PER_STREAM: usize = 100;
+    const MAX_CHANNELS_PER_REQUEST: usize = 20;
+    const REQUEST_DELAY: Duration = Duration::from_millis(100);
+
+    fn ticker_to_channel(ticker: &str) -> String {
+        ticker.to_uppercase().replace("/", "-")
+    }
+
+    fn channel_to_symbol(channel: &str) -> &str {
+        channel
+    }
+
+    fn subscribe_message(&self, channels: &[String], _request_id: usize) -> JsonValue {
+        Self::request("subscribe", channels)
+    }
+
+    fn unsubscribe_message(&self, channels: &[String], _request_id: usize) -> JsonValue {
+        Self::request("unsubscribe", channels)
+    }
+
+    // every argument is acknowledged separately, the request is done when all of them are
+    fn ack(&mut self, request: &JsonValue, raw: &str) -> Option<Result<JsonValue, String>> {
+        if !raw.starts_with("{\"event\"") {
+            return None;
+        }
+        let data = json::parse(raw).ok()?;
+        let inst_ids = request["args"].members().filter_map(|arg| arg["instId"].as_str().map(String::from));
+        self.acks.track(request, inst_ids);
+        if data["event"] == "error" {
+            // the error has no argument, the reason names the instId
+            let msg = data["msg"].as_str().unwrap_or_default();
+            if !self.acks.pending().any(|inst_id| msg.contains(inst_id.as_str())) {
+                log::warn!("OKX error of another request: {raw}");
+                return None;
+            }
+            self.acks.clear();
+            return Some(Err(format!("{}: {}", data["code"], data["msg"])));
+        }
+        if data["event"] != request["op"] || !self.acks.ack(data["arg"]["instId"].as_str()?) {
+            return None;
+        }
+        Some(Ok(data))
+    }
+
+    fn on_connect(&mut self) {
+        self.acks.clear();
+    }
+
+    fn ping_policy(&self) -> PingPolicy {
+        PingPolicy::Client { message: "ping".to_string(), interval: Duration::from_secs(25) }
+    }
+
+    fn is_pong(&self, raw: &str) -> bool {
+        raw == "pong"
+    }
+
+    fn parse(&mut self, ts: u128, raw: &str, instruments: &InstrumentsMap) -> Result<Option<PriceTicker>, String> {
+        let data = &json::parse(raw).map_err(|err| err.to_string())?;
+        if data["arg"]["channel"] != CHANNEL || data["data"].is_null() {
+            return Ok(None);
+        }
+        let symbol = data["arg"]["instId"].as_str().ok_or("No instId")?;
+        let instrument = instruments.get(&Exchange::Okx, symbol).ok_or(format!("No instrument: {symbol}"))?;
+        let book = &data["data"][0];
+        let (bid, bid_amount) = Self::parse_level(&book["bids"])?;
+        let (ask, ask_amount) = Self::parse_level(&book["asks"])?;
+        Ok(Some(PriceTicker {
+            timestamp: ts,
+            instrument: Arc::clone(instrument),
+            bid,
+            bid_amount,
+            ask,
+            ask_amount,
+            update_id: book["seqId"].as_u64().unwrap_or(0),
+            exchange_ts: json_millis_to_nanos(&book["ts"]),
+            connection: 0,
+        }))
+    }
+}
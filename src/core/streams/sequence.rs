@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+pub enum Sequence {
+    Next,
+    // the same or an older update
+    Stale,
+    // count of the missed updates
+    Gap(u64),
+}
+
+/// Latest update id per symbol of the stream
+#[derive(Default)]
+pub struct SequenceTracker {
+    latest: HashMap<String, u64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `sequential` - the ids of the symbol go one by one, otherwise they only grow
+    pub fn check(&mut self, symbol: &str, update_id: u64, sequential: bool) -> Sequence {
+        let Some(latest) = self.latest.get_mut(symbol) else {
+            self.latest.insert(symbol.to_string(), update_id);
+            return Sequence::Next;
+        };
+        if update_id <= *latest {
+            return Sequence::Stale;
+        }
+        let missed = update_id - *latest - 1;
+        *latest = update_id;
+        if sequential && missed > 0 {
+            Sequence::Gap(missed)
+        } else {
+            Sequence::Next
+        }
+    }
+
+    /// The resubscribed symbol starts over
+    pub fn remove(&mut self, symbol: &str) {
+        self.latest.remove(symbol);
+    }
+
+    pub fn clear(&mut self) {
+        self.latest.clear();
+    }
+}
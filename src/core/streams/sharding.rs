@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+use crate::core::dto::Exchange;
+
+pub const REBALANCE_INTERVAL: Duration = Duration::from_secs(60);
+// disconnects within the window mark the shard as flapping
+const FLAPPING_DISCONNECTS: usize = 3;
+const FLAPPING_WINDOW: Duration = Duration::from_secs(600);
+// channels are moved when the hottest shard exceeds the average load that much
+const IMBALANCE_RATIO: f64 = 1.5;
+const MAX_MOVES: usize = 10;
+
+#[derive(Debug, Default, Clone)]
+pub struct Shard {
+    pub channels: Vec<String>,
+    // messages per channel since the last rebalance
+    pub messages: HashMap<String, u64>,
+    pub disconnects: Vec<u128>,
+}
+
+impl Shard {
+    pub fn load(&self) -> u64 {
+        self.channels.iter().map(|c| self.rate(c)).sum()
+    }
+
+    fn rate(&self, channel: &str) -> u64 {
+        self.messages.get(channel).copied().unwrap_or(0)
+    }
+}
+
+/// Channels assignment of the venue sockets, one shard per socket
+pub struct Shards {
+    pub exchange: Exchange,
+    max_channels: usize,
+    shards: RwLock<Vec<Shard>>,
+    version: AtomicUsize,
+}
+
+impl Shards {
+    /// The least sockets within the limit, the channels are spread evenly
+    pub fn new(exchange: Exchange, channels: Vec<String>, max_channels: usize) -> Self {
+        let count = channels.len().div_ceil(max_channels).max(1);
+        let chunk_size = channels.len().div_ceil(count).max(1);
+        let mut shards: Vec<Shard> = channels.chunks(chunk_size)
+            .map(|chunk| Shard { channels: chunk.to_vec(), ..Default::default() })
+            .collect();
+        if shards.is_empty() {
+            shards.push(Shard::default());
+        }
+        Self { exchange, max_channels, shards: RwLock::new(shards), version: AtomicUsize::new(0) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.read().expect("Can't get the lock").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bumped on every channels move
+    pub fn version(&self) -> usize {
+        self.version.load(Ordering::Acquire)
+    }
+
+    pub fn get(&self, id: usize) -> Shard {
+        self.shards.read().expect("Can't get the lock")[id].clone()
+    }
+
+    /// Channels of the shard with the version they belong to
+    pub fn channels(&self, id: usize) -> (usize, Vec<String>) {
+        let shards = self.shards.read().expect("Can't get the lock");
+        (self.version(), shards[id].channels.clone())
+    }
+
+    pub fn record_messages(&self, id: usize, messages: HashMap<String, u64>) {
+        let mut shards = self.shards.write().expect("Can't get the lock");
+        for (channel, count) in messages {
+            *shards[id].messages.entry(channel).or_insert(0) += count;
+        }
+    }
+
+    pub fn record_disconnect(&self, id: usize, ts: u128) {
+        self.shards.write().expect("Can't get the lock")[id].disconnects.push(ts);
+    }
+
+    /// Channels of the removed instruments, dropped by the socket itself
+    pub fn remove(&self, id: usize, channels: &[String]) {
+        let mut shards = self.shards.write().expect("Can't get the lock");
+        shards[id].channels.retain(|c| !channels.contains(c));
+    }
+
+    /// Channels of the added instruments, spread over the shards with the fewest channels.
+    /// Returns the count of the appended shards, their sockets are to be spawned
+    pub fn add(&self, channels: Vec<String>) -> usize {
+        let mut shards = self.shards.write().expect("Can't get the lock");
+        let count = shards.len();
+        for channel in channels {
+            if shards.iter().any(|s| s.channels.contains(&channel)) {
+                continue;
+            }
+            // no rates are observed for the new channels yet
+            let target = (0..shards.len())
+                .filter(|id| shards[*id].channels.len() < self.max_channels)
+                .min_by_key(|id| shards[*id].channels.len())
+                .unwrap_or_else(|| {
+                    shards.push(Shard::default());
+                    shards.len() - 1
+                });
+            shards[target].channels.push(channel);
+        }
+        self.version.fetch_add(1, Ordering::AcqRel);
+        shards.len() - count
+    }
+
+    /// Move the channels off the flapping shards and from the hottest shards to the coolest ones.
+    /// Returns the count of the appended shards, their sockets are to be spawned
+    pub fn rebalance(&self, ts: u128) -> usize {
+        let mut shards = self.shards.write().expect("Can't get the lock");
+        let count = shards.len();
+        let mut moved = false;
+
+        let mut flapping = HashSet::new();
+        for id in 0..count {
+            let shard = &mut shards[id];
+            shard.disconnects.retain(|d| d + FLAPPING_WINDOW.as_nanos() > ts);
+            if shard.disconnects.len() >= FLAPPING_DISCONNECTS && !shard.channels.is_empty() {
+                log::warn!("{:?} shard {id} disconnected {} times, move {} channels off", self.exchange, shard.disconnects.len(), shard.channels.len());
+                shard.disconnects.clear();
+                flapping.insert(id);
+            }
+        }
+        for id in flapping.iter().copied() {
+            let channels = std::mem::take(&mut shards[id].channels);
+            for channel in channels {
+                let rate = shards[id].messages.remove(&channel).unwrap_or(0);
+                let target = self.coolest(&shards, &flapping).unwrap_or_else(|| {
+                    shards.push(Shard::default());
+                    shards.len() - 1
+                });
+                shards[target].messages.insert(channel.clone(), rate);
+                shards[target].channels.push(channel);
+            }
+            moved = true;
+        }
+
+        moved |= self.balance(&mut shards, &flapping);
+        for shard in shards.iter_mut() {
+            shard.messages.clear();
+        }
+        if moved {
+            self.version.fetch_add(1, Ordering::AcqRel);
+        }
+        shards.len() - count
+    }
+
+    // the least loaded shard with the free capacity
+    fn coolest(&self, shards: &[Shard], excluded: &HashSet<usize>) -> Option<usize> {
+        (0..shards.len())
+            .filter(|id| !excluded.contains(id) && shards[*id].channels.len() < self.max_channels)
+            .min_by_key(|id| shards[*id].load())
+    }
+
+    fn balance(&self, shards: &mut [Shard], excluded: &HashSet<usize>) -> bool {
+        let mut moved = false;
+        for _ in 0..MAX_MOVES {
+            let loads: Vec<u64> = shards.iter().map(Shard::load).collect();
+            let average = loads.iter().sum::<u64>() as f64 / shards.len() as f64;
+            let hot = (0..shards.len()).max_by_key(|id| loads[*id]).unwrap();
+            if (loads[hot] as f64) <= average * IMBALANCE_RATIO || shards[hot].channels.len() < 2 {
+                break;
+            }
+            let Some(cold) = self.coolest(shards, excluded).filter(|cold| *cold != hot) else {
+                break;
+            };
+            // the busiest channel which still leaves the cold shard cooler than the hot one was
+            let gap = loads[hot] - loads[cold];
+            let Some(channel) = shards[hot].channels.iter()
+                .filter(|c| shards[hot].rate(c) > 0 && shards[hot].rate(c) < gap)
+                .max_by_key(|c| shards[hot].rate(c))
+                .cloned() else {
+                break;
+            };
+            log::info!("{:?} move {channel} from shard {hot} to {cold}", self.exchange);
+            let rate = shards[hot].messages.remove(&channel).unwrap_or(0);
+            shards[hot].channels.retain(|c| *c != channel);
+            shards[cold].messages.insert(channel.clone(), rate);
+            shards[cold].channels.push(channel);
+            moved = true;
+        }
+        moved
+    }
+}
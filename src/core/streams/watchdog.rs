@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, PartialEq)]
+pub enum Silence {
+    // no updates on the whole socket for the duration
+    Socket(Duration),
+    // symbols without updates over the instrument threshold
+    Instruments(Vec<String>),
+}
+
+/// Last update time per socket and symbol, flags the streams which are open but silent
+pub struct Watchdog {
+    socket_timeout: Duration,
+    // disabled for None, some instruments are quiet for long
+    instrument_timeout: Option<Duration>,
+    last_socket_ts: u128,
+    last_symbol_ts: HashMap<String, u128>,
+    next_check_ts: u128,
+}
+
+impl Watchdog {
+    pub fn new(socket_timeout: Duration, instrument_timeout: Option<Duration>) -> Self {
+        Self {
+            socket_timeout,
+            instrument_timeout,
+            last_socket_ts: 0,
+            last_symbol_ts: HashMap::new(),
+            next_check_ts: 0,
+        }
+    }
+
+    /// Watch the subscribed symbols from the ts, the previous ones are forgotten
+    pub fn reset<'a>(&mut self, ts: u128, symbols: impl Iterator<Item = &'a str>) {
+        self.last_socket_ts = ts;
+        self.last_symbol_ts = symbols.map(|symbol| (symbol.to_string(), ts)).collect();
+        self.next_check_ts = 0;
+    }
+
+    /// Start or restart watching the symbol
+    pub fn watch(&mut self, ts: u128, symbol: &str) {
+        self.last_symbol_ts.insert(symbol.to_string(), ts);
+    }
+
+    pub fn unwatch(&mut self, symbol: &str) {
+        self.last_symbol_ts.remove(symbol);
+    }
+
+    pub fn on_update(&mut self, ts: u128, symbol: &str) {
+        self.last_socket_ts = ts;
+        if let Some(last_ts) = self.last_symbol_ts.get_mut(symbol) {
+            *last_ts = ts;
+        }
+    }
+
+    /// The silence over the thresholds, the silent symbols are reported once per threshold
+    pub fn check(&mut self, ts: u128) -> Option<Silence> {
+        if self.next_check_ts > ts || self.last_symbol_ts.is_empty() {
+            return None;
+        }
+        self.next_check_ts = ts + CHECK_INTERVAL.as_nanos();
+
+        let silence = ts.saturating_sub(self.last_socket_ts);
+        if silence > self.socket_timeout.as_nanos() {
+            return Some(Silence::Socket(Duration::from_nanos(silence as u64)));
+        }
+
+        let timeout = self.instrument_timeout?.as_nanos();
+        let mut symbols: Vec<String> = Vec::new();
+        for (symbol, last_ts) in self.last_symbol_ts.iter_mut() {
+            if ts.saturating_sub(*last_ts) > timeout {
+                symbols.push(symbol.clone());
+                *last_ts = ts;
+            }
+        }
+        if symbols.is_empty() {
+            return None;
+        }
+        symbols.sort();
+        Some(Silence::Instruments(symbols))
+    }
+}
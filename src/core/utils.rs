@@ -1,7 +1,8 @@
-use std::{fs, thread};
+use std::fs;
 use json::JsonValue;
 use std::time::SystemTime;
-use std::io::{Write};
+use crate::core::decimal::Decimal;
+use crate::core::logging::{self, LogFormat};
 
 pub fn time() -> u128 {
     match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
@@ -10,30 +11,37 @@ pub fn time() -> u128 {
     }
 }
 
-pub fn parse_f64_field(data: &JsonValue, field: &str) -> f64 {
+pub fn parse_decimal_field(data: &JsonValue, field: &str) -> Decimal {
     data[field]
         .as_str()
-        .expect(&format!("Missing '{field}' field or not a string"))
+        .unwrap_or_else(|| panic!("Missing '{field}' field or not a string"))
         .parse()
-        .expect(&format!("Field '{field}' can't parse to the f64: {data:?}"))
+        .unwrap_or_else(|_| panic!("Field '{field}' can't parse to the decimal: {data:?}"))
+}
+
+// accepts both json numbers and numeric strings
+pub fn json_to_decimal(value: &JsonValue) -> Option<Decimal> {
+    if value.is_number() {
+        value.dump().parse().ok()
+    } else {
+        value.as_str()?.parse().ok()
+    }
+}
+
+/// Exchange time in ms, a number or a string, to ns. 0 if absent
+pub fn json_millis_to_nanos(value: &JsonValue) -> u128 {
+    let millis = value.as_u64().or_else(|| value.as_str()?.parse().ok()).unwrap_or(0);
+    millis as u128 * 1_000_000
+}
+
+pub fn decimal_to_json(value: Decimal) -> JsonValue {
+    let value = value.normalize();
+    let mantissa = u64::try_from(value.mantissa().unsigned_abs()).expect("Decimal mantissa doesn't fit json number");
+    json::number::Number::from_parts(value.mantissa() >= 0, mantissa, -(value.scale() as i16)).into()
 }
 
 pub fn init_logger() {
-    env_logger::builder()
-        .format(|buf, record| {
-            let ts = buf.timestamp_millis();
-            let style = buf.default_level_style(record.level());
-            let level = record.level();
-            let current_thread = thread::current();
-            let thread_name = current_thread.name().unwrap_or("");
-            let target = record.target();
-            let args = record.args();
-            writeln!(
-                buf,
-                "[{ts} {style}{level}{style:#} {thread_name}] {target}] {args}",
-            )
-        })
-        .init();
+    logging::init(LogFormat::from_env());
 }
 
 pub fn read_tickers(path: &str) -> Vec<Vec<String>> {
@@ -56,11 +64,3 @@ pub enum RoundingMode {
     Up,
     Down,
 }
-
-pub fn round(value: f64, precision: usize, rounding_mode: RoundingMode) -> f64 {
-    let factor = 10_f64.powi(precision as i32);
-    match rounding_mode {
-        RoundingMode::Up => (value * factor).ceil() / factor,
-        RoundingMode::Down => (value * factor).floor() / factor,
-    }
-}
@@ -9,14 +9,7 @@ mod tests {
     //     println!("{id}");
     // }
 
-    use std::collections::HashMap;
-    use std::{panic, process};
-    use std::sync::Arc;
-    use crossbeam_queue::ArrayQueue;
-    use fefix::FieldType;
     use uuid::Uuid;
-    use crate::core::api::OrderListener;
-    use crate::core::dto::{Exchange, Instrument, Order, OrderSide, DTO, TICKER_PRICE_NOT_CHANGED};
     // use std::{fs, io, thread};
     // use std::fs::File;
     // use std::io::{stdout, Cursor, Read, Write};
@@ -32,10 +25,6 @@ mod tests {
     // use fefix::{Dictionary, FieldMap, FieldType, GetConfig, SetField, StreamingDecoder};
     // use fefix::tagvalue::{Decoder, Encoder};
     // use rustls::RootCertStore;
-    use crate::core::map::InstrumentsMap;
-    use crate::core::oms::{BinanceFixConnection, OMS};
-    use crate::core::streams::bit2me::PriceTickerStream;
-    use crate::core::utils::{init_logger, read_tickers, round, RoundingMode};
 
     // #[test]
     // fn test_round() {
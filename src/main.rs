@@ -1,27 +1,50 @@
-mod core;
 mod draft;
 
-use std::sync::Arc;
-use std::{env, panic, process, thread};
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock};
+use std::{env, panic, process};
 use std::time::Duration;
-use crossbeam_queue::ArrayQueue;
-use core::api::PriceTickerListener;
-use core::handlers::PriceTickerFilter;
-use core::map::InstrumentsMap;
-use crate::core::api::MonitoringMessageListener;
-use crate::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order, OrderSide, DTO};
-use crate::core::oms::OMS;
-use crate::core::strategies::ArbStrategy;
-use crate::core::{streams};
-use crate::core::order_sizing::SizingConfig;
-use crate::core::price_ticker_collector::PriceTickerCollector;
-use crate::core::utils::{init_logger, read_tickers, time};
+use untitled::core::api::{InstrumentsListener, OrderListener, PriceTickerListener};
+use untitled::core::handlers::PriceTickerFilter;
+use untitled::core::map::InstrumentsRegistry;
+use untitled::core::alerting::{AlertPolicy, AlertSink, Alerter, CommandSink, FileSink, WebhookSink};
+use untitled::core::api::MonitoringMessageListener;
+use untitled::core::bus::{Channel, EventBus, OverflowPolicy, Signal};
+use untitled::core::control::{ControlCommand, ControlReply, StrategyCommand};
+use untitled::core::decimal::Decimal;
+use untitled::core::fees::{FeeCurrency, FeeModel, FeeSchedule, FeeTier};
+use untitled::core::latency::{LatencyMonitor, Stage, LATENCY};
+use untitled::core::metrics::METRICS;
+use untitled::core::dto::{Exchange, InstrumentsUpdate, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order, OrderStatus, DTO};
+use untitled::core::oms::OMS;
+use untitled::core::strategies::ArbStrategy;
+use untitled::core::streams::sharding::Shards;
+use untitled::core::{control, instruments_fetcher, streams};
+use untitled::core::order_sizing::SizingConfig;
+use untitled::core::order_tracker::OrderTracker;
+use untitled::core::risk::{RiskConfig, RiskGate};
+use untitled::core::utils::{init_logger, read_tickers, time};
+
+const METRICS_ADDRESS: &str = "127.0.0.1:9184";
+const CONTROL_ADDRESS: &str = "127.0.0.1:9185";
+const PNL_METRICS_INTERVAL: Duration = Duration::from_secs(10);
+// the periodic tasks run at least this often
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+const ALERT_DEDUP_WINDOW: Duration = Duration::from_secs(300);
+const ALERT_RATE_WINDOW: Duration = Duration::from_secs(600);
+const ALERT_RATE_LIMIT: usize = 20;
 
 
 fn main() {
     init_logger();
 
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("fetch-instruments") {
+        instruments_fetcher::run(&args[2..]);
+        return;
+    }
+
     let orig_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
         // invoke the default handler and exit the process
@@ -30,71 +53,100 @@ fn main() {
     }));
 
 
-    let queue = Arc::new(ArrayQueue::new(2_000_000));
-    let orders_queue = Arc::new(ArrayQueue::new(100_000));
+    let bus = Arc::new(EventBus::new(100_000));
+    // the OMS drains the orders, the strategies are throttled by the main loop itself
+    let orders_queue = Arc::new(Channel::new("orders", 100_000, OverflowPolicy::Block, Arc::new(Signal::default())));
+    let strategy_orders_queue = Arc::new(Channel::new("strategy_orders", 100_000, OverflowPolicy::DropNewest, bus.signal()));
+    let control_channel = Arc::new(Channel::new("control", 100, OverflowPolicy::DropNewest, bus.signal()));
 
-    let instruments_map = Arc::new(InstrumentsMap::from_json("./data/spot_insts.json"));
-    let mut sockets;
-    sockets = streams::binance::PriceTickerStream::listen_from_tickers_group(
-        Arc::clone(&queue),
-        read_tickers("./data/tickers.json"),
-        Arc::clone(&instruments_map),
-        128
-    );
+    METRICS.serve(&env::var("METRICS_ADDRESS").unwrap_or(METRICS_ADDRESS.to_string()));
 
-    log::info!("Binance sockets: {sockets}");
-
-    sockets = streams::bit2me::PriceTickerStream::listen_from_tickers_split(
-        Arc::clone(&queue),
-        HashSet::<String>::from_iter(
-            instruments_map.map.get(&Exchange::Bit2me).unwrap().values().map(|x| x.symbol.clone())
-        ).iter().map(|x| x.clone()).collect(),
-        Arc::clone(&instruments_map),
-        300,
-        1
+    let instruments = Arc::new(InstrumentsRegistry::from_json("./data/spot_insts.json"));
+    let instruments_map = instruments.load();
+    // every ticker on two independent sockets, the filter takes the first copy
+    let connections = if args.iter().any(|arg| arg == "--redundant") { 2 } else { 1 };
+    let mut exchange_shards = HashMap::new();
+    let shards = streams::binance::PriceTickerStream::listen_from_tickers_redundant(
+        Arc::clone(&bus),
+        read_tickers("./data/tickers.json").concat(),
+        Arc::clone(&instruments),
+        connections,
     );
+    log::info!("Binance sockets: {}", shards.iter().map(|s| s.len()).sum::<usize>());
+    exchange_shards.insert(Exchange::Binance, shards);
 
-    log::info!("Bit2me sockets: {sockets}");
-
-    sockets = streams::mexc::PriceTickerStream::listen_from_tickers_split(
-        Arc::clone(&queue),
-            HashSet::<String>::from_iter(
-                instruments_map.map.get(&Exchange::Mexc).unwrap().values().map(|x| x.symbol.clone())
-            ).iter().map(|x| x.clone()).collect(),
-        Arc::clone(&instruments_map),
-        30,
-        15
-    );
+    for (exchange, listen) in [
+        (Exchange::Bit2me, streams::bit2me::PriceTickerStream::listen_from_tickers_redundant as fn(_, _, _, _) -> _),
+        (Exchange::Mexc, streams::mexc::PriceTickerStream::listen_from_tickers_redundant),
+        (Exchange::Okx, streams::okx::PriceTickerStream::listen_from_tickers_redundant),
+        (Exchange::Bybit, streams::bybit::PriceTickerStream::listen_from_tickers_redundant),
+        (Exchange::Kraken, streams::kraken::PriceTickerStream::listen_from_tickers_redundant),
+    ] {
+        let symbols = instruments_map.symbols(&exchange);
+        if !symbols.is_empty() {
+            let shards = listen(Arc::clone(&bus), symbols, Arc::clone(&instruments), connections);
+            log::info!("{exchange:?} sockets: {}", shards.iter().map(|s| s.len()).sum::<usize>());
+            exchange_shards.insert(exchange, shards);
+        }
+    }
 
-    log::info!("Mexc sockets: {sockets}");
+    InstrumentsRegistry::watch(&instruments, &bus, Duration::from_secs(10));
+    LatencyMonitor::spawn_reporter(Duration::from_secs(60));
 
     let empty_map = Default::default();
 
-    let sizing_config = SizingConfig::new("USDT".to_string(), 20., 30.);
+    let mut fee_model = FeeModel::new();
+    fee_model.insert(Exchange::Binance, FeeSchedule::new(
+        vec![
+            FeeTier::new("VIP0", Decimal::ZERO, 0.001, 0.001),
+            FeeTier::new("VIP1", Decimal::new(1_000_000, 0), 0.0009, 0.001),
+            FeeTier::new("VIP2", Decimal::new(5_000_000, 0), 0.0008, 0.001),
+            FeeTier::new("VIP3", Decimal::new(20_000_000, 0), 0.00042, 0.0006),
+        ],
+        Decimal::ZERO,
+        FeeCurrency::Token { currency: "BNB".to_string(), discount: 0.25 },
+    ));
+    // mexc rates come with the instruments
+    fee_model.insert(Exchange::Mexc, FeeSchedule::new(
+        vec![], Decimal::ZERO, FeeCurrency::Token { currency: "MX".to_string(), discount: 0.2 },
+    ));
+    let fee_model = Arc::new(fee_model);
+
+    let sizing_config = SizingConfig::new("USDT".to_string(), Decimal::new(20, 0), Decimal::new(30, 0));
+    let order_tracker = Arc::new(RwLock::new(OrderTracker::new()));
+    let mut risk_gate = RiskGate::new(
+        RiskConfig::new("USDT".to_string(), Decimal::new(50, 0), Decimal::new(5, 0), 10, 0.05),
+        Arc::clone(&instruments),
+    );
 
     let mut price_ticker_filter = PriceTickerFilter::new(
         vec![
             // Box::new(PriceTickerCollector::new("/Users/alex/RustroverProjects/untitled/tickers.csv"))
-            Box::new(ArbStrategy::new(Arc::clone(&orders_queue), Exchange::Binance, sizing_config.clone(), false)),
-            Box::new(ArbStrategy::new(Arc::clone(&orders_queue), Exchange::Bit2me, sizing_config.clone(), true)),
-            Box::new(ArbStrategy::new(Arc::clone(&orders_queue), Exchange::Mexc, sizing_config.clone(), true))
+            Box::new(ArbStrategy::new(Arc::clone(&strategy_orders_queue), Arc::clone(&order_tracker), Exchange::Binance, sizing_config.clone(), Arc::clone(&fee_model), false)),
+            Box::new(ArbStrategy::new(Arc::clone(&strategy_orders_queue), Arc::clone(&order_tracker), Exchange::Bit2me, sizing_config.clone(), Arc::clone(&fee_model), true)),
+            Box::new(ArbStrategy::new(Arc::clone(&strategy_orders_queue), Arc::clone(&order_tracker), Exchange::Mexc, sizing_config.clone(), Arc::clone(&fee_model), true)),
+            Box::new(ArbStrategy::new(Arc::clone(&strategy_orders_queue), Arc::clone(&order_tracker), Exchange::Okx, sizing_config.clone(), Arc::clone(&fee_model), true)),
+            Box::new(ArbStrategy::new(Arc::clone(&strategy_orders_queue), Arc::clone(&order_tracker), Exchange::Bybit, sizing_config.clone(), Arc::clone(&fee_model), true)),
+            Box::new(ArbStrategy::new(Arc::clone(&strategy_orders_queue), Arc::clone(&order_tracker), Exchange::Kraken, sizing_config.clone(), Arc::clone(&fee_model), true))
         ],
+        Arc::clone(&order_tracker),
     );
 
     // // oms isn't up yet
-    queue.push(
+    bus.publish(
         DTO::MonitoringMessage(MonitoringMessage::new(
             time(),
             MonitoringStatus::Error,
             MonitoringEntity::OrderManagementSystem,
+            Exchange::Binance,
             1,
         ))
-    ).expect("Can't add message to queue");
+    );
 
     OMS::start(
         Arc::clone(&orders_queue),
-        Arc::clone(&queue),
-        Arc::clone(&instruments_map),
+        Arc::clone(&bus),
+        Arc::clone(&instruments),
         ".creds/binance.pem".to_string(),
         env::var("API_KEY").unwrap()
     );
@@ -105,18 +157,53 @@ fn main() {
     // order.side = OrderSide::Buy;
     // order.client_order_id = "dummy".to_string();
     //
-    // orders_queue.push(DTO::Order(order)).expect("Can't add order to queue");
+    // orders_queue.push(order);
 
+    control::serve(&env::var("CONTROL_ADDRESS").unwrap_or(CONTROL_ADDRESS.to_string()), Arc::clone(&control_channel));
+    let mut alerter = Alerter::new(AlertPolicy::new(ALERT_DEDUP_WINDOW, ALERT_RATE_WINDOW, ALERT_RATE_LIMIT), alert_sinks());
+
+    let mut next_pnl_metrics_ts = 0;
     loop {
-        match queue.pop() {
+        alerter.check(time());
+        if next_pnl_metrics_ts < time() {
+            next_pnl_metrics_ts = time() + PNL_METRICS_INTERVAL.as_nanos();
+            update_pnl_metrics(&risk_gate, &price_ticker_filter);
+        }
+
+        while let Some(request) = control_channel.pop() {
+            let result = handle_control(&request.command, &mut price_ticker_filter, &risk_gate, &order_tracker, &orders_queue);
+            request.reply(result);
+        }
+
+        // pre-trade checks of the orders created by strategies
+        while let Some(mut order) = strategy_orders_queue.pop() {
+            match risk_gate.check(&order, price_ticker_filter.tickers_map()) {
+                Ok(_) => {
+                    orders_queue.push(order);
+                }
+                Err(reason) => {
+                    log::warn!("Order rejected by risk gate: {reason}");
+                    order.timestamp = time();
+                    order.status = OrderStatus::Error;
+                    order.error = reason;
+                    bus.publish(DTO::Order(order));
+                }
+            }
+        }
+
+        match bus.next() {
             Some(dto) => {
                 match dto {
                     DTO::PriceTicker(price_ticker) => {
+                        LATENCY.record(&price_ticker.instrument.exchange, Stage::QueuePop, price_ticker.timestamp, time());
                         price_ticker_filter.on_price_ticker(&price_ticker, &empty_map);
                     },
                     DTO::Order(order) => {
-                        for l in &mut price_ticker_filter.listeners {
-                            l.on_order(&order);
+                        alerter.on_order(&order);
+                        price_ticker_filter.on_order(&order);
+                        // the tracked state has the cumulative fill and fees
+                        if let Some(tracked) = order_tracker.read().expect("Can't get the lock").get(&order.client_order_id) {
+                            risk_gate.on_order(tracked);
                         }
                     },
                     DTO::Balance(balance) => {
@@ -125,17 +212,144 @@ fn main() {
                         }
                     },
                     DTO::MonitoringMessage(msg) => {
+                        alerter.on_monitoring_message(&msg);
+                        // strategies get the health events
                         price_ticker_filter.on_monitoring_message(&msg);
-                        for l in &mut price_ticker_filter.listeners {
-                            l.on_monitoring_message(&msg);
-                        }
+                    }
+                    DTO::InstrumentsUpdate(update) => {
+                        price_ticker_filter.on_instruments_update(&update);
+                        // removed instruments are unsubscribed by the streams themselves
+                        listen_added_instruments(&update, &mut exchange_shards, &bus, &instruments, connections);
                     }
                 }
             }
             None => {
-                // log::info!("Empty queue");
-                thread::sleep(Duration::from_millis(1));
-                // log::info!("Queue size {}", queue.len());
+                // woken up by the bus and control messages
+                bus.wait(IDLE_WAIT);
+            }
+        }
+    }
+}
+
+fn handle_control(
+    command: &ControlCommand,
+    price_ticker_filter: &mut PriceTickerFilter,
+    risk_gate: &RiskGate,
+    order_tracker: &Arc<RwLock<OrderTracker>>,
+    orders_queue: &Arc<Channel<Order>>,
+) -> ControlReply {
+    match command {
+        ControlCommand::Strategies => {
+            let states = price_ticker_filter.listeners.iter_mut()
+                .map(|l| l.on_control(&StrategyCommand::State))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(states.into())
+        }
+        ControlCommand::Strategy(id, command) => {
+            let strategy = price_ticker_filter.listeners.iter_mut()
+                .find(|l| l.strategy_id() == id)
+                .ok_or(format!("Unknown strategy {id}"))?;
+            strategy.on_control(command)
+        }
+        ControlCommand::KillSwitch(active) => {
+            risk_gate.kill_switch().store(*active, Ordering::Relaxed);
+            let canceled = if *active { cancel_open_orders(price_ticker_filter, order_tracker, orders_queue) } else { 0 };
+            log::warn!("Kill switch active: {active}, orders canceled: {canceled}");
+            Ok(json::object! { active: *active, canceled: canceled })
+        }
+        ControlCommand::Tickers => Ok(control::tickers_to_json(price_ticker_filter.tickers_map())),
+        ControlCommand::Health => Ok(price_ticker_filter.health.to_json()),
+    }
+}
+
+/// Send the cancels of the orders placed on the exchanges
+fn cancel_open_orders(price_ticker_filter: &mut PriceTickerFilter, order_tracker: &Arc<RwLock<OrderTracker>>, orders_queue: &Arc<Channel<Order>>) -> usize {
+    let orders: Vec<Order> = order_tracker.read().expect("Can't get the lock").open_orders()
+        .filter(|o| matches!(o.status, OrderStatus::New | OrderStatus::Open | OrderStatus::PartiallyFilled))
+        .cloned()
+        .collect();
+    for mut order in orders.iter().cloned() {
+        order.status = OrderStatus::Canceling;
+        order.timestamp = time();
+        // tracked as canceling till the exchange report
+        price_ticker_filter.on_order(&order);
+        orders_queue.push(order);
+    }
+    orders.len()
+}
+
+/// Sinks configured by the ALERT_WEBHOOK_URL, ALERT_COMMAND and ALERT_FILE env variables
+fn alert_sinks() -> Vec<Box<dyn AlertSink>> {
+    let mut sinks: Vec<Box<dyn AlertSink>> = Vec::new();
+    // the misconfigured sinks are skipped, the alerting keeps the others
+    if let Ok(url) = env::var("ALERT_WEBHOOK_URL") {
+        match WebhookSink::new(&url) {
+            Ok(sink) => sinks.push(Box::new(sink)),
+            Err(err) => log::error!("Webhook alert sink skipped: {err}"),
+        }
+    }
+    if let Ok(command) = env::var("ALERT_COMMAND") {
+        let mut parts = command.split_whitespace().map(String::from);
+        match parts.next() {
+            Some(program) => sinks.push(Box::new(CommandSink::new(&program, parts.collect()))),
+            None => log::error!("Command alert sink skipped: empty ALERT_COMMAND"),
+        }
+    }
+    if let Ok(path) = env::var("ALERT_FILE") {
+        sinks.push(Box::new(FileSink::new(&path)));
+    }
+    if sinks.is_empty() {
+        log::warn!("No alert sinks configured, the alerts are only logged");
+    }
+    sinks
+}
+
+/// Daily PnL of the strategies with fills
+fn update_pnl_metrics(risk_gate: &RiskGate, price_ticker_filter: &PriceTickerFilter) {
+    for strategy_id in risk_gate.strategies() {
+        let pnl = risk_gate.daily_pnl(strategy_id, time(), price_ticker_filter.tickers_map());
+        METRICS.gauge(
+            "arbot_daily_pnl",
+            "Mark-to-market PnL of the strategy since the start of the UTC day",
+            &[("strategy", strategy_id), ("currency", &risk_gate.config.currency)],
+        ).set(pnl.to_f64());
+    }
+}
+
+/// The added symbols join the running shards, the exchanges without any get the new streams
+fn listen_added_instruments(
+    update: &InstrumentsUpdate,
+    exchange_shards: &mut HashMap<Exchange, Vec<Arc<Shards>>>,
+    bus: &Arc<EventBus>,
+    instruments: &Arc<InstrumentsRegistry>,
+    connections: usize,
+) {
+    let added = |exchange: Exchange| -> Vec<String> {
+        update.added.iter().filter(|i| i.exchange == exchange).map(|i| i.symbol.clone()).collect()
+    };
+
+    for (exchange, listen, add) in [
+        (
+            Exchange::Binance,
+            streams::binance::PriceTickerStream::listen_from_tickers_redundant as fn(_, _, _, _) -> _,
+            streams::binance::PriceTickerStream::add_tickers as fn(&[Arc<Shards>], &[String]),
+        ),
+        (Exchange::Bit2me, streams::bit2me::PriceTickerStream::listen_from_tickers_redundant, streams::bit2me::PriceTickerStream::add_tickers),
+        (Exchange::Mexc, streams::mexc::PriceTickerStream::listen_from_tickers_redundant, streams::mexc::PriceTickerStream::add_tickers),
+        (Exchange::Okx, streams::okx::PriceTickerStream::listen_from_tickers_redundant, streams::okx::PriceTickerStream::add_tickers),
+        (Exchange::Bybit, streams::bybit::PriceTickerStream::listen_from_tickers_redundant, streams::bybit::PriceTickerStream::add_tickers),
+        (Exchange::Kraken, streams::kraken::PriceTickerStream::listen_from_tickers_redundant, streams::kraken::PriceTickerStream::add_tickers),
+    ] {
+        let symbols = added(exchange.clone());
+        if symbols.is_empty() {
+            continue;
+        }
+        match exchange_shards.get(&exchange) {
+            Some(shards) => add(shards, &symbols),
+            None => {
+                let shards = listen(Arc::clone(bus), symbols, Arc::clone(instruments), connections);
+                log::info!("{exchange:?} sockets for the new instruments: {}", shards.iter().map(|s| s.len()).sum::<usize>());
+                exchange_shards.insert(exchange, shards);
             }
         }
     }
@@ -0,0 +1,208 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use untitled::core::alerting::{Alert, AlertPolicy, AlertSink, Alerter, CommandSink, CONNECT_GRACE, FileSink, Severity, WebhookSink};
+    use untitled::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order, OrderStatus};
+    use untitled::core::risk::DAILY_LOSS_LIMIT_REASON;
+
+    const SEC: u128 = 1_000_000_000;
+
+    fn create_alert(ts: u128, key: &str) -> Alert {
+        Alert::new(ts, Severity::Warning, key.to_string(), format!("{key} failed"))
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("arbot_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_policy_dedup() {
+        let mut policy = AlertPolicy::new(Duration::from_secs(60), Duration::from_secs(60), 100);
+
+        assert!(policy.admit(create_alert(0, "oms:binance")).is_some());
+        assert_eq!(None, policy.admit(create_alert(30 * SEC, "oms:binance")));
+        // other keys aren't deduplicated
+        assert!(policy.admit(create_alert(30 * SEC, "oms:okx")).is_some());
+        let alert = policy.admit(create_alert(60 * SEC, "oms:binance")).unwrap();
+        assert_eq!("oms:binance failed", alert.message);
+        assert_eq!(None, policy.admit(create_alert(61 * SEC, "oms:okx")));
+    }
+
+    #[test]
+    fn test_policy_rate_limit() {
+        let mut policy = AlertPolicy::new(Duration::ZERO, Duration::from_secs(60), 2);
+
+        assert!(policy.admit(create_alert(0, "a")).is_some());
+        assert!(policy.admit(create_alert(SEC, "b")).is_some());
+        assert_eq!(None, policy.admit(create_alert(2 * SEC, "c")));
+        assert_eq!(None, policy.admit(create_alert(3 * SEC, "d")));
+        // the first alert is out of the window
+        let alert = policy.admit(create_alert(60 * SEC, "e")).unwrap();
+        assert_eq!("e failed (2 alerts suppressed)", alert.message);
+        assert_eq!(None, policy.admit(create_alert(60 * SEC, "f")));
+    }
+
+    #[test]
+    fn test_message_alerts() {
+        let mut alerter = Alerter::new(AlertPolicy::new(Duration::ZERO, Duration::from_secs(60), 100), vec![]);
+        let message = |status, entity| MonitoringMessage::new(SEC, status, entity, Exchange::Binance, 0);
+
+        // not connected yet
+        assert_eq!(None, alerter.message_alert(&message(MonitoringStatus::Error, MonitoringEntity::OrderManagementSystem)));
+        assert_eq!(None, alerter.message_alert(&message(MonitoringStatus::Ok, MonitoringEntity::OrderManagementSystem)));
+        let alert = alerter.message_alert(&message(MonitoringStatus::Error, MonitoringEntity::OrderManagementSystem)).unwrap();
+        assert_eq!((Severity::Critical, "oms:binance", "Binance OMS disconnected"), (alert.severity, alert.key.as_str(), alert.message.as_str()));
+        // the repeated error is the same disconnect
+        assert_eq!(None, alerter.message_alert(&message(MonitoringStatus::Error, MonitoringEntity::OrderManagementSystem)));
+
+        let alert = alerter.message_alert(&message(MonitoringStatus::Error, MonitoringEntity::PriceTicker)).unwrap();
+        assert_eq!((Severity::Warning, "price_ticker:binance"), (alert.severity, alert.key.as_str()));
+        let alert = alerter.message_alert(&message(MonitoringStatus::Error, MonitoringEntity::OrderRateLimit)).unwrap();
+        assert_eq!((Severity::Warning, "rate_limit:binance"), (alert.severity, alert.key.as_str()));
+        assert_eq!(None, alerter.message_alert(&message(MonitoringStatus::Ok, MonitoringEntity::PriceTicker)));
+    }
+
+    #[test]
+    fn test_connect_grace() {
+        let mut alerter = Alerter::new(AlertPolicy::new(Duration::ZERO, Duration::from_secs(60), 100), vec![]);
+        let message = |ts, status| MonitoringMessage::new(ts, status, MonitoringEntity::OrderManagementSystem, Exchange::Okx, 0);
+        let grace = CONNECT_GRACE.as_nanos();
+
+        assert_eq!(None, alerter.message_alert(&message(SEC, MonitoringStatus::Error)));
+        assert_eq!(None, alerter.message_alert(&message(2 * SEC, MonitoringStatus::Error)));
+        assert!(alerter.connect_alerts(SEC + grace - 1).is_empty());
+        let alerts = alerter.connect_alerts(SEC + grace);
+        assert_eq!(1, alerts.len());
+        assert_eq!((Severity::Critical, "oms:okx", "Okx OMS isn't connected"), (alerts[0].severity, alerts[0].key.as_str(), alerts[0].message.as_str()));
+        // reported once
+        assert!(alerter.connect_alerts(2 * grace).is_empty());
+
+        // connected within the grace period
+        let mut alerter = Alerter::new(AlertPolicy::new(Duration::ZERO, Duration::from_secs(60), 100), vec![]);
+        alerter.message_alert(&message(SEC, MonitoringStatus::Error));
+        alerter.message_alert(&message(2 * SEC, MonitoringStatus::Ok));
+        assert!(alerter.connect_alerts(2 * grace).is_empty());
+    }
+
+    #[test]
+    fn test_order_alerts() {
+        let mut order = Order::new();
+        order.timestamp = SEC;
+        order.strategy_id = "arb".to_string();
+        order.client_order_id = "1".to_string();
+        order.status = OrderStatus::Filled;
+        assert_eq!(None, Alerter::order_alert(&order));
+
+        order.status = OrderStatus::Error;
+        order.error = "Insufficient balance".to_string();
+        let alert = Alerter::order_alert(&order).unwrap();
+        assert_eq!((Severity::Warning, "order_error:arb"), (alert.severity, alert.key.as_str()));
+        assert!(alert.message.ends_with("failed: Insufficient balance"));
+
+        order.error = format!("{DAILY_LOSS_LIMIT_REASON} by arb: -101USDT");
+        let alert = Alerter::order_alert(&order).unwrap();
+        assert_eq!((Severity::Critical, "loss_limit:arb"), (alert.severity, alert.key.as_str()));
+        assert_eq!(order.error, alert.message);
+    }
+
+    #[test]
+    fn test_webhook_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for status in ["200 OK", "500 Internal Server Error"] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                sender.send((request_line, String::from_utf8(body).unwrap())).unwrap();
+                reader.get_mut().write_all(format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n").as_bytes()).unwrap();
+            }
+        });
+
+        assert!(WebhookSink::new("hooks.example.com/alerts").is_err());
+        assert!(WebhookSink::new("ftp://hooks.example.com/alerts").is_err());
+        let mut sink = WebhookSink::new(&format!("http://{address}/hooks/alerts?token=1")).unwrap();
+        let alert = Alert::new(SEC, Severity::Critical, "oms:binance".to_string(), "Binance OMS disconnected".to_string());
+        sink.send(&alert).unwrap();
+        let (request_line, body) = receiver.recv().unwrap();
+        assert_eq!("POST /hooks/alerts?token=1 HTTP/1.1", request_line.trim());
+        let body = json::parse(&body).unwrap();
+        assert_eq!("Critical", body["severity"].as_str().unwrap());
+        assert_eq!("oms:binance", body["key"].as_str().unwrap());
+        assert_eq!("[Critical] Binance OMS disconnected", body["text"].as_str().unwrap());
+
+        let err = sink.send(&alert).unwrap_err();
+        assert!(err.contains("500"), "{err}");
+    }
+
+    #[test]
+    fn test_file_sink() {
+        let path = temp_path("alerts.jsonl");
+        let mut sink = FileSink::new(&path);
+        sink.send(&create_alert(SEC, "a")).unwrap();
+        sink.send(&create_alert(2 * SEC, "b")).unwrap();
+
+        let lines: Vec<json::JsonValue> = std::fs::read_to_string(&path).unwrap().lines().map(|line| json::parse(line).unwrap()).collect();
+        assert_eq!(2, lines.len());
+        assert_eq!("b", lines[1]["key"].as_str().unwrap());
+        assert_eq!("2000000000", lines[1]["timestamp"].as_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_command_sink() {
+        let path = temp_path("alert_command");
+        let mut sink = CommandSink::new("sh", vec!["-c".to_string(), format!("echo \"$ALERT_SEVERITY $ALERT_KEY $ALERT_MESSAGE\" > {path}")]);
+        sink.send(&create_alert(SEC, "oms:okx")).unwrap();
+        assert_eq!("Warning oms:okx oms:okx failed\n", std::fs::read_to_string(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let mut sink = CommandSink::new("sh", vec!["-c".to_string(), "exit 3".to_string()]);
+        assert!(sink.send(&create_alert(SEC, "oms:okx")).is_err());
+    }
+
+    #[test]
+    fn test_alerter_delivery() {
+        let path = temp_path("alerter.jsonl");
+        let mut alerter = Alerter::new(
+            AlertPolicy::new(Duration::from_secs(60), Duration::from_secs(60), 100),
+            vec![Box::new(FileSink::new(&path))],
+        );
+        alerter.alert(create_alert(SEC, "a"));
+        // deduplicated
+        alerter.alert(create_alert(2 * SEC, "a"));
+        alerter.alert(create_alert(3 * SEC, "b"));
+
+        let mut lines = 0;
+        for _ in 0..50 {
+            lines = std::fs::read_to_string(&path).map(|text| text.lines().count()).unwrap_or(0);
+            if lines == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(2, lines);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
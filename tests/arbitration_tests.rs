@@ -0,0 +1,104 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+    use untitled::core::api::{MonitoringMessageListener, PriceTickerListener};
+    use untitled::core::arbitration::{Arbitrator, ConnectionStats};
+    use untitled::core::dto::{Exchange, Instrument, MonitoringEntity, MonitoringMessage, MonitoringStatus, PriceTicker};
+    use untitled::core::handlers::PriceTickerFilter;
+    use untitled::core::order_tracker::OrderTracker;
+    use crate::common::{self, create_instrument};
+
+    fn create_ticker(instrument: &Arc<Instrument>, update_id: u64, connection: usize) -> PriceTicker {
+        PriceTicker { update_id, connection, ..common::create_ticker(instrument, "100", "101") }
+    }
+
+    #[test]
+    fn test_first_copy_wins() {
+        let btc = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let eth = Arc::new(create_instrument(2, Exchange::Binance, "ETH", "USDT"));
+        let mut arbitrator = Arbitrator::new();
+
+        assert!(arbitrator.accept(&create_ticker(&btc, 10, 0)));
+        assert!(!arbitrator.accept(&create_ticker(&btc, 10, 1)));
+        assert!(arbitrator.accept(&create_ticker(&btc, 11, 1)));
+        // late update of the slow connection
+        assert!(!arbitrator.accept(&create_ticker(&btc, 10, 0)));
+        assert!(!arbitrator.accept(&create_ticker(&btc, 11, 0)));
+        // ids are per instrument
+        assert!(arbitrator.accept(&create_ticker(&eth, 5, 0)));
+
+        assert_eq!(ConnectionStats { wins: 2, duplicates: 2 }, arbitrator.connection_stats(&Exchange::Binance, 0));
+        assert_eq!(ConnectionStats { wins: 1, duplicates: 1 }, arbitrator.connection_stats(&Exchange::Binance, 1));
+    }
+
+    #[test]
+    fn test_no_update_id_and_reset() {
+        let btc = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let mut arbitrator = Arbitrator::new();
+
+        assert!(arbitrator.accept(&create_ticker(&btc, 0, 0)));
+        assert!(arbitrator.accept(&create_ticker(&btc, 0, 1)));
+        assert_eq!(ConnectionStats::default(), arbitrator.connection_stats(&Exchange::Binance, 0));
+
+        assert!(arbitrator.accept(&create_ticker(&btc, 100, 0)));
+        arbitrator.reset();
+        // the resubscribed stream starts over
+        assert!(arbitrator.accept(&create_ticker(&btc, 1, 1)));
+    }
+
+    #[test]
+    fn test_exchange_time_order() {
+        let btc = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let mut arbitrator = Arbitrator::new();
+        let create = |exchange_ts: u128| PriceTicker { exchange_ts, ..create_ticker(&btc, 0, 0) };
+
+        assert!(arbitrator.accept(&create(2_000)));
+        assert!(arbitrator.accept(&create(2_000)));
+        assert!(!arbitrator.accept(&create(1_000)));
+        assert!(arbitrator.accept(&create(3_000)));
+    }
+
+    #[test]
+    fn test_reset_connection() {
+        let btc = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let eth = Arc::new(create_instrument(2, Exchange::Binance, "ETH", "USDT"));
+        let mut arbitrator = Arbitrator::new();
+
+        assert!(arbitrator.accept(&create_ticker(&btc, 100, 0)));
+        assert!(arbitrator.accept(&create_ticker(&eth, 100, 1)));
+        arbitrator.reset_connection(&Exchange::Binance, 0);
+        // the restarted ids of the failed connection are taken, the other connection keeps its instruments
+        assert!(arbitrator.accept(&create_ticker(&btc, 1, 0)));
+        assert!(!arbitrator.accept(&create_ticker(&eth, 1, 0)));
+
+        arbitrator.reset_exchange(&Exchange::Okx);
+        assert!(!arbitrator.accept(&create_ticker(&eth, 1, 0)));
+        arbitrator.reset_exchange(&Exchange::Binance);
+        assert!(arbitrator.accept(&create_ticker(&eth, 1, 0)));
+    }
+
+    #[test]
+    fn test_stream_error_keeps_other_connections() {
+        let btc = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let mut filter = PriceTickerFilter::new(vec![], Arc::new(RwLock::new(OrderTracker::new())));
+        let message = |status: MonitoringStatus, connection: usize| MonitoringMessage {
+            connection,
+            ..MonitoringMessage::new(1, status, MonitoringEntity::PriceTicker, Exchange::Binance, 0)
+        };
+        filter.on_monitoring_message(&message(MonitoringStatus::Ok, 0));
+        filter.on_monitoring_message(&message(MonitoringStatus::Ok, 1));
+        filter.on_price_ticker(&create_ticker(&btc, 100, 1), &HashMap::new());
+
+        // the degraded exchange keeps the prices of the live connection
+        filter.on_monitoring_message(&message(MonitoringStatus::Error, 0));
+        assert_eq!(1, filter.tickers_map()[&Exchange::Binance].len());
+        assert!(!filter.arbitrator.accept(&create_ticker(&btc, 100, 0)));
+
+        filter.on_monitoring_message(&message(MonitoringStatus::Error, 1));
+        assert!(filter.tickers_map()[&Exchange::Binance].is_empty());
+        assert!(filter.arbitrator.accept(&create_ticker(&btc, 1, 0)));
+    }
+}
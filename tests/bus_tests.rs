@@ -0,0 +1,153 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use untitled::core::bus::{Channel, EventBus, OverflowPolicy, Signal, TickerChannel};
+    use untitled::core::decimal::Decimal;
+    use untitled::core::dto::{Exchange, Instrument, MonitoringEntity, MonitoringMessage, MonitoringStatus, Order, PriceTicker, DTO, TICKER_PRICE_NOT_CHANGED};
+    use crate::common::{self, create_instrument, dec};
+
+    fn create_ticker(timestamp: u128) -> PriceTicker {
+        let btc = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        PriceTicker { timestamp, ..common::create_ticker(&btc, "1", "1") }
+    }
+
+    fn create_update(instrument: &Arc<Instrument>, update_id: u64, bid: Decimal, ask: Decimal) -> PriceTicker {
+        PriceTicker { timestamp: update_id as u128, bid, ask, update_id, ..common::create_ticker(instrument, "1", "1") }
+    }
+
+    #[test]
+    fn test_ticker_conflation() {
+        // the only test of the exchange, the counters are global
+        let btc = Arc::new(create_instrument(10, Exchange::Kraken, "XBT", "USD"));
+        let eth = Arc::new(create_instrument(11, Exchange::Kraken, "ETH", "USD"));
+        let tickers = TickerChannel::new(Arc::new(Signal::default()));
+
+        assert!(tickers.push(create_update(&btc, 1, dec("100"), dec("101"))));
+        assert!(tickers.push(create_update(&eth, 2, dec("10"), dec("11"))));
+        // the bid only update is merged into the pending one
+        assert!(tickers.push(create_update(&btc, 3, dec("100.5"), TICKER_PRICE_NOT_CHANGED)));
+        // the redundant copy of the older update
+        assert!(!tickers.push(create_update(&btc, 2, dec("99"), dec("99.5"))));
+        assert_eq!(2, tickers.len());
+        // only the merge is counted
+        assert_eq!(1, tickers.conflated(&Exchange::Kraken));
+
+        let ticker = tickers.pop().unwrap();
+        assert_eq!((btc.id, 3, dec("100.5"), dec("101")), (ticker.instrument.id, ticker.update_id, ticker.bid, ticker.ask));
+        let ticker = tickers.pop().unwrap();
+        assert_eq!((eth.id, 2), (ticker.instrument.id, ticker.update_id));
+        assert!(tickers.pop().is_none());
+
+        // the consumed instrument is pending again
+        assert!(tickers.push(create_update(&btc, 4, dec("102"), dec("103"))));
+        assert_eq!(4, tickers.pop().unwrap().update_id);
+        assert_eq!(1, tickers.conflated(&Exchange::Kraken));
+    }
+
+    #[test]
+    fn test_drop_policies() {
+        let signal = Arc::new(Signal::default());
+        let channel = Channel::new("test_drop_newest", 2, OverflowPolicy::DropNewest, Arc::clone(&signal));
+        assert!(channel.push(1));
+        assert!(channel.push(2));
+        assert!(!channel.push(3));
+        assert_eq!((Some(1), Some(2), None), (channel.pop(), channel.pop(), channel.pop()));
+        assert_eq!(1, channel.overflows());
+
+        let channel = Channel::new("test_drop_oldest", 2, OverflowPolicy::DropOldest, signal);
+        assert!(channel.push(1));
+        assert!(channel.push(2));
+        assert!(!channel.push(3));
+        assert_eq!((Some(2), Some(3), None), (channel.pop(), channel.pop(), channel.pop()));
+        assert_eq!(1, channel.overflows());
+    }
+
+    #[test]
+    fn test_block_policy() {
+        let channel = Arc::new(Channel::new("test_block", 1, OverflowPolicy::Block, Arc::new(Signal::default())));
+        assert!(channel.push(1));
+
+        let producer = {
+            let channel = Arc::clone(&channel);
+            thread::spawn(move || channel.push(2))
+        };
+        thread::sleep(Duration::from_millis(20));
+        // the producer waits for the slot
+        assert_eq!(1, channel.len());
+        assert_eq!(Some(1), channel.pop());
+        assert!(producer.join().unwrap());
+        assert_eq!(Some(2), channel.pop());
+        assert_eq!(1, channel.overflows());
+    }
+
+    #[test]
+    fn test_event_bus_priority() {
+        let bus = EventBus::new(10);
+        assert!(bus.publish(DTO::PriceTicker(create_ticker(1))));
+        assert!(bus.publish(DTO::Order(Order::new())));
+        assert!(bus.publish(DTO::MonitoringMessage(MonitoringMessage::new(
+            2, MonitoringStatus::Ok, MonitoringEntity::PriceTicker, Exchange::Binance, 0,
+        ))));
+        assert_eq!(3, bus.len());
+
+        assert!(matches!(bus.next(), Some(DTO::Order(_))));
+        assert!(matches!(bus.next(), Some(DTO::MonitoringMessage(_))));
+        assert!(matches!(bus.next(), Some(DTO::PriceTicker(ticker)) if ticker.timestamp == 1));
+        assert!(bus.next().is_none());
+        assert!(bus.is_empty());
+    }
+
+    #[test]
+    fn test_wait_wakes_up_on_publish() {
+        let bus = Arc::new(EventBus::new(10));
+        // the notification before the wait isn't lost
+        bus.publish(DTO::PriceTicker(create_ticker(1)));
+        let start = Instant::now();
+        bus.wait(Duration::from_secs(5));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        bus.next().unwrap();
+
+        let producer = {
+            let bus = Arc::clone(&bus);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                bus.publish(DTO::PriceTicker(create_ticker(2)));
+            })
+        };
+        let start = Instant::now();
+        bus.wait(Duration::from_secs(5));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(matches!(bus.next(), Some(DTO::PriceTicker(ticker)) if ticker.timestamp == 2));
+        producer.join().unwrap();
+
+        // the timeout without the messages
+        let start = Instant::now();
+        bus.wait(Duration::from_millis(20));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_tickers_before_stream_error() {
+        let bus = EventBus::new(10);
+        let mut okx_ticker = create_ticker(2);
+        okx_ticker.instrument = Arc::new(create_instrument(2, Exchange::Okx, "BTC", "USDT"));
+        assert!(bus.publish(DTO::PriceTicker(create_ticker(1))));
+        assert!(bus.publish(DTO::PriceTicker(okx_ticker)));
+        assert!(bus.publish(DTO::MonitoringMessage(MonitoringMessage::new(
+            3, MonitoringStatus::Error, MonitoringEntity::PriceTicker, Exchange::Binance, 0,
+        ))));
+        assert!(bus.publish(DTO::Order(Order::new())));
+
+        // the order goes first, the binance ticker is handled before the error of its stream
+        assert!(matches!(bus.next(), Some(DTO::Order(_))));
+        assert!(matches!(bus.next(), Some(DTO::PriceTicker(ticker)) if ticker.timestamp == 1));
+        assert_eq!(2, bus.len());
+        assert!(matches!(bus.next(), Some(DTO::MonitoringMessage(message)) if message.status == MonitoringStatus::Error));
+        assert!(matches!(bus.next(), Some(DTO::PriceTicker(ticker)) if ticker.timestamp == 2));
+        assert!(bus.is_empty());
+    }
+}
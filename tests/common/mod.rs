@@ -0,0 +1,43 @@
+// every test crate takes a part of the fixtures
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use untitled::core::decimal::Decimal;
+use untitled::core::dto::{Exchange, Instrument, InstrumentId, PriceTicker};
+
+pub fn dec(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
+
+/// BASEQUOTE instrument with the Binance spot filters
+pub fn create_instrument(id: u32, exchange: Exchange, base: &str, quote: &str) -> Instrument {
+    Instrument {
+        id: InstrumentId(id),
+        exchange,
+        symbol: format!("{base}{quote}"),
+        base: base.to_string(),
+        quote: quote.to_string(),
+        amount_precision: 4,
+        price_precision: 2,
+        order_amount_min: dec("0.0001"),
+        order_amount_max: dec("9000"),
+        order_notional_min: dec("5"),
+        order_notional_max: dec("9000000"),
+        maker_fee: 0.001,
+        taker_fee: 0.001,
+    }
+}
+
+pub fn create_ticker(instrument: &Arc<Instrument>, bid: &str, ask: &str) -> PriceTicker {
+    PriceTicker {
+        timestamp: 0,
+        instrument: Arc::clone(instrument),
+        bid: dec(bid),
+        bid_amount: dec("10"),
+        ask: dec(ask),
+        ask_amount: dec("10"),
+        update_id: 0,
+        exchange_ts: 0,
+        connection: 0,
+    }
+}
@@ -0,0 +1,84 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+    use json::object;
+    use untitled::core::bus::{Channel, OverflowPolicy, Signal};
+    use untitled::core::control::{parse_command, serve, ControlCommand, ControlRequest, StrategyCommand};
+    use crate::common::dec;
+
+    #[test]
+    fn test_parse_command() {
+        let strategy = |command| Ok(Some(ControlCommand::Strategy("arb_binance".to_string(), command)));
+
+        assert_eq!(Ok(Some(ControlCommand::Strategies)), parse_command("GET", "/strategies", ""));
+        assert_eq!(Ok(Some(ControlCommand::Tickers)), parse_command("GET", "/tickers/", ""));
+        assert_eq!(Ok(Some(ControlCommand::Health)), parse_command("GET", "/health", ""));
+        assert_eq!(strategy(StrategyCommand::State), parse_command("GET", "/strategies/arb_binance", ""));
+        assert_eq!(strategy(StrategyCommand::Graph), parse_command("GET", "/strategies/arb_binance/graph", ""));
+        assert_eq!(strategy(StrategyCommand::Pause), parse_command("POST", "/strategies/arb_binance/pause", ""));
+        assert_eq!(strategy(StrategyCommand::Resume), parse_command("POST", "/strategies/arb_binance/resume", ""));
+        assert_eq!(
+            strategy(StrategyCommand::MonitoringOnly(true)),
+            parse_command("POST", "/strategies/arb_binance/monitoring_only", r#"{"enabled": true}"#),
+        );
+        assert_eq!(
+            strategy(StrategyCommand::Sizing { min_order_size: dec("20"), max_order_size: dec("30.5") }),
+            parse_command("POST", "/strategies/arb_binance/sizing", r#"{"min_order_size": "20", "max_order_size": 30.5}"#),
+        );
+        assert_eq!(Ok(Some(ControlCommand::KillSwitch(false))), parse_command("POST", "/kill_switch", r#"{"active": false}"#));
+
+        // unknown routes
+        assert_eq!(Ok(None), parse_command("POST", "/strategies", ""));
+        assert_eq!(Ok(None), parse_command("GET", "/strategies/arb_binance/pause", ""));
+        assert_eq!(Ok(None), parse_command("GET", "/", ""));
+
+        // invalid bodies
+        assert!(parse_command("POST", "/kill_switch", "").is_err());
+        assert!(parse_command("POST", "/strategies/arb_binance/monitoring_only", r#"{"enabled": "yes"}"#).is_err());
+        assert!(parse_command("POST", "/strategies/arb_binance/sizing", r#"{"min_order_size": "0", "max_order_size": "30"}"#).is_err());
+        assert!(parse_command("POST", "/strategies/arb_binance/sizing", r#"{"min_order_size": "40", "max_order_size": "30"}"#).is_err());
+    }
+
+    fn request(address: &str, method: &str, path: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve() {
+        let signal = Arc::new(Signal::default());
+        let channel: Arc<Channel<ControlRequest>> = Arc::new(Channel::new("control_test", 10, OverflowPolicy::DropNewest, Arc::clone(&signal)));
+        let address = serve("127.0.0.1:0", Arc::clone(&channel)).to_string();
+
+        // the main loop
+        thread::spawn(move || loop {
+            while let Some(request) = channel.pop() {
+                match &request.command {
+                    ControlCommand::KillSwitch(active) => request.reply(Ok(object! { active: *active })),
+                    _ => request.reply(Err("Unknown strategy".to_string())),
+                }
+            }
+            signal.wait(Duration::from_secs(1));
+        });
+
+        let response = request(&address, "POST", "/kill_switch", r#"{"active": true}"#);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with(r#"{"active":true}"#), "{response}");
+
+        let response = request(&address, "GET", "/strategies/unknown", "");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{response}");
+        assert!(response.ends_with(r#"{"error":"Unknown strategy"}"#), "{response}");
+
+        assert!(request(&address, "POST", "/kill_switch", "{").starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(request(&address, "GET", "/orders", "").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
@@ -0,0 +1,82 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use untitled::core::decimal::Decimal;
+    use untitled::core::utils::RoundingMode;
+    use crate::common::dec;
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!("0.00010000", dec("0.00010000").to_string());
+        assert_eq!("-12.5", dec("-12.5").to_string());
+        assert_eq!("1500", dec("1.5e3").to_string());
+        assert_eq!("0.0015", dec("1.5E-3").to_string());
+        assert_eq!("0.5", dec(".5").to_string());
+        assert_eq!(dec("1.10"), dec("1.1"));
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("".parse::<Decimal>().is_err());
+        assert!("abc".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(dec("0.3"), dec("0.1") + dec("0.2"));
+        assert_eq!(dec("-0.1"), dec("0.1") - dec("0.2"));
+        assert_eq!(dec("17515.71297"), dec("0.25137") * dec("69681"));
+        assert_eq!(dec("0.004"), dec("10") / dec("2500"));
+        assert_eq!(dec("0.333333333333333333"), dec("1") / dec("3"));
+        assert_eq!(dec("0.6"), vec![dec("0.1"), dec("0.2"), dec("0.3")].into_iter().sum());
+        assert!(dec("0.1") < dec("0.10001"));
+        assert!(dec("-1") < Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_parse_bounds() {
+        assert_eq!(dec("1e37"), Decimal::new(10_i128.pow(37), 0));
+        assert!("1e50".parse::<Decimal>().is_err());
+        assert!("1e2147483647".parse::<Decimal>().is_err());
+        assert!("123456789012345678901234567890123456789012".parse::<Decimal>().is_err());
+        // the digits beyond the max scale are rounded
+        assert_eq!(Decimal::ZERO, dec("1e-50"));
+        assert_eq!(Decimal::ZERO, dec("1e-2147483648"));
+        assert_eq!(dec("0.000000000000000001"), dec("5e-19"));
+    }
+
+    #[test]
+    fn test_compare_wide_scales() {
+        let wide = Decimal::new(i128::MAX, 0);
+        let precise = dec("0.000000000000000001");
+        assert!(wide > precise);
+        assert!(-wide < precise);
+        assert!(precise < wide);
+        assert!(Decimal::new(-1, 0) < Decimal::new(1, 30));
+        assert_eq!(Decimal::ZERO, Decimal::new(0, 40));
+    }
+
+    #[test]
+    fn test_division_precision() {
+        // the amount of the high priced asset keeps the significant digits, the scale is capped
+        assert_eq!(dec("0.000000005"), dec("5") / dec("1000000000"));
+        assert_eq!(dec("0.000001234567901189"), dec("1") / dec("810000.00003"));
+        assert_eq!(dec("0.016666666666666667"), dec("1000") / dec("60000"));
+        assert_eq!(dec("33333333333333333333"), dec("100000000000000000000") / dec("3"));
+        assert_eq!(dec("2000000000000000000000000000000"), dec("2000000000000000000000000000000") / Decimal::ONE);
+        assert_eq!(dec("-2.5"), dec("5") / dec("-2"));
+    }
+
+    #[test]
+    fn test_round() {
+        assert_eq!(dec("2500.12"), dec("2500.129").round(2, RoundingMode::Down));
+        assert_eq!(dec("2500.13"), dec("2500.121").round(2, RoundingMode::Up));
+        assert_eq!(dec("2500.12"), dec("2500.12").round(2, RoundingMode::Up));
+        assert_eq!(dec("-1.24"), dec("-1.235").round(2, RoundingMode::Down));
+        assert_eq!("1.500", dec("1.5").round(3, RoundingMode::Down).to_string());
+    }
+
+    #[test]
+    fn test_f64_conversion() {
+        assert_eq!(dec("0.001"), Decimal::from_f64(0.001, 8));
+        assert_eq!(69681.5, dec("69681.5").to_f64());
+    }
+}
@@ -0,0 +1,114 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use untitled::core::decimal::Decimal;
+    use untitled::core::dto::{Exchange, Instrument, Order, OrderSide, OrderStatus, OrderType};
+    use untitled::core::fees::{FeeCurrency, FeeModel, FeeSchedule, FeeTier, Liquidity};
+    use untitled::core::oes::OrderExecutionSimulator;
+    use crate::common::{self, create_ticker, dec};
+
+    // the instrument fees differ from the schedule tiers
+    fn create_instrument(id: u32, exchange: Exchange, base: &str, quote: &str) -> Arc<Instrument> {
+        Arc::new(Instrument { maker_fee: 0.002, taker_fee: 0.002, ..common::create_instrument(id, exchange, base, quote) })
+    }
+
+    fn binance_schedule(volume_30d: &str, fee_currency: FeeCurrency) -> FeeSchedule {
+        FeeSchedule::new(
+            vec![
+                FeeTier::new("VIP1", dec("1000000"), 0.0009, 0.001),
+                FeeTier::new("VIP0", Decimal::ZERO, 0.001, 0.001),
+                FeeTier::new("VIP3", dec("20000000"), 0.00042, 0.0006),
+            ],
+            dec(volume_30d),
+            fee_currency,
+        )
+    }
+
+    fn bnb() -> FeeCurrency {
+        FeeCurrency::Token { currency: "BNB".to_string(), discount: 0.25 }
+    }
+
+    #[test]
+    fn test_tiers_and_discount() {
+        let eth_usdt = create_instrument(1, Exchange::Binance, "ETH", "USDT");
+        assert_eq!("VIP1", binance_schedule("2500000", FeeCurrency::Proceeds).tier().unwrap().name);
+        assert_eq!("VIP3", binance_schedule("20000000", FeeCurrency::Proceeds).tier().unwrap().name);
+
+        let mut fee_model = FeeModel::new();
+        // no schedule, the instrument fees from the proceeds
+        assert_eq!(0.002, fee_model.rate(&eth_usdt, Liquidity::Taker));
+        assert_eq!("ETH", fee_model.fee_currency(&eth_usdt, OrderSide::Buy));
+
+        fee_model.insert(Exchange::Binance, binance_schedule("0", FeeCurrency::Proceeds));
+        assert_eq!(0.001, fee_model.proceeds_rate(&eth_usdt, Liquidity::Taker));
+        assert_eq!("USDT", fee_model.fee_currency(&eth_usdt, OrderSide::Sell));
+
+        fee_model.insert(Exchange::Binance, binance_schedule("1000000", bnb()));
+        assert!((fee_model.rate(&eth_usdt, Liquidity::Maker) - 0.000675).abs() < 1e-12);
+        assert_eq!(0., fee_model.proceeds_rate(&eth_usdt, Liquidity::Taker));
+        assert_eq!("BNB", fee_model.fee_currency(&eth_usdt, OrderSide::Buy));
+
+        // other exchanges aren't affected
+        let mexc_eth_usdt = create_instrument(2, Exchange::Mexc, "ETH", "USDT");
+        assert_eq!(0.002, fee_model.proceeds_rate(&mexc_eth_usdt, Liquidity::Taker));
+    }
+
+    #[test]
+    fn test_order_balance_net_of_fees() {
+        let eth_usdt = create_instrument(1, Exchange::Binance, "ETH", "USDT");
+        let mut order = Order::new();
+        order.instrument = eth_usdt;
+        order.side = OrderSide::Buy;
+        order.status = OrderStatus::Filled;
+        order.amount_filled = dec("1");
+        order.fees = vec![("ETH".to_string(), dec("0.001"))];
+        assert_eq!(Some(("ETH".to_string(), dec("0.999"))), order.balance());
+
+        order.fees = vec![("BNB".to_string(), dec("0.003"))];
+        assert_eq!(Some(("ETH".to_string(), dec("1"))), order.balance());
+    }
+
+    #[test]
+    fn test_simulator_fees() {
+        let eth_usdt = create_instrument(1, Exchange::Binance, "ETH", "USDT");
+        let bnb_usdt = create_instrument(2, Exchange::Binance, "BNB", "USDT");
+        let mut tickers = HashMap::new();
+        tickers.insert(eth_usdt.id, create_ticker(&eth_usdt, "2000", "2000"));
+        tickers.insert(bnb_usdt.id, create_ticker(&bnb_usdt, "500", "501"));
+
+        let mut order = Order::new();
+        order.instrument = Arc::clone(&eth_usdt);
+        order.order_type = OrderType::Market;
+        order.side = OrderSide::Buy;
+        order.amount = dec("1");
+
+        let mut fee_model = FeeModel::new();
+        fee_model.insert(Exchange::Binance, binance_schedule("0", FeeCurrency::Proceeds));
+        let balances = HashMap::from([("USDT".to_string(), dec("2000"))]);
+        let mut simulator = OrderExecutionSimulator::new(balances.clone(), fee_model.clone());
+        let executed = simulator.execute_market_orders(vec![order.clone()], &tickers);
+        assert_eq!(vec![("ETH".to_string(), dec("0.001"))], executed[0].fees);
+        assert_eq!(&dec("0.999"), simulator.balances.get("ETH").unwrap());
+
+        // 2000 * 0.00075 = 1.5 USDT = 0.003 BNB
+        fee_model.insert(Exchange::Binance, binance_schedule("0", bnb()));
+        let mut balances = balances;
+        balances.insert("BNB".to_string(), dec("1"));
+        let mut simulator = OrderExecutionSimulator::new(balances.clone(), fee_model.clone());
+        let executed = simulator.execute_market_orders(vec![order.clone()], &tickers);
+        assert_eq!(vec![("BNB".to_string(), dec("0.003"))], executed[0].fees);
+        assert_eq!(&dec("1"), simulator.balances.get("ETH").unwrap());
+        assert_eq!(&dec("0.997"), simulator.balances.get("BNB").unwrap());
+
+        // not enough BNB, paid from the proceeds
+        balances.insert("BNB".to_string(), Decimal::ZERO);
+        let mut simulator = OrderExecutionSimulator::new(balances, fee_model);
+        let executed = simulator.execute_market_orders(vec![order], &tickers);
+        // the full rate without the discount
+        assert_eq!(vec![("ETH".to_string(), dec("0.001"))], executed[0].fees);
+        assert_eq!(&dec("0.999"), simulator.balances.get("ETH").unwrap());
+    }
+}
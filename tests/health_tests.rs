@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use untitled::core::dto::{Exchange, MonitoringEntity, MonitoringMessage, MonitoringStatus};
+    use untitled::core::health::{HealthKey, HealthRegistry, HealthStatus};
+
+    fn create_message(ts: u128, status: MonitoringStatus, entity: MonitoringEntity, exchange: Exchange, connection: usize, entity_id: usize) -> MonitoringMessage {
+        let mut message = MonitoringMessage::new(ts, status, entity, exchange, entity_id);
+        message.connection = connection;
+        message
+    }
+
+    #[test]
+    fn test_exchange_down_and_up() {
+        let mut registry = HealthRegistry::new();
+        let stream = |ts, status, connection, socket| {
+            create_message(ts, status, MonitoringEntity::PriceTicker, Exchange::Kraken, connection, socket)
+        };
+
+        assert_eq!(None, registry.on_monitoring_message(&stream(1, MonitoringStatus::Ok, 0, 0)));
+        // the same socket id of the redundant connection
+        assert_eq!(None, registry.on_monitoring_message(&stream(2, MonitoringStatus::Ok, 1, 0)));
+
+        let event = registry.on_monitoring_message(&stream(3, MonitoringStatus::Error, 0, 0)).unwrap();
+        assert_eq!((HealthStatus::Degraded, 1, 2), (event.status, event.errored, event.total));
+        assert_eq!(HealthStatus::Degraded, registry.status());
+        // repeated errors don't change the group
+        assert_eq!(None, registry.on_monitoring_message(&stream(4, MonitoringStatus::Error, 0, 0)));
+
+        let event = registry.on_monitoring_message(&stream(5, MonitoringStatus::Error, 1, 0)).unwrap();
+        assert_eq!((Exchange::Kraken, HealthStatus::Down, 2, 2), (event.exchange.clone(), event.status, event.errored, event.total));
+        assert_eq!(HealthStatus::Down, registry.status());
+        // other exchanges aren't affected
+        assert_eq!(HealthStatus::Ok, registry.group_status(&MonitoringEntity::PriceTicker, &Exchange::Binance));
+
+        registry.on_monitoring_message(&stream(6, MonitoringStatus::Ok, 0, 0)).unwrap();
+        let event = registry.on_monitoring_message(&stream(7, MonitoringStatus::Ok, 1, 0)).unwrap();
+        assert_eq!((HealthStatus::Ok, 0, 2), (event.status, event.errored, event.total));
+        assert_eq!(HealthStatus::Ok, registry.status());
+
+        let key = HealthKey { entity: MonitoringEntity::PriceTicker, exchange: Exchange::Kraken, connection: 0, entity_id: 0 };
+        let health = registry.get(&key).unwrap();
+        assert_eq!((MonitoringStatus::Ok, 6, 6, 2), (health.status, health.last_seen_ts, health.changed_ts, health.errors));
+    }
+
+    #[test]
+    fn test_single_entity_group() {
+        let mut registry = HealthRegistry::new();
+        let oms = |ts, status| create_message(ts, status, MonitoringEntity::OrderManagementSystem, Exchange::Binance, 0, 1);
+
+        // not connected yet
+        let event = registry.on_monitoring_message(&oms(1, MonitoringStatus::Error)).unwrap();
+        assert_eq!((MonitoringEntity::OrderManagementSystem, HealthStatus::Down), (event.entity, event.status));
+        let event = registry.on_monitoring_message(&oms(2, MonitoringStatus::Ok)).unwrap();
+        assert_eq!(HealthStatus::Ok, event.status);
+        assert_eq!(1, registry.entities().count());
+        assert_eq!("Ok", registry.to_json()["status"].as_str().unwrap());
+    }
+}
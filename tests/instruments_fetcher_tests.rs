@@ -0,0 +1,104 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use untitled::core::dto::Exchange;
+    use untitled::core::instruments_fetcher::{diff, parse_binance, parse_bit2me, parse_bybit, parse_mexc, parse_okx, to_json};
+    use untitled::core::map::InstrumentsMap;
+    use crate::common::dec;
+
+    const BINANCE_EXCHANGE_INFO: &str = r#"{"symbols": [
+        {"symbol": "ETHUSDT", "status": "TRADING", "baseAsset": "ETH", "quoteAsset": "USDT", "filters": [
+            {"filterType": "PRICE_FILTER", "minPrice": "0.01000000", "maxPrice": "1000000.00000000", "tickSize": "0.01000000"},
+            {"filterType": "LOT_SIZE", "minQty": "0.00010000", "maxQty": "9000.00000000", "stepSize": "0.00010000"},
+            {"filterType": "NOTIONAL", "minNotional": "5.00000000", "maxNotional": "9000000.00000000"}
+        ]},
+        {"symbol": "BNBBTC", "status": "TRADING", "baseAsset": "BNB", "quoteAsset": "BTC", "filters": [
+            {"filterType": "PRICE_FILTER", "tickSize": "0.00000100"},
+            {"filterType": "LOT_SIZE", "minQty": "0.00100000", "maxQty": "100000.00000000", "stepSize": "0.00100000"},
+            {"filterType": "MIN_NOTIONAL", "minNotional": "0.00010000"}
+        ]},
+        {"symbol": "LUNAUSDT", "status": "BREAK", "baseAsset": "LUNA", "quoteAsset": "USDT", "filters": []}
+    ]}"#;
+
+    #[test]
+    fn test_parse_binance() {
+        let instruments = parse_binance(&json::parse(BINANCE_EXCHANGE_INFO).unwrap());
+        assert_eq!(2, instruments.len());
+
+        let eth_usdt = &instruments[0];
+        assert_eq!(Exchange::Binance, eth_usdt.exchange);
+        assert_eq!(("ETH", "USDT"), (eth_usdt.base.as_str(), eth_usdt.quote.as_str()));
+        assert_eq!((4, 2), (eth_usdt.amount_precision, eth_usdt.price_precision));
+        assert_eq!((dec("0.0001"), dec("9000")), (eth_usdt.order_amount_min, eth_usdt.order_amount_max));
+        assert_eq!((dec("5"), dec("9000000")), (eth_usdt.order_notional_min, eth_usdt.order_notional_max));
+
+        let bnb_btc = &instruments[1];
+        assert_eq!((3, 6), (bnb_btc.amount_precision, bnb_btc.price_precision));
+        assert_eq!(dec("0.0001"), bnb_btc.order_notional_min);
+    }
+
+    #[test]
+    fn test_parse_mexc_and_bit2me() {
+        let mexc = parse_mexc(&json::parse(r#"{"symbols": [
+            {"symbol": "BTCUSDT", "status": "1", "isSpotTradingAllowed": true, "baseAsset": "BTC", "quoteAsset": "USDT",
+             "baseAssetPrecision": 6, "quotePrecision": 2, "baseSizePrecision": "0", "quoteAmountPrecision": "1",
+             "maxQuoteAmount": "2000000", "makerCommission": "0", "takerCommission": "0.0005"},
+            {"symbol": "XYZUSDT", "status": "2", "isSpotTradingAllowed": true, "baseAsset": "XYZ", "quoteAsset": "USDT"}
+        ]}"#).unwrap());
+        assert_eq!(1, mexc.len());
+        assert_eq!(dec("0.000001"), mexc[0].order_amount_min);
+        assert_eq!((dec("1"), dec("2000000")), (mexc[0].order_notional_min, mexc[0].order_notional_max));
+        assert_eq!((0., 0.0005), (mexc[0].maker_fee, mexc[0].taker_fee));
+
+        let bit2me = parse_bit2me(&json::parse(r#"[
+            {"symbol": "B2M/USDT", "status": "active", "amountPrecision": 2, "pricePrecision": 6, "minAmount": 10, "maxAmount": "1000000"},
+            {"symbol": "OLD/EUR", "status": "inactive", "amountPrecision": 2, "pricePrecision": 2}
+        ]"#).unwrap());
+        assert_eq!(1, bit2me.len());
+        assert_eq!(("B2M", "USDT"), (bit2me[0].base.as_str(), bit2me[0].quote.as_str()));
+        assert_eq!(dec("10"), bit2me[0].order_amount_min);
+    }
+
+    #[test]
+    fn test_parse_okx_and_bybit() {
+        let okx = parse_okx(&json::parse(r#"{"code": "0", "data": [
+            {"instType": "SPOT", "instId": "BTC-USDT", "baseCcy": "BTC", "quoteCcy": "USDT", "state": "live",
+             "tickSz": "0.1", "lotSz": "0.00000001", "minSz": "0.00001", "maxLmtSz": "9999999999", "maxLmtAmt": "20000000"},
+            {"instType": "SPOT", "instId": "OLD-USDT", "baseCcy": "OLD", "quoteCcy": "USDT", "state": "suspend",
+             "tickSz": "0.1", "lotSz": "1", "minSz": "1"}
+        ]}"#).unwrap());
+        assert_eq!(1, okx.len());
+        assert_eq!(Exchange::Okx, okx[0].exchange);
+        assert_eq!("BTC-USDT", okx[0].symbol);
+        assert_eq!((8, 1), (okx[0].amount_precision, okx[0].price_precision));
+        assert_eq!((dec("0.00001"), dec("20000000")), (okx[0].order_amount_min, okx[0].order_notional_max));
+
+        let bybit = parse_bybit(&json::parse(r#"{"retCode": 0, "result": {"category": "spot", "list": [
+            {"symbol": "BTCUSDT", "baseCoin": "BTC", "quoteCoin": "USDT", "status": "Trading",
+             "lotSizeFilter": {"basePrecision": "0.000001", "quotePrecision": "0.00000001", "minOrderQty": "0.000048",
+                               "maxOrderQty": "71.73956243", "minOrderAmt": "1", "maxOrderAmt": "2000000"},
+             "priceFilter": {"tickSize": "0.01"}},
+            {"symbol": "OLDUSDT", "baseCoin": "OLD", "quoteCoin": "USDT", "status": "Closed"}
+        ]}}"#).unwrap());
+        assert_eq!(1, bybit.len());
+        assert_eq!((6, 2), (bybit[0].amount_precision, bybit[0].price_precision));
+        assert_eq!((dec("0.000048"), dec("71.73956243")), (bybit[0].order_amount_min, bybit[0].order_amount_max));
+        assert_eq!((dec("1"), dec("2000000")), (bybit[0].order_notional_min, bybit[0].order_notional_max));
+    }
+
+    #[test]
+    fn test_to_json_and_diff() {
+        let new = parse_binance(&json::parse(BINANCE_EXCHANGE_INFO).unwrap());
+        // the schema InstrumentsMap reads
+        let mut old = InstrumentsMap::parse_instruments(&to_json(&new)).unwrap();
+        assert!(diff(&old, &new).changed.is_empty());
+
+        old[0].price_precision = 3;
+        old[1].symbol = "LUNAUSDT".to_string();
+        let report = diff(&old, &new);
+        assert_eq!(vec!["binance:BNBBTC".to_string()], report.added);
+        assert_eq!(vec!["binance:LUNAUSDT".to_string()], report.delisted);
+        assert_eq!(vec!["binance:ETHUSDT".to_string()], report.changed);
+    }
+}
@@ -0,0 +1,114 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::sync::Arc;
+    use untitled::core::dto::{Exchange, Instrument};
+    use untitled::core::map::{InstrumentsMap, InstrumentsRegistry};
+    use untitled::core::streams::sharding::Shards;
+    use crate::common;
+
+    fn create_instrument(exchange: Exchange, base: &str, quote: &str, taker_fee: f64) -> Instrument {
+        Instrument { taker_fee, ..common::create_instrument(0, exchange, base, quote) }
+    }
+
+    #[test]
+    fn test_swap() {
+        let registry = InstrumentsRegistry::new("", InstrumentsMap::from_instruments(vec![
+            create_instrument(Exchange::Binance, "ETH", "USDT", 0.001),
+            create_instrument(Exchange::Binance, "BTC", "USDT", 0.001),
+            create_instrument(Exchange::Mexc, "BTC", "USDT", 0.001),
+        ]));
+        let snapshot = registry.load();
+        assert_eq!(0, registry.version());
+        assert_eq!(3, snapshot.instruments().count());
+
+        let update = registry.swap(vec![
+            create_instrument(Exchange::Binance, "ETH", "USDT", 0.00075),
+            create_instrument(Exchange::Binance, "SOL", "USDT", 0.001),
+            create_instrument(Exchange::Mexc, "BTC", "USDT", 0.001),
+        ]);
+        assert_eq!(1, registry.version());
+        assert_eq!(vec!["SOLUSDT"], update.added.iter().map(|i| i.symbol.as_str()).collect::<Vec<_>>());
+        assert_eq!(vec!["BTCUSDT"], update.removed.iter().map(|i| i.symbol.as_str()).collect::<Vec<_>>());
+        assert_eq!(Exchange::Binance, update.removed[0].exchange);
+        assert_eq!(0.00075, update.changed[0].taker_fee);
+
+        assert!(registry.get(&Exchange::Binance, "btcusdt").is_none());
+        assert!(registry.get(&Exchange::Mexc, "BTC/USDT").is_some());
+        // readers keep their snapshot until they reload it
+        assert!(snapshot.get(&Exchange::Binance, "BTCUSDT").is_some());
+
+        // nothing changed, the version stays
+        let update = registry.swap(vec![
+            create_instrument(Exchange::Binance, "ETH", "USDT", 0.00075),
+            create_instrument(Exchange::Binance, "SOL", "USDT", 0.001),
+            create_instrument(Exchange::Mexc, "BTC", "USDT", 0.001),
+        ]);
+        assert!(update.is_empty());
+        assert_eq!(1, registry.version());
+    }
+
+    #[test]
+    fn test_instrument_ids() {
+        let registry = InstrumentsRegistry::new("", InstrumentsMap::from_instruments(vec![
+            create_instrument(Exchange::Binance, "BTC", "USDT", 0.001),
+            create_instrument(Exchange::Mexc, "BTC", "USDT", 0.001),
+        ]));
+        let binance = registry.get(&Exchange::Binance, "BTCUSDT").unwrap();
+        let mexc = registry.get(&Exchange::Mexc, "BTCUSDT").unwrap();
+        assert_ne!(binance.id, mexc.id);
+        assert_ne!(binance, mexc);
+        assert_eq!(2, HashSet::from([Arc::clone(&binance), Arc::clone(&mexc)]).len());
+        assert_eq!(binance.id, registry.get(&Exchange::Binance, "btc/usdt").unwrap().id);
+
+        // known instruments keep the id, removed ids aren't reused
+        registry.swap(vec![
+            create_instrument(Exchange::Binance, "ETH", "USDT", 0.001),
+            create_instrument(Exchange::Mexc, "BTC", "USDT", 0.0005),
+        ]);
+        assert_eq!(mexc.id, registry.get(&Exchange::Mexc, "BTCUSDT").unwrap().id);
+        let eth_usdt = registry.get(&Exchange::Binance, "ETHUSDT").unwrap();
+        assert!(eth_usdt.id != binance.id && eth_usdt.id != mexc.id);
+    }
+
+    #[test]
+    fn test_symbols() {
+        let okx_btc_usdt = Instrument { symbol: "BTC-USDT".to_string(), ..common::create_instrument(0, Exchange::Okx, "BTC", "USDT") };
+        let map = InstrumentsMap::from_instruments(vec![
+            okx_btc_usdt,
+            create_instrument(Exchange::Binance, "BTC", "USDT", 0.001),
+        ]);
+        // the aliases aren't subscribed
+        assert_eq!(5, map.map[&Exchange::Okx].len());
+        assert_eq!(vec!["BTC-USDT"], map.symbols(&Exchange::Okx));
+        let shards = Shards::new(Exchange::Okx, map.symbols(&Exchange::Okx), 30);
+        assert_eq!(1, shards.get(0).channels.len());
+        assert!(map.symbols(&Exchange::Kraken).is_empty());
+    }
+
+    #[test]
+    fn test_reload() {
+        let path = std::env::temp_dir().join(format!("spot_insts_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, r#"[{"exchange": "binance", "symbol": "ETHUSDT", "base": "ETH", "quote": "USDT",
+            "amount_precision": 4, "price_precision": 2, "order_amount_max": 9000, "order_amount_min": 0.0001,
+            "order_notional_min": 5, "order_notional_max": 9000000, "maker_fee": 0.001, "taker_fee": 0.001}]"#).unwrap();
+        let registry = InstrumentsRegistry::from_json(path);
+        assert!(registry.get(&Exchange::Binance, "ETH/USDT").is_some());
+
+        fs::write(path, "[{").unwrap();
+        assert!(registry.reload().is_err());
+        fs::write(path, r#"[{"exchange": "binance", "symbol": "ETHUSDT"}]"#).unwrap();
+        assert!(registry.reload().is_err());
+        assert_eq!(0, registry.version());
+
+        fs::write(path, "[]").unwrap();
+        let update = registry.reload().unwrap();
+        assert_eq!(1, update.removed.len());
+        assert!(registry.get(&Exchange::Binance, "ETHUSDT").is_none());
+        fs::remove_file(path).unwrap();
+    }
+}
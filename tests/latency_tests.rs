@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    use untitled::core::dto::Exchange;
+    use untitled::core::latency::{Histogram, LatencyMonitor, Stage};
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut histogram = Histogram::new();
+        assert_eq!((0, 0, 0), (histogram.percentile(50.0), histogram.min(), histogram.max()));
+        for value in 1..=1000 {
+            histogram.record(value * 1000);
+        }
+        assert_eq!(1000, histogram.count());
+        assert_eq!((1000, 1_000_000), (histogram.min(), histogram.max()));
+        // within the bucket error
+        let assert_near = |expected: u64, actual: u64| {
+            assert!(actual >= expected && actual as f64 <= expected as f64 * 1.07, "{actual} isn't near {expected}");
+        };
+        assert_near(500_000, histogram.percentile(50.0));
+        assert_near(990_000, histogram.percentile(99.0));
+        assert_eq!(1_000_000, histogram.percentile(100.0));
+
+        // small values are exact
+        let mut histogram = Histogram::new();
+        for value in [3, 3, 7, 31] {
+            histogram.record(value);
+        }
+        assert_eq!((3, 7, 31), (histogram.percentile(50.0), histogram.percentile(75.0), histogram.percentile(100.0)));
+        histogram.record(u64::MAX);
+        assert_eq!(u64::MAX, histogram.percentile(100.0));
+    }
+
+    #[test]
+    fn test_monitor() {
+        let monitor = LatencyMonitor::default();
+        monitor.record(&Exchange::Binance, Stage::QueuePop, 1_000, 6_000);
+        monitor.record(&Exchange::Binance, Stage::QueuePop, 1_000, 3_000);
+        // no exchange time
+        monitor.record(&Exchange::Binance, Stage::SocketRead, 0, 3_000);
+        monitor.record(&Exchange::Mexc, Stage::Decision, 1_000, 500);
+
+        let histograms = monitor.snapshot(true);
+        assert_eq!(1, histograms.len());
+        let histogram = &histograms[&(Exchange::Binance, Stage::QueuePop)];
+        assert_eq!((2, 2_000, 5_000), (histogram.count(), histogram.min(), histogram.max()));
+        assert!(monitor.snapshot(false).is_empty());
+    }
+}
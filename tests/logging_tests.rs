@@ -0,0 +1,60 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use json::object;
+    use untitled::core::dto::{Order, OrderStatus};
+    use untitled::core::logging::{format_event, format_json_record, order_fields, LogFormat, EVENT_TARGET};
+    use crate::common::dec;
+
+    #[test]
+    fn test_format_event() {
+        let fields = object! { exchange: "binance", path: ["USDT", "BTC", "ETH", "USDT"], expected_profit_pct: 0.25 };
+
+        assert_eq!(
+            "arb_found exchange=binance path=[\"USDT\",\"BTC\",\"ETH\",\"USDT\"] expected_profit_pct=0.25",
+            format_event(LogFormat::Text, "arb_found", &fields),
+        );
+        let data = json::parse(&format_event(LogFormat::Json, "arb_found", &fields)).unwrap();
+        assert_eq!("arb_found", data["event"].as_str().unwrap());
+        assert_eq!(4, data["path"].len());
+        assert_eq!(Some(0.25), data["expected_profit_pct"].as_f64());
+    }
+
+    #[test]
+    fn test_format_json_record() {
+        let line = format_json_record(1_700_000_000_000, "INFO", "main", "untitled::core::oms", "OMS connected");
+        let data = json::parse(&line).unwrap();
+        assert_eq!(Some(1_700_000_000_000), data["ts"].as_u64());
+        assert_eq!("INFO", data["level"].as_str().unwrap());
+        assert_eq!("main", data["thread"].as_str().unwrap());
+        assert_eq!("untitled::core::oms", data["target"].as_str().unwrap());
+        assert_eq!("OMS connected", data["message"].as_str().unwrap());
+
+        // the event fields are top level
+        let message = format_event(LogFormat::Json, "stream_reconnect", &object! { exchange: "okx", delay_s: 5 });
+        let data = json::parse(&format_json_record(1, "WARN", "okx", EVENT_TARGET, &message)).unwrap();
+        assert_eq!("stream_reconnect", data["event"].as_str().unwrap());
+        assert_eq!(Some(5), data["delay_s"].as_u64());
+        assert_eq!("WARN", data["level"].as_str().unwrap());
+        assert!(data["message"].is_null());
+    }
+
+    #[test]
+    fn test_order_fields() {
+        let mut order = Order::new();
+        order.timestamp = 5;
+        order.client_order_id = "1".to_string();
+        order.status = OrderStatus::Filled;
+        order.amount = dec("0.015");
+        order.amount_filled = dec("0.015");
+        order.fees = vec![("BNB".to_string(), dec("0.00001"))];
+
+        let data = order_fields(&order);
+        assert_eq!("Filled", data["status"].as_str().unwrap());
+        assert_eq!("Buy", data["side"].as_str().unwrap());
+        assert_eq!(Some(0.015), data["amount_filled"].as_f64());
+        assert_eq!("BNB", data["fees"][0]["currency"].as_str().unwrap());
+        assert_eq!(Some(5), data["timestamp"].as_u64());
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use untitled::core::metrics::{Metrics, METRICS};
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        let binance = metrics.counter("tickers_total", "Tickers", &[("exchange", "binance"), ("socket", "0")]);
+        binance.inc();
+        binance.add(2);
+        // the same series
+        metrics.counter("tickers_total", "Tickers", &[("exchange", "binance"), ("socket", "0")]).inc();
+        metrics.counter("tickers_total", "Tickers", &[("exchange", "mexc"), ("socket", "0")]);
+        metrics.gauge("pnl", "PnL", &[("strategy", "arb \"x\"")]).set(-1.5);
+        metrics.gauge_fn("depth", "Depth", &[], || 7.0);
+        metrics.gauge("up", "Up", &[]).set(f64::NAN);
+
+        assert_eq!(4, binance.get());
+        assert_eq!(
+            "# HELP depth Depth\n\
+             # TYPE depth gauge\n\
+             depth 7\n\
+             # HELP pnl PnL\n\
+             # TYPE pnl gauge\n\
+             pnl{strategy=\"arb \\\"x\\\"\"} -1.5\n\
+             # HELP tickers_total Tickers\n\
+             # TYPE tickers_total counter\n\
+             tickers_total{exchange=\"binance\",socket=\"0\"} 4\n\
+             tickers_total{exchange=\"mexc\",socket=\"0\"} 0\n\
+             # HELP up Up\n\
+             # TYPE up gauge\n\
+             up NaN\n",
+            metrics.render(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Metric tickers_total is registered as Counter")]
+    fn test_kind_mismatch() {
+        let metrics = Metrics::default();
+        metrics.counter("tickers_total", "Tickers", &[]);
+        metrics.gauge("tickers_total", "Tickers", &[]);
+    }
+
+    fn get(address: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve() {
+        METRICS.counter("test_serve_total", "Serve test", &[]).inc();
+        let address = METRICS.serve("127.0.0.1:0").to_string();
+
+        let response = get(&address, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with("test_serve_total 1\n"), "{response}");
+        assert!(get(&address, "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
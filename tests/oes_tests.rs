@@ -1,3 +1,5 @@
+mod common;
+
 // use std::collections::HashMap;
 // use std::rc::Rc;
 // use std::sync::Arc;
@@ -259,3 +261,92 @@
 //
 //     assert_eq!(simulator.balances.get("USD").unwrap(), &49875.31172069826);
 // }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use untitled::core::dto::{Exchange, Instrument, InstrumentId, Order, OrderSide, OrderStatus, OrderType, PriceTicker, TimeInForce};
+    use untitled::core::fees::FeeModel;
+    use untitled::core::oes::OrderExecutionSimulator;
+    use crate::common::{create_instrument, create_ticker, dec};
+
+    fn setup_instrument_and_tickers() -> (HashMap<InstrumentId, PriceTicker>, Arc<Instrument>) {
+        let btc_usdt = Arc::new(Instrument {
+            amount_precision: 5,
+            order_amount_min: dec("0.00001"),
+            maker_fee: 0.0,
+            taker_fee: 0.0,
+            ..create_instrument(1, Exchange::Binance, "BTC", "USDT")
+        });
+
+        let mut tickers = HashMap::new();
+        tickers.insert(btc_usdt.id, create_ticker(&btc_usdt, "40000", "40100"));
+        (tickers, btc_usdt)
+    }
+
+    fn create_limit_order(instrument: &Arc<Instrument>, order_type: OrderType, time_in_force: TimeInForce, side: OrderSide, price: &str) -> Order {
+        let mut order = Order::new();
+        order.instrument = Arc::clone(instrument);
+        order.client_order_id = "dummy".to_string();
+        order.order_type = order_type;
+        order.time_in_force = time_in_force;
+        order.side = side;
+        order.price = dec(price);
+        order.amount = dec("0.1");
+        order
+    }
+
+    #[test]
+    fn test_resting_limit_order_fills_when_crossed() {
+        let (tickers, btc_usdt) = setup_instrument_and_tickers();
+        let mut balances = HashMap::new();
+        balances.insert("USDT".to_string(), dec("5000"));
+        let mut simulator = OrderExecutionSimulator::new(balances, FeeModel::new());
+
+        let order = create_limit_order(&btc_usdt, OrderType::Limit, TimeInForce::GTC, OrderSide::Buy, "39000");
+        let executed = simulator.execute_limit_orders(vec![order], &tickers);
+        assert_eq!(OrderStatus::New, executed[0].status);
+        assert_eq!(1, simulator.open_orders.len());
+
+        assert!(simulator.on_price_ticker(&create_ticker(&btc_usdt, "39500", "39600")).is_empty());
+
+        let filled = simulator.on_price_ticker(&create_ticker(&btc_usdt, "38900", "38950"));
+        assert_eq!(OrderStatus::Filled, filled[0].status);
+        assert_eq!(dec("3900"), filled[0].amount_quote);
+        assert_eq!(&dec("1100"), simulator.balances.get("USDT").unwrap());
+        assert!(simulator.open_orders.is_empty());
+    }
+
+    #[test]
+    fn test_marketable_limit_orders() {
+        let (tickers, btc_usdt) = setup_instrument_and_tickers();
+        let mut balances = HashMap::new();
+        balances.insert("USDT".to_string(), dec("5000"));
+        let mut simulator = OrderExecutionSimulator::new(balances, FeeModel::new());
+
+        let executed = simulator.execute_limit_orders(vec![
+            create_limit_order(&btc_usdt, OrderType::LimitMaker, TimeInForce::GTC, OrderSide::Buy, "40200"),
+            create_limit_order(&btc_usdt, OrderType::Limit, TimeInForce::IOC, OrderSide::Buy, "39000"),
+            create_limit_order(&btc_usdt, OrderType::Limit, TimeInForce::FOK, OrderSide::Buy, "40200"),
+        ], &tickers);
+
+        assert_eq!(OrderStatus::Error, executed[0].status);
+        assert_eq!(OrderStatus::Canceled, executed[1].status);
+        assert_eq!(OrderStatus::Filled, executed[2].status);
+        // filled by the best ask, not the limit price
+        assert_eq!(dec("4010"), executed[2].amount_quote);
+        assert!(simulator.open_orders.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_resting_order() {
+        let (tickers, btc_usdt) = setup_instrument_and_tickers();
+        let mut simulator = OrderExecutionSimulator::new(HashMap::new(), FeeModel::new());
+
+        let order = create_limit_order(&btc_usdt, OrderType::LimitMaker, TimeInForce::GTC, OrderSide::Sell, "41000");
+        simulator.execute_limit_orders(vec![order], &tickers);
+        assert_eq!(OrderStatus::Canceled, simulator.cancel_order("dummy").unwrap().status);
+        assert!(simulator.cancel_order("dummy").is_none());
+    }
+}
@@ -0,0 +1,78 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::Arc;
+    use ed25519_dalek::SigningKey;
+    use fefix::definitions::fix44;
+    use fefix::field_types::Timestamp;
+    use fefix::tagvalue::{Decoder, Encoder};
+    use fefix::{Dictionary, FieldMap, SetField};
+    use untitled::core::dto::{Exchange, Order, OrderStatus};
+    use untitled::core::map::{InstrumentsMap, InstrumentsRegistry};
+    use untitled::core::oms::{BinanceFixConnection, FixMessageEncoderHandler};
+    use crate::common::create_instrument;
+
+    fn create_decoder() -> Decoder {
+        let spec = fs::read_to_string("./data/binance-spot-fix-oe.xml").unwrap();
+        Decoder::new(Dictionary::from_quickfix_spec(&spec).unwrap())
+    }
+
+    fn create_canceled_report(cl_ord_id: Option<&str>, orig_cl_ord_id: Option<&str>) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let mut encoder = Encoder::default();
+        let mut msg = encoder.start_message(b"FIX.4.4", &mut buffer, b"8");
+        msg.set(fix44::MSG_SEQ_NUM, 2);
+        msg.set(fix44::SENDER_COMP_ID, "SPOT");
+        msg.set(fix44::SENDING_TIME, Timestamp::utc_now());
+        msg.set(fix44::TARGET_COMP_ID, "EXAMPLE2");
+        if let Some(cl_ord_id) = cl_ord_id {
+            msg.set(fix44::CL_ORD_ID, cl_ord_id);
+        }
+        if let Some(orig_cl_ord_id) = orig_cl_ord_id {
+            msg.set(fix44::ORIG_CL_ORD_ID, orig_cl_ord_id);
+        }
+        msg.set(fix44::ORDER_ID, "11057589");
+        msg.set(fix44::ORDER_QTY, "0.00100000");
+        msg.set(fix44::ORD_TYPE, fix44::OrdType::Limit);
+        msg.set(fix44::PRICE, "60000.00000000");
+        msg.set(fix44::SIDE, fix44::Side::Buy);
+        msg.set(fix44::SYMBOL, "BTCUSDT");
+        msg.set(fix44::TRANSACT_TIME, "20241023-20:52:04.021000");
+        msg.set(fix44::ORD_STATUS, fix44::OrdStatus::Canceled);
+        msg.set(fix44::CUM_QTY, "0.00000000");
+        msg.done().0.to_vec()
+    }
+
+    #[test]
+    fn test_cancel_report() {
+        let registry = Arc::new(InstrumentsRegistry::new("", InstrumentsMap::from_instruments(vec![
+            create_instrument(0, Exchange::Binance, "BTC", "USDT"),
+        ])));
+        let mut order = Order::new();
+        order.instrument = registry.get(&Exchange::Binance, "BTCUSDT").unwrap();
+        order.client_order_id = "original".to_string();
+
+        let mut encoder = FixMessageEncoderHandler::new(SigningKey::from_bytes(&[1; 32]), "api_key");
+        let mut decoder = create_decoder();
+        let cancel = encoder.create_cancel_message(&order).to_vec();
+        let cancel = decoder.decode(&cancel).unwrap();
+        let cancel_id = String::from_utf8_lossy(cancel.get::<&[u8]>(fix44::CL_ORD_ID).unwrap()).to_string();
+        assert_ne!("original", cancel_id);
+        assert_eq!("original".as_bytes(), cancel.get::<&[u8]>(fix44::ORIG_CL_ORD_ID).unwrap());
+
+        // the venue reports the cancel under the request id
+        let report = create_canceled_report(Some(&cancel_id), Some("original"));
+        let canceled = BinanceFixConnection::execution_report_to_order(decoder.decode(&report).unwrap(), &registry).unwrap();
+        assert_eq!(("original", OrderStatus::Canceled), (canceled.client_order_id.as_str(), canceled.status));
+
+        let report = create_canceled_report(Some("original"), None);
+        let canceled = BinanceFixConnection::execution_report_to_order(decoder.decode(&report).unwrap(), &registry).unwrap();
+        assert_eq!("original", canceled.client_order_id);
+
+        // dropped instead of the panic
+        let report = create_canceled_report(None, None);
+        assert!(BinanceFixConnection::execution_report_to_order(decoder.decode(&report).unwrap(), &registry).is_none());
+    }
+}
@@ -0,0 +1,71 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use untitled::core::decimal::Decimal;
+    use untitled::core::dto::{Exchange, OrderSide, OrderType, TimeInForce};
+    use untitled::core::order_builder::{OrderBuilder, OrderValidationError};
+    use crate::common::{create_instrument, dec};
+
+    #[test]
+    fn test_build_limit_order() {
+        let eth_usdt = Arc::new(create_instrument(1, Exchange::Binance, "ETH", "USDT"));
+        let order = OrderBuilder::new(&eth_usdt, OrderSide::Buy, OrderType::Limit)
+            .amount(dec("0.012345"))
+            .price(dec("2500.129"))
+            .time_in_force(TimeInForce::IOC)
+            .strategy_id("arb_binance")
+            .build()
+            .unwrap();
+
+        assert_eq!(dec("0.0123"), order.amount);
+        assert_eq!(dec("2500.12"), order.price);
+        assert_eq!(TimeInForce::IOC, order.time_in_force);
+        assert_eq!("arb_binance", order.strategy_id);
+        assert_eq!(36, order.client_order_id.len());
+
+        let order = OrderBuilder::new(&eth_usdt, OrderSide::Sell, OrderType::LimitMaker)
+            .amount_quote(dec("30"))
+            .price(dec("2500.121"))
+            .build()
+            .unwrap();
+        assert_eq!(dec("2500.13"), order.price);
+        assert_eq!(dec("0.0119"), order.amount);
+    }
+
+    #[test]
+    fn test_build_market_order() {
+        let eth_usdt = Arc::new(create_instrument(1, Exchange::Binance, "ETH", "USDT"));
+        let order = OrderBuilder::new(&eth_usdt, OrderSide::Buy, OrderType::Market)
+            .amount_quote(dec("20.555"))
+            .reference_price(dec("2500"))
+            .build()
+            .unwrap();
+        assert_eq!(Decimal::ZERO, order.amount);
+        assert_eq!(dec("20.55"), order.amount_quote);
+        assert_eq!(Decimal::ZERO, order.price);
+
+        assert_eq!(
+            Err(OrderValidationError::MissingPrice),
+            OrderBuilder::new(&eth_usdt, OrderSide::Buy, OrderType::Market).amount(dec("0.01")).build().map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let eth_usdt = Arc::new(create_instrument(1, Exchange::Binance, "ETH", "USDT"));
+        assert_eq!(
+            Err(OrderValidationError::MissingSize),
+            OrderBuilder::new(&eth_usdt, OrderSide::Buy, OrderType::Limit).price(dec("2500")).build().map(|_| ())
+        );
+        assert_eq!(
+            Err(OrderValidationError::AmountTooSmall { amount: Decimal::ZERO, min: dec("0.0001") }),
+            OrderBuilder::new(&eth_usdt, OrderSide::Buy, OrderType::Limit).amount(dec("0.00001")).price(dec("2500")).build().map(|_| ())
+        );
+        assert_eq!(
+            Err(OrderValidationError::NotionalTooSmall { notional: dec("2.5"), min: dec("5") }),
+            OrderBuilder::new(&eth_usdt, OrderSide::Sell, OrderType::Market).amount(dec("0.001")).reference_price(dec("2500")).build().map(|_| ())
+        );
+    }
+}
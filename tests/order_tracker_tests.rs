@@ -0,0 +1,127 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use untitled::core::decimal::Decimal;
+    use untitled::core::dto::{Exchange, Instrument, InstrumentId, Order, OrderSide, OrderStatus};
+    use untitled::core::order_tracker::{OrderTracker, OrderUpdateError};
+    use untitled::core::utils::time;
+    use crate::common::{create_instrument, dec};
+
+    fn create_report(
+        instrument: &Arc<Instrument>,
+        timestamp: u128,
+        status: OrderStatus,
+        amount_filled: &str,
+        fees: Vec<(String, Decimal)>,
+    ) -> Order {
+        let mut order = Order::new();
+        order.instrument = Arc::clone(instrument);
+        order.client_order_id = "dummy".to_string();
+        order.side = OrderSide::Buy;
+        order.timestamp = timestamp;
+        order.status = status;
+        order.amount = dec("0.002");
+        order.amount_filled = dec(amount_filled);
+        order.fees = fees;
+        order
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let instrument = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let mut tracker = OrderTracker::new();
+
+        let mut order = create_report(&instrument, 0, OrderStatus::Scheduled, "0", vec![]);
+        tracker.register(&order).unwrap();
+        assert_eq!(Err(OrderUpdateError::AlreadyRegistered), tracker.register(&order));
+        assert_eq!(1, tracker.open_orders().count());
+
+        order = create_report(&instrument, 1, OrderStatus::New, "0", vec![]);
+        assert_eq!(OrderStatus::New, tracker.update(&order).unwrap().status);
+
+        order = create_report(&instrument, 2, OrderStatus::PartiallyFilled, "0.001", vec![("BNB".to_string(), dec("0.1"))]);
+        tracker.update(&order).unwrap();
+
+        order = create_report(&instrument, 3, OrderStatus::Filled, "0.002", vec![("BNB".to_string(), dec("0.2"))]);
+        let tracked = tracker.update(&order).unwrap();
+        assert_eq!(OrderStatus::Filled, tracked.status);
+        assert_eq!(dec("0.002"), tracked.amount_filled);
+        assert_eq!(vec![("BNB".to_string(), dec("0.3"))], tracked.fees);
+
+        assert_eq!(0, tracker.open_orders().count());
+        tracker.remove_closed();
+        assert!(tracker.get("dummy").is_none());
+    }
+
+    #[test]
+    fn test_duplicate_and_out_of_order() {
+        let instrument = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let mut tracker = OrderTracker::new();
+
+        let partially_filled = create_report(&instrument, 2, OrderStatus::PartiallyFilled, "0.001", vec![]);
+        tracker.update(&partially_filled).unwrap();
+        assert_eq!(Err(OrderUpdateError::Duplicate), tracker.update(&partially_filled).map(|_| ()));
+
+        let new = create_report(&instrument, 1, OrderStatus::New, "0", vec![]);
+        assert_eq!(
+            Err(OrderUpdateError::OutOfOrder { from: OrderStatus::PartiallyFilled, to: OrderStatus::New }),
+            tracker.update(&new).map(|_| ())
+        );
+
+        let filled = create_report(&instrument, 3, OrderStatus::Filled, "0.002", vec![]);
+        tracker.update(&filled).unwrap();
+        let canceled = create_report(&instrument, 4, OrderStatus::Canceled, "0.002", vec![]);
+        assert!(tracker.update(&canceled).is_err());
+    }
+
+    #[test]
+    fn test_open_orders_by_exchange_and_instrument() {
+        let mut tracker = OrderTracker::new();
+        let mut order = create_report(&Arc::new(create_instrument(2, Exchange::Mexc, "BTC", "USDT")), 1, OrderStatus::New, "0", vec![]);
+        tracker.update(&order).unwrap();
+        order.client_order_id = "another".to_string();
+        order.instrument = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        tracker.update(&order).unwrap();
+
+        assert_eq!(2, tracker.open_orders().count());
+        assert_eq!(1, tracker.open_orders_by_exchange(&Exchange::Mexc).count());
+        assert_eq!("dummy", tracker.open_orders_by_exchange(&Exchange::Mexc).next().unwrap().client_order_id);
+        assert_eq!("another", tracker.open_orders_by_instrument(InstrumentId(1)).next().unwrap().client_order_id);
+        assert_eq!(0, tracker.open_orders_by_instrument(InstrumentId(3)).count());
+    }
+
+    #[test]
+    fn test_exchange_clock_behind() {
+        let instrument = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let mut tracker = OrderTracker::new();
+        // registered with the local time, the exchange clock is behind
+        tracker.register(&create_report(&instrument, 1_000, OrderStatus::Scheduled, "0", vec![])).unwrap();
+        assert_eq!(OrderStatus::New, tracker.update(&create_report(&instrument, 900, OrderStatus::New, "0", vec![])).unwrap().status);
+        tracker.update(&create_report(&instrument, 950, OrderStatus::PartiallyFilled, "0.001", vec![])).unwrap();
+        assert_eq!(
+            Err(OrderUpdateError::OutOfOrder { from: OrderStatus::PartiallyFilled, to: OrderStatus::PartiallyFilled }),
+            tracker.update(&create_report(&instrument, 960, OrderStatus::PartiallyFilled, "0.0005", vec![])).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_prune_closed() {
+        let instrument = Arc::new(create_instrument(1, Exchange::Binance, "BTC", "USDT"));
+        let mut tracker = OrderTracker::new();
+        tracker.update(&create_report(&instrument, 1, OrderStatus::Filled, "0.002", vec![])).unwrap();
+        let mut open = create_report(&instrument, 1, OrderStatus::New, "0", vec![]);
+        open.client_order_id = "open".to_string();
+        tracker.update(&open).unwrap();
+        assert_eq!(vec!["open"], tracker.open_orders().map(|o| o.client_order_id.as_str()).collect::<Vec<_>>());
+
+        // the closed order is kept for the retention
+        tracker.prune(time());
+        assert!(tracker.get("dummy").is_some());
+        tracker.prune(time() + Duration::from_secs(24 * 60 * 60).as_nanos());
+        assert!(tracker.get("dummy").is_none());
+        assert!(tracker.get("open").is_some());
+    }
+}
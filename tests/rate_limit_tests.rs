@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use untitled::core::rate_limit::{RateLimitType, RateLimiter};
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(Some(Duration::from_secs(10)), RateLimiter::parse_interval(10, "s"));
+        assert_eq!(Some(Duration::from_secs(86400)), RateLimiter::parse_interval(1, "d"));
+        assert_eq!(None, RateLimiter::parse_interval(1, "y"));
+    }
+
+    #[test]
+    fn test_try_acquire() {
+        let second = Duration::from_secs(1).as_nanos();
+        let mut limiter = RateLimiter::new();
+        // unknown limits don't block
+        assert!(limiter.try_acquire(&[RateLimitType::Order], 0).is_ok());
+
+        // the window of 10s started at 20s, 8 orders are already counted by the exchange
+        limiter.update(RateLimitType::Order, 8, 10, Duration::from_secs(10), 25 * second);
+        limiter.update(RateLimitType::Message, 0, 1000, Duration::from_secs(60), 25 * second);
+
+        assert!(limiter.try_acquire(&[RateLimitType::Order, RateLimitType::Message], 25 * second).is_ok());
+        assert!(limiter.try_acquire(&[RateLimitType::Order, RateLimitType::Message], 25 * second).is_ok());
+        assert_eq!(
+            Err(Duration::from_secs(5)),
+            limiter.try_acquire(&[RateLimitType::Order, RateLimitType::Message], 25 * second)
+        );
+        // message limit is not exhausted
+        assert!(limiter.try_acquire(&[RateLimitType::Message], 25 * second).is_ok());
+
+        // nothing is refilled within the window
+        assert_eq!(Err(Duration::from_secs(1)), limiter.try_acquire(&[RateLimitType::Order], 29 * second));
+        // the next window has the full limit, once
+        for _ in 0..10 {
+            assert!(limiter.try_acquire(&[RateLimitType::Order], 30 * second).is_ok());
+        }
+        assert!(limiter.try_acquire(&[RateLimitType::Order], 39 * second).is_err());
+
+        // reseed from the exchange counters
+        limiter.update(RateLimitType::Order, 0, 10, Duration::from_secs(10), 39 * second);
+        assert!(limiter.try_acquire(&[RateLimitType::Order], 39 * second).is_ok());
+    }
+}
@@ -0,0 +1,152 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering;
+    use untitled::core::decimal::Decimal;
+    use untitled::core::api::OrderListener;
+    use untitled::core::dto::{Exchange, Instrument, InstrumentId, Order, OrderSide, OrderStatus, OrderType, PriceTicker};
+    use untitled::core::map::{InstrumentsMap, InstrumentsRegistry};
+    use untitled::core::risk::{RiskConfig, RiskGate};
+    use untitled::core::utils::time;
+    use crate::common::{create_instrument, create_ticker, dec};
+
+    fn create_tickers_map(tickers: Vec<PriceTicker>) -> HashMap<Exchange, HashMap<InstrumentId, PriceTicker>> {
+        let tickers = tickers.into_iter().map(|ticker| (ticker.instrument.id, ticker)).collect();
+        HashMap::from([(Exchange::Binance, tickers)])
+    }
+
+    // the registry ids follow the order of the instruments
+    fn create_gate(instruments: &[&Arc<Instrument>], max_open_orders: usize) -> RiskGate {
+        let map = InstrumentsMap::from_instruments(instruments.iter().map(|i| (***i).clone()).collect());
+        let registry = Arc::new(InstrumentsRegistry::new("", map));
+        RiskGate::new(RiskConfig::new("USDT".to_string(), dec("100"), dec("5"), max_open_orders, 0.05), registry)
+    }
+
+    fn create_order(instrument: &Arc<Instrument>, side: OrderSide, amount: &str) -> Order {
+        let mut order = Order::new();
+        order.instrument = Arc::clone(instrument);
+        order.client_order_id = "dummy".to_string();
+        order.strategy_id = "arb_binance".to_string();
+        order.side = side;
+        order.amount = dec(amount);
+        order
+    }
+
+    #[test]
+    fn test_filters_and_limits() {
+        let eth_usdt = Arc::new(create_instrument(1, Exchange::Binance, "ETH", "USDT"));
+        let tickers_map = create_tickers_map(vec![create_ticker(&eth_usdt, "2500", "2501")]);
+        let mut gate = create_gate(&[&eth_usdt], 10);
+
+        assert!(gate.check(&create_order(&eth_usdt, OrderSide::Buy, "0.01"), &tickers_map).is_ok());
+        // notional filter
+        assert!(gate.check(&create_order(&eth_usdt, OrderSide::Buy, "0.001"), &tickers_map).is_err());
+        // max notional
+        assert!(gate.check(&create_order(&eth_usdt, OrderSide::Buy, "0.1"), &tickers_map).is_err());
+
+        let mut limit = create_order(&eth_usdt, OrderSide::Sell, "0.01");
+        limit.order_type = OrderType::Limit;
+        limit.price = dec("2600");
+        assert!(gate.check(&limit, &tickers_map).is_ok());
+        limit.price = dec("2700");
+        assert!(gate.check(&limit, &tickers_map).is_err());
+
+        gate.kill_switch().store(true, Ordering::Relaxed);
+        assert_eq!(
+            Err("Kill switch is active".to_string()),
+            gate.check(&create_order(&eth_usdt, OrderSide::Buy, "0.01"), &tickers_map)
+        );
+    }
+
+    #[test]
+    fn test_zero_prices() {
+        // the empty sides of the book
+        let eth_usdt = Arc::new(create_instrument(1, Exchange::Binance, "ETH", "USDT"));
+        let btc_usdt = Arc::new(create_instrument(2, Exchange::Binance, "BTC", "USDT"));
+        let eth_btc = Arc::new(Instrument { order_notional_min: dec("0.0001"), ..create_instrument(3, Exchange::Binance, "ETH", "BTC") });
+        let tickers_map = create_tickers_map(vec![
+            create_ticker(&eth_usdt, "2500", "0"),
+            create_ticker(&btc_usdt, "0", "0"),
+            create_ticker(&eth_btc, "0.05", "0.0501"),
+        ]);
+        let mut gate = create_gate(&[&eth_usdt, &btc_usdt, &eth_btc], 10);
+
+        assert_eq!(
+            Err("No ask price for ETHUSDT".to_string()),
+            gate.check(&create_order(&eth_usdt, OrderSide::Buy, "0.01"), &tickers_map)
+        );
+        let mut by_quote = create_order(&eth_usdt, OrderSide::Buy, "0");
+        by_quote.amount_quote = dec("30");
+        assert!(gate.check(&by_quote, &tickers_map).is_err());
+        assert!(gate.check(&create_order(&eth_usdt, OrderSide::Sell, "0.01"), &tickers_map).is_ok());
+        assert_eq!(
+            Err("Can't value BTC in USDT".to_string()),
+            gate.check(&create_order(&eth_btc, OrderSide::Buy, "0.01"), &tickers_map)
+        );
+    }
+
+    #[test]
+    fn test_open_orders_and_daily_loss() {
+        let eth_usdt = Arc::new(create_instrument(1, Exchange::Binance, "ETH", "USDT"));
+        let tickers_map = create_tickers_map(vec![create_ticker(&eth_usdt, "2000", "2001")]);
+        let mut gate = create_gate(&[&eth_usdt], 1);
+
+        // bought 0.02 ETH by 2500, now it costs 2000: -10 USDT
+        let mut filled = create_order(&eth_usdt, OrderSide::Buy, "0.02");
+        filled.client_order_id = "filled".to_string();
+        filled.timestamp = time();
+        filled.status = OrderStatus::Filled;
+        filled.amount_filled = dec("0.02");
+        filled.amount_quote = dec("50");
+        gate.on_order(&filled);
+        // the repeated report changes nothing
+        gate.on_order(&filled);
+
+        assert_eq!(dec("-10"), gate.daily_pnl("arb_binance", time(), &tickers_map));
+        assert_eq!(Decimal::ZERO, gate.daily_pnl("arb_mexc", time(), &tickers_map));
+        let reason = gate.check(&create_order(&eth_usdt, OrderSide::Sell, "0.01"), &tickers_map).unwrap_err();
+        assert!(reason.starts_with("Daily loss limit reached"));
+
+        let mut gate = create_gate(&[&eth_usdt], 1);
+        let mut open = create_order(&eth_usdt, OrderSide::Sell, "0.01");
+        open.client_order_id = "open".to_string();
+        assert!(gate.check(&open, &tickers_map).is_ok());
+        let reason = gate.check(&create_order(&eth_usdt, OrderSide::Sell, "0.01"), &tickers_map).unwrap_err();
+        assert!(reason.starts_with("Open orders limit reached"));
+        open.status = OrderStatus::Canceled;
+        gate.on_order(&open);
+        assert!(gate.check(&create_order(&eth_usdt, OrderSide::Sell, "0.01"), &tickers_map).is_ok());
+    }
+
+    #[test]
+    fn test_partial_fills_and_fees() {
+        let eth_usdt = Arc::new(create_instrument(1, Exchange::Binance, "ETH", "USDT"));
+        let bnb_usdt = Arc::new(create_instrument(2, Exchange::Binance, "BNB", "USDT"));
+        let tickers_map = create_tickers_map(vec![create_ticker(&eth_usdt, "2500", "2501"), create_ticker(&bnb_usdt, "500", "501")]);
+        let mut gate = create_gate(&[&eth_usdt, &bnb_usdt], 10);
+
+        let mut order = create_order(&eth_usdt, OrderSide::Sell, "0.02");
+        order.timestamp = time();
+        order.status = OrderStatus::PartiallyFilled;
+        order.amount_filled = dec("0.01");
+        order.amount_quote = dec("26");
+        order.fees = vec![("BNB".to_string(), dec("0.001"))];
+        gate.on_order(&order);
+        // 0.01 ETH sold by 2600, valued by 2500, the fee is 0.5 USDT
+        assert_eq!(dec("0.5"), gate.daily_pnl("arb_binance", time(), &tickers_map));
+
+        // the cumulative fill and fees of the tracked order
+        order.status = OrderStatus::Filled;
+        order.amount_filled = dec("0.02");
+        order.amount_quote = dec("52");
+        order.fees = vec![("BNB".to_string(), dec("0.002"))];
+        gate.on_order(&order);
+        assert_eq!(dec("1"), gate.daily_pnl("arb_binance", time(), &tickers_map));
+        assert_eq!(vec!["arb_binance"], gate.strategies().collect::<Vec<_>>());
+        // the next day starts over
+        assert_eq!(Decimal::ZERO, gate.daily_pnl("arb_binance", time() + 24 * 60 * 60 * 1_000_000_000, &tickers_map));
+    }
+}
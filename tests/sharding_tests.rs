@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use untitled::core::dto::Exchange;
+    use untitled::core::streams::sharding::Shards;
+
+    const MINUTE: u128 = 60_000_000_000;
+
+    fn channels(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("c{i}")).collect()
+    }
+
+    #[test]
+    fn test_initial_split() {
+        let shards = Shards::new(Exchange::Binance, channels(1259), 1024);
+        assert_eq!(2, shards.len());
+        assert_eq!((630, 629), (shards.get(0).channels.len(), shards.get(1).channels.len()));
+
+        let shards = Shards::new(Exchange::Mexc, channels(61), 30);
+        assert_eq!(3, shards.len());
+        assert!((0..3).all(|id| shards.get(id).channels.len() <= 30));
+
+        let shards = Shards::new(Exchange::Mexc, vec![], 30);
+        assert_eq!(1, shards.len());
+    }
+
+    #[test]
+    fn test_rebalance_by_rates() {
+        let shards = Shards::new(Exchange::Okx, channels(4), 2);
+        // c0 and c1 are hot, c2 and c3 are quiet
+        shards.record_messages(0, HashMap::from([("c0".to_string(), 1000), ("c1".to_string(), 900)]));
+        shards.record_messages(1, HashMap::from([("c2".to_string(), 10), ("c3".to_string(), 10)]));
+
+        // both shards are full
+        assert_eq!(0, shards.rebalance(MINUTE));
+        assert_eq!(0, shards.version());
+
+        let shards = Shards::new(Exchange::Okx, channels(4), 3);
+        assert_eq!(vec!["c0", "c1"], shards.get(0).channels);
+        shards.record_messages(0, HashMap::from([("c0".to_string(), 1000), ("c1".to_string(), 900)]));
+        shards.record_messages(1, HashMap::from([("c2".to_string(), 10), ("c3".to_string(), 10)]));
+        assert_eq!(0, shards.rebalance(MINUTE));
+        assert_eq!(1, shards.version());
+        assert_eq!(vec!["c1"], shards.get(0).channels);
+        assert_eq!(vec!["c2", "c3", "c0"], shards.get(1).channels);
+        // the rates are observed per interval
+        assert!(shards.get(1).messages.is_empty());
+        assert_eq!(0, shards.rebalance(2 * MINUTE));
+        assert_eq!(1, shards.version());
+    }
+
+    #[test]
+    fn test_add_channels() {
+        let shards = Shards::new(Exchange::Okx, channels(3), 2);
+        assert_eq!(vec!["c0", "c1"], shards.get(0).channels);
+
+        // the subscribed channels are skipped, the free capacity is taken first
+        assert_eq!(0, shards.add(vec!["c2".to_string(), "c3".to_string()]));
+        assert_eq!(1, shards.version());
+        assert_eq!(vec!["c2", "c3"], shards.get(1).channels);
+
+        assert_eq!(1, shards.add(vec!["c4".to_string()]));
+        assert_eq!(3, shards.len());
+        assert_eq!((2, vec!["c4".to_string()]), shards.channels(2));
+    }
+
+    #[test]
+    fn test_flapping_shard() {
+        let shards = Shards::new(Exchange::Mexc, channels(4), 2);
+        shards.record_disconnect(0, MINUTE);
+        shards.record_disconnect(0, 2 * MINUTE);
+        assert_eq!(0, shards.rebalance(3 * MINUTE));
+        shards.record_disconnect(0, 3 * MINUTE);
+
+        // the other shard is full, the channels go to the new one
+        assert_eq!(1, shards.rebalance(4 * MINUTE));
+        assert_eq!(3, shards.len());
+        assert!(shards.get(0).channels.is_empty());
+        assert_eq!(vec!["c0", "c1"], shards.get(2).channels);
+        let (version, channels) = shards.channels(2);
+        assert_eq!((1, 2), (version, channels.len()));
+
+        // old disconnects are out of the window
+        shards.record_disconnect(1, MINUTE);
+        shards.record_disconnect(1, 2 * MINUTE);
+        shards.record_disconnect(1, 20 * MINUTE);
+        assert_eq!(0, shards.rebalance(20 * MINUTE));
+        assert_eq!(vec!["c2", "c3"], shards.get(1).channels);
+    }
+}
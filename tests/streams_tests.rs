@@ -0,0 +1,296 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use json::object;
+    use untitled::core::decimal::Decimal;
+    use untitled::core::dto::{Exchange, Instrument, InstrumentId, TICKER_PRICE_NOT_CHANGED};
+    use untitled::core::map::InstrumentsMap;
+    use untitled::core::streams::binance::BinanceVenue;
+    use untitled::core::streams::bit2me::Bit2meVenue;
+    use untitled::core::streams::bybit::BybitVenue;
+    use untitled::core::streams::connector::{ChannelIndex, Venue};
+    use untitled::core::streams::kraken::KrakenVenue;
+    use untitled::core::streams::mexc::MexcVenue;
+    use untitled::core::streams::okx::OkxVenue;
+    use untitled::core::streams::sequence::{Sequence, SequenceTracker};
+    use untitled::core::streams::sharding::Shards;
+    use untitled::core::streams::watchdog::{Silence, Watchdog};
+    use crate::common::{self, dec};
+
+    fn create_instruments() -> InstrumentsMap {
+        let create_instrument = |exchange: Exchange, symbol: &str| Instrument {
+            symbol: symbol.to_string(),
+            ..common::create_instrument(0, exchange, "BTC", "USDT")
+        };
+        InstrumentsMap::from_instruments(vec![
+            create_instrument(Exchange::Binance, "BTCUSDT"),
+            create_instrument(Exchange::Mexc, "BTCUSDT"),
+            create_instrument(Exchange::Bit2me, "BTC/USDT"),
+            create_instrument(Exchange::Okx, "BTC-USDT"),
+            create_instrument(Exchange::Bybit, "BTCUSDT"),
+        ])
+    }
+
+    #[test]
+    fn test_binance() {
+        let instruments = create_instruments();
+        let mut venue = BinanceVenue;
+        assert_eq!("btcusdt@bookTicker", BinanceVenue::ticker_to_channel("BTC/USDT"));
+        assert_eq!("btcusdt", BinanceVenue::channel_to_symbol("btcusdt@bookTicker"));
+
+        let ticker = venue.parse(1, r#"{"u":400900217,"s":"BTCUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#, &instruments)
+            .unwrap().unwrap();
+        assert_eq!(Exchange::Binance, ticker.instrument.exchange);
+        assert_eq!(400900217, ticker.update_id);
+        assert_eq!((dec("25.3519"), dec("40.66")), (ticker.bid, ticker.ask_amount));
+        assert!(venue.parse(1, r#"{"s":"ETHUSDT","b":"1"}"#, &instruments).is_err());
+
+        let request = venue.subscribe_message(&["btcusdt@bookTicker".to_string()], 3);
+        assert!(venue.ack(&request, r#"{"result":null,"id":2}"#).is_none());
+        assert!(venue.ack(&request, r#"{"result":null,"id":3}"#).unwrap().is_ok());
+        assert!(venue.ack(&request, r#"{"error":{"code":2,"msg":"Invalid request"},"id":3}"#).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_mexc() {
+        let instruments = create_instruments();
+        let mut venue = MexcVenue;
+        assert_eq!("BTCUSDT", MexcVenue::channel_to_symbol(&MexcVenue::ticker_to_channel("btc/usdt")));
+
+        let raw = r#"{"c":"spot@public.bookTicker.v3.api@BTCUSDT","d":{"A":"","B":"0.5","a":"","b":"69000.1"},"s":"BTCUSDT","t":1}"#;
+        let ticker = venue.parse(1, raw, &instruments).unwrap().unwrap();
+        assert_eq!((Exchange::Mexc, 1_000_000), (ticker.instrument.exchange.clone(), ticker.exchange_ts));
+        assert_eq!((dec("69000.1"), TICKER_PRICE_NOT_CHANGED), (ticker.bid, ticker.ask));
+
+        let request = object! {method: "SUBSCRIPTION"};
+        let pong = r#"{"id":0,"code":0,"msg":"PONG"}"#;
+        assert!(venue.is_pong(pong));
+        assert!(venue.ack(&request, pong).is_none());
+        assert!(venue.ack(&request, r#"{"id":0,"code":0,"msg":"spot@public.bookTicker.v3.api@BTCUSDT"}"#).unwrap().is_ok());
+        assert!(venue.ack(&request, r#"{"id":0,"code":1,"msg":"Not Subscribed successfully!"}"#).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_bit2me() {
+        let instruments = create_instruments();
+        let mut venue = Bit2meVenue;
+        let raw = r#"{"event":"order-book","data":{"symbol":"BTC/USDT","bids":[[64000.5,0.2]],"asks":[],"sequence":42,"timestamp":1712345678901}}"#;
+        let ticker = venue.parse(1, raw, &instruments).unwrap().unwrap();
+        assert_eq!((42, 1_712_345_678_901_000_000), (ticker.update_id, ticker.exchange_ts));
+        assert_eq!((dec("64000.5"), TICKER_PRICE_NOT_CHANGED), (ticker.bid, ticker.ask));
+        assert!(venue.parse(1, r#"{"event":"heartbeat"}"#, &instruments).unwrap().is_none());
+
+        let request = venue.subscribe_message(&["BTC/USDT".to_string()], 0);
+        assert!(venue.ack(&request, raw).is_none());
+        assert!(venue.ack(&request, r#"{"event":"subscribe","result":"subscribed","symbol":"BTC/USDT"}"#).unwrap().is_ok());
+        assert!(venue.ack(&request, r#"{"event":"subscribe","result":"error","symbol":"BTC/USDT"}"#).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_okx() {
+        let instruments = create_instruments();
+        let mut venue = OkxVenue::default();
+        assert_eq!("BTC-USDT", OkxVenue::ticker_to_channel("btc/usdt"));
+
+        let raw = r#"{"arg":{"channel":"bbo-tbt","instId":"BTC-USDT"},"data":[{"asks":[["67321.1","0.41","0","5"]],"bids":[["67321","1.2","0","9"]],"ts":"1712345678901","seqId":3821}]}"#;
+        let ticker = venue.parse(1, raw, &instruments).unwrap().unwrap();
+        assert_eq!((Exchange::Okx, 3821), (ticker.instrument.exchange.clone(), ticker.update_id));
+        assert_eq!(1_712_345_678_901_000_000, ticker.exchange_ts);
+        assert_eq!((dec("67321"), dec("1.2"), dec("67321.1"), dec("0.41")), (ticker.bid, ticker.bid_amount, ticker.ask, ticker.ask_amount));
+        let raw = r#"{"arg":{"channel":"bbo-tbt","instId":"BTC-USDT"},"data":[{"asks":[],"bids":[["67320.5","0.3","0","1"]],"ts":"1"}]}"#;
+        // the empty side of the snapshot has no level
+        let ticker = venue.parse(1, raw, &instruments).unwrap().unwrap();
+        assert_eq!((Decimal::ZERO, Decimal::ZERO), (ticker.ask, ticker.ask_amount));
+        let subscribed = r#"{"event":"subscribe","arg":{"channel":"bbo-tbt","instId":"BTC-USDT"},"connId":"a4d3ae55"}"#;
+        assert!(venue.parse(1, subscribed, &instruments).unwrap().is_none());
+
+        let request = venue.subscribe_message(&["BTC-USDT".to_string(), "ETH-USDT".to_string()], 0);
+        assert_eq!("BTC-USDT", request["args"][0]["instId"]);
+        assert!(venue.is_pong("pong"));
+        assert!(venue.ack(&request, raw).is_none());
+        // done when every argument is acked
+        assert!(venue.ack(&request, subscribed).is_none());
+        assert!(venue.ack(&request, subscribed).is_none());
+        let eth_subscribed = r#"{"event":"subscribe","arg":{"channel":"bbo-tbt","instId":"ETH-USDT"},"connId":"a4d3ae55"}"#;
+        assert!(venue.ack(&request, eth_subscribed).unwrap().is_ok());
+
+        // the error of one argument fails the request, the unrelated one is skipped
+        let rejected = r#"{"event":"error","code":"60018","msg":"Wrong URL or channel:bbo-tbt,instId:ETH-USDT doesn't exist","connId":"a4d3ae55"}"#;
+        assert!(venue.ack(&request, subscribed).is_none());
+        assert!(venue.ack(&request, r#"{"event":"error","code":"60012","msg":"Invalid request","connId":"a4d3ae55"}"#).is_none());
+        assert!(venue.ack(&request, rejected).unwrap().is_err());
+        assert!(venue.ack(&request, subscribed).is_none());
+        assert!(venue.ack(&request, eth_subscribed).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_bybit() {
+        let instruments = create_instruments();
+        let mut venue = BybitVenue;
+        assert_eq!("orderbook.1.BTCUSDT", BybitVenue::ticker_to_channel("btc/usdt"));
+        assert_eq!("BTCUSDT", BybitVenue::channel_to_symbol("orderbook.1.BTCUSDT"));
+
+        let raw = r#"{"topic":"orderbook.1.BTCUSDT","ts":1712345678901,"type":"snapshot","data":{"s":"BTCUSDT","b":[["67310.01","0.52"]],"a":[["67310.02","1.07"]],"u":1829311,"seq":3412211},"cts":1712345678899}"#;
+        let ticker = venue.parse(1, raw, &instruments).unwrap().unwrap();
+        assert_eq!((Exchange::Bybit, 3412211), (ticker.instrument.exchange.clone(), ticker.update_id));
+        assert_eq!(1_712_345_678_899_000_000, ticker.exchange_ts);
+        assert_eq!((dec("67310.01"), dec("0.52"), dec("67310.02"), dec("1.07")), (ticker.bid, ticker.bid_amount, ticker.ask, ticker.ask_amount));
+        let raw = r#"{"topic":"orderbook.1.BTCUSDT","ts":1,"type":"delta","data":{"s":"BTCUSDT","b":[],"a":[["67310.5","0.1"]],"u":1829312}}"#;
+        assert_eq!(TICKER_PRICE_NOT_CHANGED, venue.parse(1, raw, &instruments).unwrap().unwrap().bid);
+
+        let request = venue.subscribe_message(&["orderbook.1.BTCUSDT".to_string()], 4);
+        let pong = r#"{"success":true,"ret_msg":"pong","conn_id":"0970e817","op":"ping"}"#;
+        assert!(venue.is_pong(pong));
+        assert!(venue.ack(&request, pong).is_none());
+        assert!(venue.ack(&request, r#"{"success":true,"ret_msg":"","conn_id":"0970e817","req_id":"3","op":"subscribe"}"#).is_none());
+        assert!(venue.ack(&request, r#"{"success":true,"ret_msg":"","conn_id":"0970e817","req_id":"4","op":"subscribe"}"#).unwrap().is_ok());
+        assert!(venue.ack(&request, r#"{"success":false,"ret_msg":"Invalid topic","conn_id":"0970e817","req_id":"4","op":"subscribe"}"#).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_kraken_checksum() {
+        let instruments = InstrumentsMap::from_instruments(vec![Instrument {
+            id: InstrumentId::default(),
+            exchange: Exchange::Kraken,
+            symbol: "BTC/USD".to_string(),
+            base: "BTC".to_string(),
+            quote: "USD".to_string(),
+            amount_precision: 8,
+            price_precision: 1,
+            order_amount_min: dec("0.0001"),
+            order_amount_max: dec("9000"),
+            order_notional_min: dec("0.5"),
+            order_notional_max: dec("9000000"),
+            maker_fee: 0.0025,
+            taker_fee: 0.004,
+        }]);
+        let mut venue = KrakenVenue::default();
+        assert!(venue.parse(1, r#"{"channel":"heartbeat"}"#, &instruments).unwrap().is_none());
+
+        let snapshot = r#"{"channel":"book","type":"snapshot","data":[{"symbol":"BTC/USD","bids":[{"price":45283.5,"qty":0.1},{"price":45283.4,"qty":1.54582015}],"asks":[{"price":45285.2,"qty":0.001},{"price":45286.4,"qty":1.54582015}],"checksum":2178325928}]}"#;
+        let ticker = venue.parse(1, snapshot, &instruments).unwrap().unwrap();
+        assert_eq!((dec("45283.5"), dec("0.1"), dec("45285.2"), dec("0.001")), (ticker.bid, ticker.bid_amount, ticker.ask, ticker.ask_amount));
+
+        let update = r#"{"channel":"book","type":"update","data":[{"symbol":"BTC/USD","bids":[{"price":45283.5,"qty":0},{"price":45284.0,"qty":0.5}],"asks":[],"checksum":2818128875,"timestamp":"2024-01-01T00:00:00.000000Z"}]}"#;
+        let ticker = venue.parse(2, update, &instruments).unwrap().unwrap();
+        assert_eq!((dec("45284"), dec("0.5")), (ticker.bid, ticker.bid_amount));
+        assert_eq!(1704067200000000000, ticker.exchange_ts);
+        assert!(venue.take_resubscribe().is_empty());
+
+        // the book is dropped on the mismatch, updates are ignored till the new snapshot
+        let corrupted = r#"{"channel":"book","type":"update","data":[{"symbol":"BTC/USD","bids":[{"price":45283.9,"qty":0.2}],"asks":[],"checksum":1}]}"#;
+        assert!(venue.parse(3, corrupted, &instruments).unwrap().is_none());
+        assert!(venue.book("BTC/USD").is_none());
+        assert_eq!(vec!["BTC/USD".to_string()], venue.take_resubscribe());
+        assert!(venue.parse(4, update, &instruments).unwrap().is_none());
+        assert!(venue.parse(5, snapshot, &instruments).unwrap().is_some());
+
+        let request = venue.subscribe_message(&["BTC/USD".to_string(), "ETH/USD".to_string()], 7);
+        let pong = r#"{"method":"pong","req_id":7,"time_in":"2024-01-01T00:00:00.000000Z","time_out":"2024-01-01T00:00:00.000001Z"}"#;
+        assert!(venue.is_pong(pong));
+        assert!(venue.ack(&request, pong).is_none());
+        // done when every symbol is acked
+        let subscribed = r#"{"method":"subscribe","result":{"channel":"book","depth":10,"snapshot":true,"symbol":"BTC/USD"},"success":true,"req_id":7}"#;
+        let eth_subscribed = r#"{"method":"subscribe","result":{"channel":"book","depth":10,"snapshot":true,"symbol":"ETH/USD"},"success":true,"req_id":7}"#;
+        assert!(venue.ack(&request, subscribed).is_none());
+        assert!(venue.ack(&request, subscribed).is_none());
+        assert!(venue.ack(&request, eth_subscribed).unwrap().is_ok());
+
+        // the rejected symbol fails the request
+        assert!(venue.ack(&request, subscribed).is_none());
+        assert!(venue.ack(&request, r#"{"error":"Currency pair not supported ETH/USD","method":"subscribe","success":false,"symbol":"ETH/USD","req_id":7}"#).unwrap().is_err());
+        assert!(venue.ack(&request, r#"{"error":"Currency pair not supported","method":"subscribe","success":false,"req_id":8}"#).is_none());
+    }
+
+    #[test]
+    fn test_sequence_gaps() {
+        let mut sequences = SequenceTracker::new();
+        assert_eq!(Sequence::Next, sequences.check("BTC/USDT", 10, true));
+        assert_eq!(Sequence::Next, sequences.check("BTC/USDT", 11, true));
+        assert_eq!(Sequence::Stale, sequences.check("BTC/USDT", 11, true));
+        assert_eq!(Sequence::Stale, sequences.check("BTC/USDT", 9, true));
+        assert_eq!(Sequence::Gap(2), sequences.check("BTC/USDT", 14, true));
+        assert_eq!(Sequence::Next, sequences.check("ETH/USDT", 1, true));
+
+        // ids which only grow
+        assert_eq!(Sequence::Next, sequences.check("btcusdt", 100, false));
+        assert_eq!(Sequence::Next, sequences.check("btcusdt", 150, false));
+        assert_eq!(Sequence::Stale, sequences.check("btcusdt", 120, false));
+
+        sequences.remove("BTC/USDT");
+        assert_eq!(Sequence::Next, sequences.check("BTC/USDT", 1, true));
+    }
+
+    #[test]
+    fn test_watchdog_silence() {
+        let sec = |secs: u128| secs * 1_000_000_000;
+        let mut watchdog = Watchdog::new(Duration::from_secs(60), Some(Duration::from_secs(600)));
+        // nothing is watched before the subscription
+        assert_eq!(None, watchdog.check(sec(1000)));
+
+        watchdog.reset(sec(1000), ["BTCUSDT", "ETHUSDT"].into_iter());
+        watchdog.watch(sec(1000), "XRPUSDT");
+        watchdog.unwatch("XRPUSDT");
+        for ts in (1030..=1630).step_by(30) {
+            watchdog.on_update(sec(ts), "BTCUSDT");
+        }
+        assert_eq!(Some(Silence::Instruments(vec!["ETHUSDT".to_string()])), watchdog.check(sec(1630)));
+        // reported once per threshold
+        assert_eq!(None, watchdog.check(sec(1640)));
+
+        assert_eq!(None, watchdog.check(sec(1690)));
+        assert_eq!(Some(Silence::Socket(Duration::from_secs(61))), watchdog.check(sec(1691)));
+
+        let mut watchdog = Watchdog::new(Duration::from_secs(60), None);
+        watchdog.reset(sec(1000), ["BTCUSDT", "ETHUSDT"].into_iter());
+        watchdog.on_update(sec(2000), "BTCUSDT");
+        assert_eq!(None, watchdog.check(sec(2001)));
+    }
+
+    #[test]
+    fn test_watchdog_binance_channels() {
+        let sec = |secs: u128| secs * 1_000_000_000;
+        let instruments = create_instruments();
+        let channels = vec!["btcusdt@bookTicker".to_string()];
+        let index = ChannelIndex::build::<BinanceVenue>(&channels, &instruments);
+        let mut watchdog = Watchdog::new(Duration::from_secs(60), Some(Duration::from_secs(600)));
+        watchdog.reset(sec(1000), channels.iter().map(String::as_str));
+
+        // the channel symbol is lowercase, the ticker has the instrument one
+        let raw = r#"{"u":400900217,"s":"BTCUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+        for ts in (1030..=1630).step_by(30) {
+            let ticker = BinanceVenue.parse(sec(ts), raw, &instruments).unwrap().unwrap();
+            let channel = index.channel(ticker.instrument.id).unwrap();
+            assert_eq!("btcusdt@bookTicker", channel);
+            watchdog.on_update(sec(ts), channel);
+        }
+        assert_eq!(None, watchdog.check(sec(1630)));
+    }
+
+    #[test]
+    fn test_channel_messages_binance() {
+        let instruments = create_instruments();
+        let channels = vec!["btcusdt@bookTicker".to_string()];
+        let shards = Shards::new(Exchange::Binance, channels.clone(), 1024);
+        let mut index = ChannelIndex::build::<BinanceVenue>(&channels, &instruments);
+
+        let raw = r#"{"u":400900217,"s":"BTCUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+        for _ in 0..3 {
+            let ticker = BinanceVenue.parse(1, raw, &instruments).unwrap().unwrap();
+            assert!(index.on_ticker(ticker.instrument.id).is_some());
+        }
+        shards.record_messages(0, index.take_messages());
+        assert_eq!(Some(&3), shards.get(0).messages.get("btcusdt@bookTicker"));
+        assert_eq!(3, shards.get(0).load());
+        assert!(index.take_messages().is_empty());
+
+        // the counts of the channel moved off are dropped
+        let ticker = BinanceVenue.parse(1, raw, &instruments).unwrap().unwrap();
+        index.on_ticker(ticker.instrument.id);
+        index.update::<BinanceVenue>(&[], &instruments);
+        assert!(index.on_ticker(ticker.instrument.id).is_none());
+        assert!(index.take_messages().is_empty());
+    }
+}
@@ -1,87 +1,83 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
     use std::sync::Arc;
     use untitled::core::dto::{Exchange, Instrument, OrderSide, PriceTicker};
+    use untitled::core::fees::FeeModel;
     use untitled::core::order_sizing::{max_chain_amount_quote};
+    use crate::common::{self, dec};
 
+    // the precisions are (amount, price), the limits are (min, max)
     fn create_instrument(
-        symbol: &str,
+        id: u32,
         base: &str,
         quote: &str,
-        amount_precision: usize,
-        price_precision: usize,
-        min_amount: f64,
-        max_amount: f64,
-        min_notional: f64,
-        max_notional: f64
+        (amount_precision, price_precision): (usize, usize),
+        (min_amount, max_amount): (&str, &str),
+        (min_notional, max_notional): (&str, &str)
     ) -> Arc<Instrument> {
         Arc::new(Instrument {
-            exchange: Exchange::Binance,
-            symbol: symbol.to_string(),
-            base: base.to_string(),
-            quote: quote.to_string(),
             amount_precision,
             price_precision,
-            order_amount_min: min_amount,
-            order_amount_max: max_amount,
-            order_notional_min: min_notional,
-            order_notional_max: max_notional,
+            order_amount_min: dec(min_amount),
+            order_amount_max: dec(max_amount),
+            order_notional_min: dec(min_notional),
+            order_notional_max: dec(max_notional),
             maker_fee: 0.0,
             taker_fee: 0.0,
+            ..common::create_instrument(id, Exchange::Binance, base, quote)
         })
     }
 
     fn create_ticker(
         instrument: Arc<Instrument>,
-        bid: f64,
-        ask: f64,
-        bid_amount: f64,
-        ask_amount: f64
+        bid: &str,
+        ask: &str,
+        bid_amount: &str,
+        ask_amount: &str
     ) -> PriceTicker {
         PriceTicker {
-            timestamp: 0,
-            instrument,
-            bid,
-            ask,
-            bid_amount,
-            ask_amount,
+            bid_amount: dec(bid_amount),
+            ask_amount: dec(ask_amount),
+            ..common::create_ticker(&instrument, bid, ask)
         }
     }
 
     #[test]
     fn test_complex_chain() {
         let btc_usdt = create_instrument(
-            "BTC/USDT", "BTC", "USDT",
-            6, 2,
-            0.001, 10.0,  // Increased max_amount to allow for chain calculations
-            10.0, 100000.0
+            1, "BTC", "USDT",
+            (6, 2),
+            ("0.001", "10.0"),  // Increased max_amount to allow for chain calculations
+            ("10.0", "100000.0")
         );
         let eth_btc = create_instrument(
-            "ETH/BTC", "ETH", "BTC",
-            6, 6,
-            0.001, 10.0,  // Increased max_amount
-            0.01, 100.0   // Increased max_notional
+            2, "ETH", "BTC",
+            (6, 6),
+            ("0.001", "10.0"),  // Increased max_amount
+            ("0.01", "100.0")   // Increased max_notional
         );
         let eth_usdt = create_instrument(
-            "ETH/USDT", "ETH", "USDT",
-            4, 2,
-            0.01, 100.0,
-            10.0, 100000.0
+            3, "ETH", "USDT",
+            (4, 2),
+            ("0.01", "100.0"),
+            ("10.0", "100000.0")
         );
 
         let mut tickers_map = HashMap::new();
         tickers_map.insert(
-            Arc::clone(&btc_usdt),
-            create_ticker(Arc::clone(&btc_usdt), 69680.0, 69681.0, 2.0, 4.0)
+            btc_usdt.id,
+            create_ticker(Arc::clone(&btc_usdt), "69680.0", "69681.0", "2.0", "4.0")
         );
         tickers_map.insert(
-            Arc::clone(&eth_btc),
-            create_ticker(Arc::clone(&eth_btc), 0.0359, 0.03591, 8., 7.0)
+            eth_btc.id,
+            create_ticker(Arc::clone(&eth_btc), "0.0359", "0.03591", "8.", "7.0")
         );
         tickers_map.insert(
-            Arc::clone(&eth_usdt),
-            create_ticker(Arc::clone(&eth_usdt), 2500.0, 2501.0, 10.0, 7.0)
+            eth_usdt.id,
+            create_ticker(Arc::clone(&eth_usdt), "2500.0", "2501.0", "10.0", "7.0")
         );
 
         let orders = vec![
@@ -90,45 +86,45 @@ mod tests {
             (Arc::clone(&eth_usdt), OrderSide::Sell),
         ];
 
-        let result = max_chain_amount_quote(&tickers_map, &orders);
+        let result = max_chain_amount_quote(&FeeModel::new(), &tickers_map, &orders);
         assert!(result.is_some());
         let size = result.unwrap();
-        assert_eq!(17515.71297, size)
+        assert_eq!(dec("17515.71297"), size)
     }
 
     #[test]
     fn test_complex_chain2() {
         let btc_usdt = create_instrument(
-            "BTC/USDT", "BTC", "USDT",
-            6, 2,
-            0.001, 10.0,  // Increased max_amount to allow for chain calculations
-            10.0, 100000.0
+            1, "BTC", "USDT",
+            (6, 2),
+            ("0.001", "10.0"),  // Increased max_amount to allow for chain calculations
+            ("10.0", "100000.0")
         );
         let btc_try = create_instrument(
-            "BTC/TRY", "BTC", "TRY",
-            6, 6,
-            0.001, 10.0,  // Increased max_amount
-            0.01, 100.0   // Increased max_notional
+            2, "BTC", "TRY",
+            (6, 6),
+            ("0.001", "10.0"),  // Increased max_amount
+            ("0.01", "100.0")   // Increased max_notional
         );
         let usdt_try = create_instrument(
-            "USDT/TRY", "USDT", "TRY",
-            4, 2,
-            0.01, 100.0,
-            10.0, 100000.0
+            3, "USDT", "TRY",
+            (4, 2),
+            ("0.01", "100.0"),
+            ("10.0", "100000.0")
         );
 
         let mut tickers_map = HashMap::new();
         tickers_map.insert(
-            Arc::clone(&btc_usdt),
-            create_ticker(Arc::clone(&btc_usdt), 69680.0, 69681.0, 2.0, 4.0)
+            btc_usdt.id,
+            create_ticker(Arc::clone(&btc_usdt), "69680.0", "69681.0", "2.0", "4.0")
         );
         tickers_map.insert(
-            Arc::clone(&btc_try),
-            create_ticker(Arc::clone(&btc_try), 2403820., 2403845., 0.05, 0.02)
+            btc_try.id,
+            create_ticker(Arc::clone(&btc_try), "2403820.", "2403845.", "0.05", "0.02")
         );
         tickers_map.insert(
-            Arc::clone(&usdt_try),
-            create_ticker(Arc::clone(&usdt_try), 34.57, 34.58, 100000., 500000.)
+            usdt_try.id,
+            create_ticker(Arc::clone(&usdt_try), "34.57", "34.58", "100000.", "500000.")
         );
 
         let orders = vec![
@@ -137,9 +133,9 @@ mod tests {
             (Arc::clone(&usdt_try), OrderSide::Buy),
         ];
 
-        let result = max_chain_amount_quote(&tickers_map, &orders);
+        let result = max_chain_amount_quote(&FeeModel::new(), &tickers_map, &orders);
         assert!(result.is_some());
         let size = result.unwrap();
-        assert_eq!(3484.05, size)
+        assert_eq!(dec("3484.05"), size)
     }
 }